@@ -1,5 +1,6 @@
 
-#[cfg(target_arch = "x86")]
+use crate::io::{AddressSpace, Result};
+
 pub mod x86;
 
 pub fn read_byte(addr: usize) -> u32 {
@@ -10,6 +11,11 @@ pub fn read_pointer(addr: usize) -> usize {
     unsafe { std::ptr::read_unaligned(addr as *const usize) }
 }
 
+/// Size of the 32-bit x86 instruction at `va`, read through `space` instead of a raw pointer.
+pub fn get_opcode_size_at<A: AddressSpace + ?Sized>(space: &mut A, va: u64) -> Result<usize> {
+    x86::get_opcode_size_32_at(space, va)
+}
+
 pub fn get_opcode_size(buffer: &[u8]) -> usize {
     #[cfg(target_arch = "x86")]
     {
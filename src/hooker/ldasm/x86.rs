@@ -1,3 +1,6 @@
+use std::convert::Infallible;
+
+use crate::io::{AddressSpace, Result};
 use super::read_byte;
 
 // const OP_X86_NONE: u32          = 0x00;
@@ -35,9 +38,21 @@ fn get_opcode_flags_32(opcode: u32) -> u32 {
 }
 
 pub fn get_opcode_size_32(buffer: &[u8]) -> usize {
-    let mut code = buffer.as_ptr() as usize;
+    let code = buffer.as_ptr() as usize;
     // let code_end = code + buffer.len();
 
+    let Ok(size) = decode_opcode_size_32(|offset| Ok::<_, Infallible>(read_byte(code + offset)));
+    size
+}
+
+/// Decodes the instruction at `va` of any [`AddressSpace`], such as a dump or an image on disk.
+pub fn get_opcode_size_32_at<A: AddressSpace + ?Sized>(space: &mut A, va: u64) -> Result<usize> {
+    decode_opcode_size_32(|offset| space.read_u8(va + offset as u64).map(u32::from))
+}
+
+fn decode_opcode_size_32<E>(mut fetch: impl FnMut(usize) -> std::result::Result<u32, E>) -> std::result::Result<usize, E> {
+    let mut code: usize = 0;
+
     let mut op1: u32;
     let op2: u32;
     let mut pfx66: u32 = 0;
@@ -45,7 +60,7 @@ pub fn get_opcode_size_32(buffer: &[u8]) -> usize {
     let mut osize: usize = 0;
     let mut oflen: usize = 0;
 
-    op1 = read_byte(code);
+    op1 = fetch(code)?;
 
     while (get_opcode_flags_32(op1) & OP_X86_PREFIX) != 0 {
         match op1 {
@@ -56,14 +71,14 @@ pub fn get_opcode_size_32(buffer: &[u8]) -> usize {
 
         code += 1;
         osize += 1;
-        op1 = read_byte(code);
+        op1 = fetch(code)?;
     }
 
     code += 1;
     osize += 1;
 
     if op1 == 0x0F {
-        op2 = read_byte(code) | OP_X86_EXTENDED;
+        op2 = fetch(code)? | OP_X86_EXTENDED;
         code += 1;
         osize += 1;
 
@@ -71,7 +86,7 @@ pub fn get_opcode_size_32(buffer: &[u8]) -> usize {
         op2 = op1;
 
         /* pfx66 = pfx67 for opcodes A0 - A3 */
-        if op2 >= 0xA0 && op2 <= 0xA3 {
+        if (0xA0..=0xA3).contains(&op2) {
             pfx66 = pfx67;
         }
     }
@@ -83,7 +98,7 @@ pub fn get_opcode_size_32(buffer: &[u8]) -> usize {
     let i_reg: u32;
 
     if flags & OP_X86_MODRM != 0 {
-        let v = read_byte(code);
+        let v = fetch(code)?;
         code += 1;
         osize += 1;
 
@@ -122,7 +137,7 @@ pub fn get_opcode_size_32(buffer: &[u8]) -> usize {
         }
 
         if pfx67 == 0  && i_rm == 4 && i_mod != 3 {
-            if (read_byte(code) & 7) == 5 && i_mod != 1 {
+            if (fetch(code)? & 7) == 5 && i_mod != 1 {
                 oflen = 4;
             }
 
@@ -136,5 +151,5 @@ pub fn get_opcode_size_32(buffer: &[u8]) -> usize {
         osize += 4 - ((pfx66 as usize) << 1);
     }
 
-    osize + ((flags & 7) as usize)
+    Ok(osize + ((flags & 7) as usize))
 }
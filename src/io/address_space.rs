@@ -0,0 +1,544 @@
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use super::byteorder::{ByteOrder, LittleEndian};
+use super::elfcore::{read_ranges, ElfCore, ElfHeader, MemoryRange, PF_R, PF_W, PF_X};
+use super::error::Result;
+use super::minidump::Minidump;
+use super::read::ReadExt;
+
+const IMAGE_DOS_SIGNATURE: u16  = 0x5A4D;
+const IMAGE_NT_SIGNATURE: u32   = 0x0000_4550;
+const IMAGE_NT_OPTIONAL_HDR64_MAGIC: u16 = 0x20B;
+
+const IMAGE_SCN_MEM_EXECUTE: u32    = 0x2000_0000;
+const IMAGE_SCN_MEM_READ: u32       = 0x4000_0000;
+const IMAGE_SCN_MEM_WRITE: u32      = 0x8000_0000;
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn unmapped(va: u64) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("address {va:#x} is not mapped"))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Protection {
+    pub read    : bool,
+    pub write   : bool,
+    pub execute : bool,
+}
+
+impl Protection {
+    pub const NONE: Self = Self { read: false, write: false, execute: false };
+    pub const READ: Self = Self { read: true, write: false, execute: false };
+
+    /// From Windows `PAGE_*` protection flags; guard pages count as inaccessible.
+    #[cfg(windows)]
+    pub const fn from_page_flags(flags: u32) -> Self {
+        use windows_sys::Win32::System::Memory::{
+            PAGE_EXECUTE, PAGE_EXECUTE_READ, PAGE_EXECUTE_READWRITE, PAGE_EXECUTE_WRITECOPY, PAGE_GUARD, PAGE_READONLY,
+            PAGE_READWRITE, PAGE_WRITECOPY,
+        };
+
+        if flags & PAGE_GUARD != 0 {
+            return Self::NONE;
+        }
+
+        let flags = flags & 0xFF;
+
+        Self {
+            read    : flags & (PAGE_READONLY | PAGE_READWRITE | PAGE_WRITECOPY | PAGE_EXECUTE_READ | PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY) != 0,
+            write   : flags & (PAGE_READWRITE | PAGE_WRITECOPY | PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY) != 0,
+            execute : flags & (PAGE_EXECUTE | PAGE_EXECUTE_READ | PAGE_EXECUTE_READWRITE | PAGE_EXECUTE_WRITECOPY) != 0,
+        }
+    }
+
+    /// From ELF `PF_*` segment flags.
+    pub const fn from_elf_flags(flags: u32) -> Self {
+        Self {
+            read    : flags & PF_R != 0,
+            write   : flags & PF_W != 0,
+            execute : flags & PF_X != 0,
+        }
+    }
+}
+
+impl fmt::Display for Protection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}{}",
+            if self.read { 'r' } else { '-' },
+            if self.write { 'w' } else { '-' },
+            if self.execute { 'x' } else { '-' },
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub start       : u64,
+    pub size        : u64,
+    pub protection  : Protection,
+}
+
+impl Region {
+    /// Saturates like [`MemoryRange::end`] for regions a corrupt dump places at the top of the address space.
+    pub const fn end(&self) -> u64 {
+        self.start.saturating_add(self.size)
+    }
+
+    pub const fn contains(&self, va: u64) -> bool {
+        va >= self.start && va - self.start < self.size
+    }
+}
+
+impl From<&MemoryRange> for Region {
+    fn from(r: &MemoryRange) -> Self {
+        Self {
+            start       : r.va,
+            size        : r.size,
+            protection  : Protection::from_elf_flags(r.flags),
+        }
+    }
+}
+
+/// Virtual memory that can be read by address, whether it belongs to a live
+/// process, a dump or an image on disk.
+///
+/// # Errors
+///
+/// `read_at` fails with [`io::ErrorKind::NotFound`] when part of the range is not mapped.
+pub trait AddressSpace {
+    fn read_at(&mut self, va: u64, buf: &mut [u8]) -> Result<()>;
+
+    /// Mapped regions sorted by address.
+    fn regions(&self) -> Vec<Region>;
+
+    fn protection(&self, va: u64) -> Option<Protection> {
+        self.regions().iter().find(|r| r.contains(va)).map(|r| r.protection)
+    }
+
+    fn read_vec(&mut self, va: u64, len: usize) -> Result<Vec<u8>> {
+        let mut buf = vec![0u8; len];
+        self.read_at(va, &mut buf)?;
+        Ok(buf)
+    }
+
+    fn read_u8(&mut self, va: u64) -> Result<u8> {
+        let mut buf = [0u8; 1];
+        self.read_at(va, &mut buf)?;
+        Ok(buf[0])
+    }
+
+    /// Reads a 4 or 8-byte pointer, counterpart of `hooker::ldasm::read_pointer`.
+    fn read_pointer<T: ByteOrder>(&mut self, va: u64, is_64: bool) -> Result<u64> where Self: Sized {
+        let mut buf = [0u8; 8];
+        let size = if is_64 { 8 } else { 4 };
+        self.read_at(va, &mut buf[..size])?;
+
+        if is_64 { T::read_u64(&buf[..]) } else { T::read_u32(&buf[..4]).map(u64::from) }
+    }
+
+    /// A [`Read`] cursor starting at `va`, so [`ReadExt`] methods work on the address space.
+    fn reader_at(&mut self, va: u64) -> SpaceReader<'_, Self> where Self: Sized {
+        SpaceReader { space: self, va }
+    }
+}
+
+/// Sequential reads from an [`AddressSpace`].
+pub struct SpaceReader<'a, A> {
+    space   : &'a mut A,
+    va      : u64,
+}
+
+impl<A> SpaceReader<'_, A> {
+    pub const fn position(&self) -> u64 {
+        self.va
+    }
+}
+
+impl<A: AddressSpace> Read for SpaceReader<'_, A> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let next = self.va.checked_add(buf.len() as u64).ok_or_else(|| unmapped(self.va))?;
+        self.space.read_at(self.va, buf)?;
+        self.va = next;
+        Ok(buf.len())
+    }
+}
+
+/// A byte buffer mapped at `base`.
+pub struct BufferSpace<T> {
+    data        : T,
+    base        : u64,
+    protection  : Protection,
+}
+
+impl<T: AsRef<[u8]>> BufferSpace<T> {
+    pub const fn new(data: T, base: u64) -> Self {
+        Self {
+            data,
+            base,
+            protection: Protection::READ,
+        }
+    }
+
+    pub const fn with_protection(mut self, protection: Protection) -> Self {
+        self.protection = protection;
+        self
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+impl<T: AsRef<[u8]>> AddressSpace for BufferSpace<T> {
+    fn read_at(&mut self, va: u64, buf: &mut [u8]) -> Result<()> {
+        let data = self.data.as_ref();
+        let start = va.checked_sub(self.base).and_then(|n| usize::try_from(n).ok()).ok_or_else(|| unmapped(va))?;
+        let end = start.checked_add(buf.len()).ok_or_else(|| unmapped(va))?;
+        let src = data.get(start..end).ok_or_else(|| unmapped(va))?;
+        buf.copy_from_slice(src);
+        Ok(())
+    }
+
+    fn regions(&self) -> Vec<Region> {
+        vec![Region { start: self.base, size: self.data.as_ref().len() as u64, protection: self.protection }]
+    }
+}
+
+/// The address space of the running process.
+///
+/// On Linux reads go through `/proc/self/mem` and regions come from
+/// `/proc/self/maps`. On Windows reads go through `ReadProcessMemory` and
+/// regions come from `VirtualQuery`. Either way unmapped or guard pages fail
+/// with an error instead of faulting. Other platforms have no such interface,
+/// so creating one fails with [`io::ErrorKind::Unsupported`].
+pub struct CurrentProcess {
+    regions : Vec<Region>,
+
+    #[cfg(target_os = "linux")]
+    mem     : std::fs::File,
+}
+
+impl CurrentProcess {
+    pub fn new() -> Result<Self> {
+        let mut process = Self {
+            regions: Vec::new(),
+
+            #[cfg(target_os = "linux")]
+            mem: std::fs::File::open("/proc/self/mem")?,
+        };
+
+        process.refresh()?;
+        Ok(process)
+    }
+
+    /// Reloads the region list, which is a snapshot taken at creation.
+    pub fn refresh(&mut self) -> Result<()> {
+        self.regions = Self::query_regions()?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn query_regions() -> Result<Vec<Region>> {
+        Ok(super::process::read_maps("/proc/self/maps")?.iter().map(|m| m.region).collect())
+    }
+
+    #[cfg(windows)]
+    fn query_regions() -> Result<Vec<Region>> {
+        use std::mem::{size_of, MaybeUninit};
+        use windows_sys::Win32::System::Memory::{VirtualQuery, MEMORY_BASIC_INFORMATION, MEM_COMMIT};
+
+        let mut regions = Vec::new();
+        let mut va = 0usize;
+
+        loop {
+            let mut info = MaybeUninit::<MEMORY_BASIC_INFORMATION>::uninit();
+
+            // SAFETY: VirtualQuery only fills `info`, and returns 0 past the highest user address
+            let written = unsafe { VirtualQuery(va as *const _, info.as_mut_ptr(), size_of::<MEMORY_BASIC_INFORMATION>()) };
+            if written == 0 {
+                break;
+            }
+
+            // SAFETY: a non-zero return means the whole structure was written
+            let info = unsafe { info.assume_init() };
+
+            if info.State == MEM_COMMIT {
+                regions.push(Region {
+                    start       : info.BaseAddress as u64,
+                    size        : info.RegionSize as u64,
+                    protection  : Protection::from_page_flags(info.Protect),
+                });
+            }
+
+            match (info.BaseAddress as usize).checked_add(info.RegionSize) {
+                Some(next) if next > va => va = next,
+                _ => break,
+            }
+        }
+
+        Ok(regions)
+    }
+
+    #[cfg(not(any(target_os = "linux", windows)))]
+    fn query_regions() -> Result<Vec<Region>> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "listing the regions of the current process is not supported on this platform"))
+    }
+}
+
+impl AddressSpace for CurrentProcess {
+    #[cfg(target_os = "linux")]
+    fn read_at(&mut self, va: u64, buf: &mut [u8]) -> Result<()> {
+        use std::os::unix::fs::FileExt;
+
+        self.mem.read_exact_at(buf, va).map_err(|_| unmapped(va))
+    }
+
+    #[cfg(windows)]
+    fn read_at(&mut self, va: u64, buf: &mut [u8]) -> Result<()> {
+        use windows_sys::Win32::System::Diagnostics::Debug::ReadProcessMemory;
+        use windows_sys::Win32::System::Threading::GetCurrentProcess;
+
+        let address = usize::try_from(va).map_err(|_| unmapped(va))?;
+        let mut read = 0usize;
+
+        // SAFETY: ReadProcessMemory checks the source range and fails instead of faulting,
+        // and writes at most `buf.len()` bytes to `buf`
+        let ok = unsafe { ReadProcessMemory(GetCurrentProcess(), address as *const _, buf.as_mut_ptr().cast(), buf.len(), &mut read) };
+
+        if ok == 0 || read != buf.len() {
+            return Err(unmapped(va));
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(any(target_os = "linux", windows)))]
+    fn read_at(&mut self, _va: u64, _buf: &mut [u8]) -> Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "reading the current process is not supported on this platform"))
+    }
+
+    fn regions(&self) -> Vec<Region> {
+        self.regions.clone()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Pe,
+    Elf,
+}
+
+/// A PE or ELF file on disk, laid out the way the loader would map it.
+///
+/// PE images are mapped by their section table at `ImageBase`, ELF files by
+/// their `PT_LOAD` segments. Use [`MappedImage::rebase`] to match the address
+/// the module was actually loaded at.
+pub struct MappedImage<R> {
+    reader  : R,
+    format  : ImageFormat,
+    base    : u64,
+    ranges  : Vec<MemoryRange>,
+}
+
+impl<R: Read + Seek> MappedImage<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        reader.seek(SeekFrom::Start(0))?;
+        let magic: [u8; 4] = ReadExt::read_array(&mut reader)?;
+
+        let (format, base, mut ranges) = if &magic == b"\x7FELF" {
+            let (base, ranges) = Self::map_elf(&mut reader)?;
+            (ImageFormat::Elf, base, ranges)
+        } else if LittleEndian::read_u16(&magic[..2])? == IMAGE_DOS_SIGNATURE {
+            let (base, ranges) = Self::map_pe(&mut reader)?;
+            (ImageFormat::Pe, base, ranges)
+        } else {
+            return Err(invalid_data("not a PE or ELF image"));
+        };
+
+        ranges.sort_by_key(|r| r.va);
+        Ok(Self { reader, format, base, ranges })
+    }
+
+    fn map_elf(reader: &mut R) -> Result<(u64, Vec<MemoryRange>)> {
+        let header = ElfHeader::read(reader)?;
+
+        let ranges: Vec<_> = header.program_headers(reader)?
+            .iter()
+            .filter(|ph| ph.is_load())
+            .map(|ph| ph.range())
+            .collect();
+
+        if ranges.is_empty() {
+            return Err(invalid_data("ELF file has no loadable segments"));
+        }
+
+        let base = ranges.iter().map(|r| r.va & !0xFFF).min().unwrap_or(0);
+        Ok((base, ranges))
+    }
+
+    fn map_pe(reader: &mut R) -> Result<(u64, Vec<MemoryRange>)> {
+        reader.seek(SeekFrom::Start(0x3C))?;
+        let nt_offset = reader.read_u32::<LittleEndian>()? as u64;
+
+        reader.seek(SeekFrom::Start(nt_offset))?;
+        if reader.read_u32::<LittleEndian>()? != IMAGE_NT_SIGNATURE {
+            return Err(invalid_data("invalid PE signature"));
+        }
+
+        let _machine            = reader.read_u16::<LittleEndian>()?;
+        let num_sections        = reader.read_u16::<LittleEndian>()?;
+        let _timestamp          = reader.read_u32::<LittleEndian>()?;
+        let _symbol_table       = reader.read_u32::<LittleEndian>()?;
+        let _num_symbols        = reader.read_u32::<LittleEndian>()?;
+        let optional_size       = reader.read_u16::<LittleEndian>()? as u64;
+        let _characteristics    = reader.read_u16::<LittleEndian>()?;
+
+        let optional_offset = nt_offset + 24;
+        let optional = reader.read_bytes(optional_size as usize)?;
+        let magic = LittleEndian::read_u16(optional.as_slice())?;
+        let field = |offset: usize| optional.get(offset..).ok_or_else(|| invalid_data("truncated optional header"));
+
+        let image_base = if magic == IMAGE_NT_OPTIONAL_HDR64_MAGIC {
+            field(24)?.read_u64::<LittleEndian>()?
+        } else {
+            field(28)?.read_u32::<LittleEndian>()? as u64
+        };
+
+        let section_alignment = (field(32)?.read_u32::<LittleEndian>()? as u64).max(1);
+        let size_of_headers = field(60)?.read_u32::<LittleEndian>()? as u64;
+        let align = |n: u64| n.div_ceil(section_alignment) * section_alignment;
+
+        let mut ranges = vec![MemoryRange {
+            va          : image_base,
+            size        : align(size_of_headers),
+            offset      : 0,
+            file_size   : size_of_headers,
+            flags       : PF_R,
+        }];
+
+        reader.seek(SeekFrom::Start(optional_offset + optional_size))?;
+
+        for _ in 0..num_sections {
+            let section = reader.read_bytes(40)?;
+            let mut s = &section[8..];
+
+            let virtual_size    = s.read_u32::<LittleEndian>()? as u64;
+            let virtual_address = s.read_u32::<LittleEndian>()? as u64;
+            let raw_size        = s.read_u32::<LittleEndian>()? as u64;
+            let raw_offset      = s.read_u32::<LittleEndian>()? as u64;
+            let characteristics = LittleEndian::read_u32(&section[36..])?;
+
+            let size = align(if virtual_size == 0 { raw_size } else { virtual_size });
+            if size == 0 {
+                continue;
+            }
+
+            let mut flags = 0;
+            if characteristics & IMAGE_SCN_MEM_READ != 0 { flags |= PF_R; }
+            if characteristics & IMAGE_SCN_MEM_WRITE != 0 { flags |= PF_W; }
+            if characteristics & IMAGE_SCN_MEM_EXECUTE != 0 { flags |= PF_X; }
+
+            let va = image_base.checked_add(virtual_address).ok_or_else(|| invalid_data(format!("section at RVA {virtual_address:#x} overflows the address space")))?;
+
+            ranges.push(MemoryRange {
+                va,
+                size,
+                offset      : raw_offset,
+                file_size   : raw_size.min(size),
+                flags,
+            });
+        }
+
+        Ok((image_base, ranges))
+    }
+}
+
+impl<R> MappedImage<R> {
+    pub const fn format(&self) -> ImageFormat {
+        self.format
+    }
+
+    /// The preferred load address, or the one set by [`MappedImage::rebase`].
+    pub const fn base(&self) -> u64 {
+        self.base
+    }
+
+    /// Moves every range by the same delta so the image starts at `base`.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if a range would end up past the top of the address space.
+    pub fn rebase(&mut self, base: u64) -> Result<()> {
+        // every range starts at or above the current base, see `map_elf` and `map_pe`
+        let rebased = self.ranges.iter().map(|r| (r.va - self.base).checked_add(base)).collect::<Option<Vec<_>>>();
+        let rebased = rebased.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("image does not fit in the address space at {base:#x}")))?;
+
+        for (range, va) in self.ranges.iter_mut().zip(rebased) {
+            range.va = va;
+        }
+
+        self.base = base;
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: Read + Seek> AddressSpace for MappedImage<R> {
+    fn read_at(&mut self, va: u64, buf: &mut [u8]) -> Result<()> {
+        read_ranges(&mut self.reader, &self.ranges, va, buf)
+    }
+
+    fn regions(&self) -> Vec<Region> {
+        self.ranges.iter().map(Region::from).collect()
+    }
+}
+
+impl<R: Read + Seek> AddressSpace for ElfCore<R> {
+    fn read_at(&mut self, va: u64, buf: &mut [u8]) -> Result<()> {
+        ElfCore::read_at(self, va, buf)
+    }
+
+    fn regions(&self) -> Vec<Region> {
+        self.ranges().iter().map(Region::from).collect()
+    }
+}
+
+impl<R: Read + Seek> AddressSpace for Minidump<R> {
+    fn read_at(&mut self, va: u64, buf: &mut [u8]) -> Result<()> {
+        Minidump::read_at(self, va, buf)
+    }
+
+    fn regions(&self) -> Vec<Region> {
+        self.ranges().iter().map(Region::from).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_at_top_of_address_space() {
+        let region = Region { start: u64::MAX - 1, size: 16, protection: Protection::READ };
+        assert_eq!(region.end(), u64::MAX);
+        assert!(region.contains(u64::MAX));
+        assert!(!region.contains(u64::MAX - 2));
+    }
+
+    #[test]
+    fn buffer_reads_past_top_are_unmapped() {
+        let mut space = BufferSpace::new([1u8, 2, 3, 4], u64::MAX - 3);
+        assert_eq!(space.read_u8(u64::MAX).unwrap(), 4);
+
+        let mut buf = [0u8; 2];
+        assert_eq!(space.read_at(u64::MAX, &mut buf).unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(space.reader_at(u64::MAX).read(&mut buf).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,136 @@
+//! Async counterparts of [`ReadExt`](super::ReadExt) for `tokio` and `futures-io` readers.
+//!
+//! The returned futures only collect the exact number of bytes a value needs; decoding
+//! is done by the same [`ByteOrder`](super::ByteOrder) code the blocking readers use.
+
+use std::io;
+use std::task::Poll;
+use super::error::Result;
+use super::byteorder::MAX_NUMBER_BYTES;
+
+#[cfg(feature = "tokio")]
+pub mod tokio;
+
+#[cfg(feature = "futures-io")]
+pub mod futures;
+
+/// State shared by the read futures: a buffer being filled and the decoder applied once it is full.
+pub(crate) struct Fill<B, T> {
+    buf     : Option<B>,
+    len     : usize,
+    filled  : usize,
+    decode  : fn(B) -> Result<T>,
+}
+
+impl<T> Fill<[u8; MAX_NUMBER_BYTES], T> {
+    pub(crate) fn number(len: usize, decode: fn([u8; MAX_NUMBER_BYTES]) -> Result<T>) -> Self {
+        Self { buf: Some([0; MAX_NUMBER_BYTES]), len, filled: 0, decode }
+    }
+}
+
+impl<const N: usize> Fill<[u8; N], [u8; N]> {
+    pub(crate) fn array() -> Self {
+        Self { buf: Some([0; N]), len: N, filled: 0, decode: Ok }
+    }
+}
+
+impl Fill<Vec<u8>, Vec<u8>> {
+    pub(crate) fn bytes(size: usize) -> Self {
+        Self { buf: Some(vec![0; size]), len: size, filled: 0, decode: Ok }
+    }
+}
+
+impl<B: AsMut<[u8]>, T> Fill<B, T> {
+    /// Drives `read` until the buffer is full. `read` returns the number of bytes it placed
+    /// at the start of the given slice, 0 meaning end of stream.
+    pub(crate) fn poll<F>(&mut self, mut read: F) -> Poll<Result<T>>
+    where
+        F: FnMut(&mut [u8]) -> Poll<io::Result<usize>>,
+    {
+        loop {
+            let buf = self.buf.as_mut().expect("read future polled after completion");
+
+            if self.filled == self.len {
+                let buf = self.buf.take().unwrap();
+                return Poll::Ready((self.decode)(buf));
+            }
+
+            let n = match read(&mut buf.as_mut()[self.filled..self.len]) {
+                Poll::Ready(Ok(n)) => n,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            if n == 0 {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            }
+
+            self.filled += n;
+        }
+    }
+}
+
+/// Declares the typed read methods of an async extension trait.
+/// `$future` is the backend's future type.
+macro_rules! async_read_methods {
+    ($future:ident) => {
+        fn read_array<const N: usize>(&mut self) -> $future<'_, Self, [u8; N], [u8; N]> {
+            $future::new(self, Fill::array())
+        }
+
+        fn read_bytes(&mut self, size: usize) -> $future<'_, Self, Vec<u8>, Vec<u8>> {
+            $future::new(self, Fill::bytes(size))
+        }
+
+        fn read_i8(&mut self) -> ReadNumber<'_, Self, i8> {
+            $future::new(self, Fill::number(1, |b| LittleEndian::read_i8(&b[..1])))
+        }
+
+        fn read_u8(&mut self) -> ReadNumber<'_, Self, u8> {
+            $future::new(self, Fill::number(1, |b| LittleEndian::read_u8(&b[..1])))
+        }
+
+        fn read_i16<E: ByteOrder>(&mut self) -> ReadNumber<'_, Self, i16> {
+            $future::new(self, Fill::number(2, |b| E::read_i16(&b[..2])))
+        }
+
+        fn read_u16<E: ByteOrder>(&mut self) -> ReadNumber<'_, Self, u16> {
+            $future::new(self, Fill::number(2, |b| E::read_u16(&b[..2])))
+        }
+
+        fn read_i32<E: ByteOrder>(&mut self) -> ReadNumber<'_, Self, i32> {
+            $future::new(self, Fill::number(4, |b| E::read_i32(&b[..4])))
+        }
+
+        fn read_u32<E: ByteOrder>(&mut self) -> ReadNumber<'_, Self, u32> {
+            $future::new(self, Fill::number(4, |b| E::read_u32(&b[..4])))
+        }
+
+        fn read_i64<E: ByteOrder>(&mut self) -> ReadNumber<'_, Self, i64> {
+            $future::new(self, Fill::number(8, |b| E::read_i64(&b[..8])))
+        }
+
+        fn read_u64<E: ByteOrder>(&mut self) -> ReadNumber<'_, Self, u64> {
+            $future::new(self, Fill::number(8, |b| E::read_u64(&b[..8])))
+        }
+
+        fn read_i128<E: ByteOrder>(&mut self) -> ReadNumber<'_, Self, i128> {
+            $future::new(self, Fill::number(16, |b| E::read_i128(&b[..16])))
+        }
+
+        fn read_u128<E: ByteOrder>(&mut self) -> ReadNumber<'_, Self, u128> {
+            $future::new(self, Fill::number(16, |b| E::read_u128(&b[..16])))
+        }
+
+        fn read_f32<E: ByteOrder>(&mut self) -> ReadNumber<'_, Self, f32> {
+            $future::new(self, Fill::number(4, |b| E::read_f32(&b[..4])))
+        }
+
+        fn read_f64<E: ByteOrder>(&mut self) -> ReadNumber<'_, Self, f64> {
+            $future::new(self, Fill::number(8, |b| E::read_f64(&b[..8])))
+        }
+    };
+}
+
+#[allow(unused_imports)]
+pub(crate) use async_read_methods;
@@ -0,0 +1,46 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::io::byteorder::{ByteOrder, LittleEndian, MAX_NUMBER_BYTES};
+use crate::io::error::Result;
+use super::{async_read_methods, Fill};
+
+/// Future returned by the [`AsyncReadExt`] methods.
+pub struct ReadFuture<'a, R: ?Sized, B, T> {
+    reader  : &'a mut R,
+    fill    : Fill<B, T>,
+}
+
+pub type ReadNumber<'a, R, T> = ReadFuture<'a, R, [u8; MAX_NUMBER_BYTES], T>;
+
+impl<'a, R: ?Sized, B, T> ReadFuture<'a, R, B, T> {
+    fn new(reader: &'a mut R, fill: Fill<B, T>) -> Self {
+        Self { reader, fill }
+    }
+}
+
+impl<R: AsyncRead + Unpin + ?Sized, B: AsMut<[u8]> + Unpin, T> Future for ReadFuture<'_, R, B, T> {
+    type Output = Result<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        let reader = &mut *this.reader;
+
+        this.fill.poll(|buf| {
+            let mut rb = ReadBuf::new(buf);
+            Pin::new(&mut *reader).poll_read(cx, &mut rb).map_ok(|()| rb.filled().len())
+        })
+    }
+}
+
+/// Extends [`tokio::io::AsyncRead`] with methods for reading numbers.
+///
+/// Mirrors [`ReadExt`](crate::io::ReadExt): `reader.read_u32::<BigEndian>().await`.
+/// The method names overlap with `tokio::io::AsyncReadExt`, so import only one of them in a scope.
+pub trait AsyncReadExt: AsyncRead + Unpin {
+    async_read_methods!(ReadFuture);
+}
+
+impl<R: AsyncRead + Unpin + ?Sized> AsyncReadExt for R {}
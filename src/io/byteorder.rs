@@ -1,12 +1,14 @@
-use std::io;
-use super::file::Result;
+use super::error::Result;
+use super::stream::{Read, Write};
 
-const MAX_NUMBER_BYTES: usize = 16;
+pub(crate) const MAX_NUMBER_BYTES: usize = 16;
 trait Number {
     type Output;
     const SIZE: usize = 0;
     fn from_le_bytes(bytes: &[u8; MAX_NUMBER_BYTES]) -> Self::Output;
     fn from_be_bytes(bytes: &[u8; MAX_NUMBER_BYTES]) -> Self::Output;
+    fn to_le_bytes(v: Self::Output, bytes: &mut [u8; MAX_NUMBER_BYTES]);
+    fn to_be_bytes(v: Self::Output, bytes: &mut [u8; MAX_NUMBER_BYTES]);
 }
 
 impl Number for u8 {
@@ -19,6 +21,14 @@ impl Number for u8 {
     fn from_be_bytes(bytes: &[u8; MAX_NUMBER_BYTES]) -> u8 {
         u8::from_be_bytes([bytes[0]])
     }
+
+    fn to_le_bytes(v: u8, bytes: &mut [u8; MAX_NUMBER_BYTES]) {
+        bytes[..Self::SIZE].copy_from_slice(&v.to_le_bytes());
+    }
+
+    fn to_be_bytes(v: u8, bytes: &mut [u8; MAX_NUMBER_BYTES]) {
+        bytes[..Self::SIZE].copy_from_slice(&v.to_be_bytes());
+    }
 }
 
 impl Number for u16 {
@@ -31,6 +41,14 @@ impl Number for u16 {
     fn from_be_bytes(bytes: &[u8; MAX_NUMBER_BYTES]) -> u16 {
         u16::from_be_bytes([bytes[0], bytes[1]])
     }
+
+    fn to_le_bytes(v: u16, bytes: &mut [u8; MAX_NUMBER_BYTES]) {
+        bytes[..Self::SIZE].copy_from_slice(&v.to_le_bytes());
+    }
+
+    fn to_be_bytes(v: u16, bytes: &mut [u8; MAX_NUMBER_BYTES]) {
+        bytes[..Self::SIZE].copy_from_slice(&v.to_be_bytes());
+    }
 }
 
 impl Number for u32 {
@@ -43,6 +61,14 @@ impl Number for u32 {
     fn from_be_bytes(bytes: &[u8; MAX_NUMBER_BYTES]) -> u32 {
         u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
     }
+
+    fn to_le_bytes(v: u32, bytes: &mut [u8; MAX_NUMBER_BYTES]) {
+        bytes[..Self::SIZE].copy_from_slice(&v.to_le_bytes());
+    }
+
+    fn to_be_bytes(v: u32, bytes: &mut [u8; MAX_NUMBER_BYTES]) {
+        bytes[..Self::SIZE].copy_from_slice(&v.to_be_bytes());
+    }
 }
 
 impl Number for u64 {
@@ -55,6 +81,14 @@ impl Number for u64 {
     fn from_be_bytes(bytes: &[u8; MAX_NUMBER_BYTES]) -> u64 {
         u64::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]])
     }
+
+    fn to_le_bytes(v: u64, bytes: &mut [u8; MAX_NUMBER_BYTES]) {
+        bytes[..Self::SIZE].copy_from_slice(&v.to_le_bytes());
+    }
+
+    fn to_be_bytes(v: u64, bytes: &mut [u8; MAX_NUMBER_BYTES]) {
+        bytes[..Self::SIZE].copy_from_slice(&v.to_be_bytes());
+    }
 }
 
 impl Number for f32 {
@@ -67,6 +101,14 @@ impl Number for f32 {
     fn from_be_bytes(bytes: &[u8; MAX_NUMBER_BYTES]) -> f32 {
         f32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
     }
+
+    fn to_le_bytes(v: f32, bytes: &mut [u8; MAX_NUMBER_BYTES]) {
+        bytes[..Self::SIZE].copy_from_slice(&v.to_le_bytes());
+    }
+
+    fn to_be_bytes(v: f32, bytes: &mut [u8; MAX_NUMBER_BYTES]) {
+        bytes[..Self::SIZE].copy_from_slice(&v.to_be_bytes());
+    }
 }
 
 impl Number for f64 {
@@ -79,15 +121,43 @@ impl Number for f64 {
     fn from_be_bytes(bytes: &[u8; MAX_NUMBER_BYTES]) -> f64 {
         f64::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7]])
     }
+
+    fn to_le_bytes(v: f64, bytes: &mut [u8; MAX_NUMBER_BYTES]) {
+        bytes[..Self::SIZE].copy_from_slice(&v.to_le_bytes());
+    }
+
+    fn to_be_bytes(v: f64, bytes: &mut [u8; MAX_NUMBER_BYTES]) {
+        bytes[..Self::SIZE].copy_from_slice(&v.to_be_bytes());
+    }
+}
+
+impl Number for u128 {
+    type Output = u128;
+    const SIZE: usize = 16;
+    fn from_le_bytes(bytes: &[u8; MAX_NUMBER_BYTES]) -> u128 {
+        u128::from_le_bytes(*bytes)
+    }
+
+    fn from_be_bytes(bytes: &[u8; MAX_NUMBER_BYTES]) -> u128 {
+        u128::from_be_bytes(*bytes)
+    }
+
+    fn to_le_bytes(v: u128, bytes: &mut [u8; MAX_NUMBER_BYTES]) {
+        bytes[..Self::SIZE].copy_from_slice(&v.to_le_bytes());
+    }
+
+    fn to_be_bytes(v: u128, bytes: &mut [u8; MAX_NUMBER_BYTES]) {
+        bytes[..Self::SIZE].copy_from_slice(&v.to_be_bytes());
+    }
 }
 
 const LITTLE_ENDIAN: u32 = 1;
 const BIG_ENDIAN: u32 = 2;
 
-fn read_number<const E: u32, R: io::Read, T: Number<Output = T> + std::fmt::Debug>(mut r: R) -> Result<T> {
+fn read_number<const E: u32, R: Read, T: Number<Output = T> + core::fmt::Debug>(mut r: R) -> Result<T> {
     let mut buf = [0u8; MAX_NUMBER_BYTES];
 
-    r.read(&mut buf[..T::SIZE])?;
+    r.read_exact(&mut buf[..T::SIZE])?;
 
     if E == LITTLE_ENDIAN {
         Ok(T::from_le_bytes(&buf))
@@ -96,87 +166,259 @@ fn read_number<const E: u32, R: io::Read, T: Number<Output = T> + std::fmt::Debu
     }
 }
 
+fn write_number<const E: u32, W: Write, T: Number<Output = T>>(mut w: W, v: T) -> Result<()> {
+    let mut buf = [0u8; MAX_NUMBER_BYTES];
+
+    if E == LITTLE_ENDIAN {
+        T::to_le_bytes(v, &mut buf);
+    } else {
+        T::to_be_bytes(v, &mut buf);
+    }
+
+    w.write_all(&buf[..T::SIZE])
+}
+
 /// `ByteOrder` describes types that can serialize integers as bytes.
 ///
 /// This crate provides two types that implement `ByteOrder`: [`BigEndian`] and [`LittleEndian`].
 pub trait ByteOrder {
-    fn read_u8<T: io::Read>(r: T) -> Result<u8>;
-    fn read_u16<T: io::Read>(r: T) -> Result<u16>;
-    fn read_u32<T: io::Read>(r: T) -> Result<u32>;
-    fn read_u64<T: io::Read>(r: T) -> Result<u64>;
-    fn read_f32<T: io::Read>(r: T) -> Result<f32>;
-    fn read_f64<T: io::Read>(r: T) -> Result<f64>;
-
-    fn read_i8<T: io::Read>(r: T) -> Result<i8> {
+    /// The same order as a runtime value, so generic code can pick `from_le_bytes` or `from_be_bytes`.
+    const ENDIAN: Endian;
+
+    fn read_u8<T: Read>(r: T) -> Result<u8>;
+    fn read_u16<T: Read>(r: T) -> Result<u16>;
+    fn read_u32<T: Read>(r: T) -> Result<u32>;
+    fn read_u64<T: Read>(r: T) -> Result<u64>;
+    fn read_u128<T: Read>(r: T) -> Result<u128>;
+    fn read_f32<T: Read>(r: T) -> Result<f32>;
+    fn read_f64<T: Read>(r: T) -> Result<f64>;
+
+    fn write_u8<T: Write>(w: T, n: u8) -> Result<()>;
+    fn write_u16<T: Write>(w: T, n: u16) -> Result<()>;
+    fn write_u32<T: Write>(w: T, n: u32) -> Result<()>;
+    fn write_u64<T: Write>(w: T, n: u64) -> Result<()>;
+    fn write_u128<T: Write>(w: T, n: u128) -> Result<()>;
+    fn write_f32<T: Write>(w: T, n: f32) -> Result<()>;
+    fn write_f64<T: Write>(w: T, n: f64) -> Result<()>;
+
+    fn read_i8<T: Read>(r: T) -> Result<i8> {
         Ok(Self::read_u8(r)? as i8)
     }
 
-    fn read_i16<T: io::Read>(r: T) -> Result<i16> {
+    fn read_i16<T: Read>(r: T) -> Result<i16> {
         Ok(Self::read_u16(r)? as i16)
     }
 
-    fn read_i32<T: io::Read>(r: T) -> Result<i32> {
+    fn read_i32<T: Read>(r: T) -> Result<i32> {
         Ok(Self::read_u32(r)? as i32)
     }
 
-    fn read_i64<T: io::Read>(r: T) -> Result<i64> {
+    fn read_i64<T: Read>(r: T) -> Result<i64> {
         Ok(Self::read_u64(r)? as i64)
     }
+
+    fn read_i128<T: Read>(r: T) -> Result<i128> {
+        Ok(Self::read_u128(r)? as i128)
+    }
+
+    fn write_i8<T: Write>(w: T, n: i8) -> Result<()> {
+        Self::write_u8(w, n as u8)
+    }
+
+    fn write_i16<T: Write>(w: T, n: i16) -> Result<()> {
+        Self::write_u16(w, n as u16)
+    }
+
+    fn write_i32<T: Write>(w: T, n: i32) -> Result<()> {
+        Self::write_u32(w, n as u32)
+    }
+
+    fn write_i64<T: Write>(w: T, n: i64) -> Result<()> {
+        Self::write_u64(w, n as u64)
+    }
+
+    fn write_i128<T: Write>(w: T, n: i128) -> Result<()> {
+        Self::write_u128(w, n as u128)
+    }
 }
 
 pub enum LittleEndian {}
 pub enum BigEndian {}
 
 impl ByteOrder for LittleEndian {
-    fn read_u8<T: io::Read>(r: T) -> Result<u8> {
+    const ENDIAN: Endian = Endian::Little;
+
+    fn read_u8<T: Read>(r: T) -> Result<u8> {
+        read_number::<LITTLE_ENDIAN, _, _>(r)
+    }
+
+    fn read_u16<T: Read>(r: T) -> Result<u16> {
         read_number::<LITTLE_ENDIAN, _, _>(r)
     }
 
-    fn read_u16<T: io::Read>(r: T) -> Result<u16> {
+    fn read_u32<T: Read>(r: T) -> Result<u32> {
         read_number::<LITTLE_ENDIAN, _, _>(r)
     }
 
-    fn read_u32<T: io::Read>(r: T) -> Result<u32> {
+    fn read_u64<T: Read>(r: T) -> Result<u64> {
         read_number::<LITTLE_ENDIAN, _, _>(r)
     }
 
-    fn read_u64<T: io::Read>(r: T) -> Result<u64> {
+    fn read_u128<T: Read>(r: T) -> Result<u128> {
         read_number::<LITTLE_ENDIAN, _, _>(r)
     }
 
-    fn read_f32<T: io::Read>(r: T) -> Result<f32> {
+    fn read_f32<T: Read>(r: T) -> Result<f32> {
         read_number::<LITTLE_ENDIAN, _, _>(r)
     }
 
-    fn read_f64<T: io::Read>(r: T) -> Result<f64> {
+    fn read_f64<T: Read>(r: T) -> Result<f64> {
         read_number::<LITTLE_ENDIAN, _, _>(r)
     }
 
+    fn write_u8<T: Write>(w: T, n: u8) -> Result<()> {
+        write_number::<LITTLE_ENDIAN, _, _>(w, n)
+    }
+
+    fn write_u16<T: Write>(w: T, n: u16) -> Result<()> {
+        write_number::<LITTLE_ENDIAN, _, _>(w, n)
+    }
+
+    fn write_u32<T: Write>(w: T, n: u32) -> Result<()> {
+        write_number::<LITTLE_ENDIAN, _, _>(w, n)
+    }
+
+    fn write_u64<T: Write>(w: T, n: u64) -> Result<()> {
+        write_number::<LITTLE_ENDIAN, _, _>(w, n)
+    }
+
+    fn write_u128<T: Write>(w: T, n: u128) -> Result<()> {
+        write_number::<LITTLE_ENDIAN, _, _>(w, n)
+    }
+
+    fn write_f32<T: Write>(w: T, n: f32) -> Result<()> {
+        write_number::<LITTLE_ENDIAN, _, _>(w, n)
+    }
+
+    fn write_f64<T: Write>(w: T, n: f64) -> Result<()> {
+        write_number::<LITTLE_ENDIAN, _, _>(w, n)
+    }
+
 }
 
 impl ByteOrder for BigEndian {
-    fn read_u8<T: io::Read>(r: T) -> Result<u8> {
+    const ENDIAN: Endian = Endian::Big;
+
+    fn read_u8<T: Read>(r: T) -> Result<u8> {
         read_number::<BIG_ENDIAN, _, _>(r)
     }
 
-    fn read_u16<T: io::Read>(r: T) -> Result<u16> {
+    fn read_u16<T: Read>(r: T) -> Result<u16> {
         read_number::<BIG_ENDIAN, _, _>(r)
     }
 
-    fn read_u32<T: io::Read>(r: T) -> Result<u32> {
+    fn read_u32<T: Read>(r: T) -> Result<u32> {
         read_number::<BIG_ENDIAN, _, _>(r)
     }
 
-    fn read_u64<T: io::Read>(r: T) -> Result<u64> {
+    fn read_u64<T: Read>(r: T) -> Result<u64> {
         read_number::<BIG_ENDIAN, _, _>(r)
     }
 
-    fn read_f32<T: io::Read>(r: T) -> Result<f32> {
+    fn read_u128<T: Read>(r: T) -> Result<u128> {
         read_number::<BIG_ENDIAN, _, _>(r)
     }
 
-    fn read_f64<T: io::Read>(r: T) -> Result<f64> {
+    fn read_f32<T: Read>(r: T) -> Result<f32> {
         read_number::<BIG_ENDIAN, _, _>(r)
     }
 
+    fn read_f64<T: Read>(r: T) -> Result<f64> {
+        read_number::<BIG_ENDIAN, _, _>(r)
+    }
+
+    fn write_u8<T: Write>(w: T, n: u8) -> Result<()> {
+        write_number::<BIG_ENDIAN, _, _>(w, n)
+    }
+
+    fn write_u16<T: Write>(w: T, n: u16) -> Result<()> {
+        write_number::<BIG_ENDIAN, _, _>(w, n)
+    }
+
+    fn write_u32<T: Write>(w: T, n: u32) -> Result<()> {
+        write_number::<BIG_ENDIAN, _, _>(w, n)
+    }
+
+    fn write_u64<T: Write>(w: T, n: u64) -> Result<()> {
+        write_number::<BIG_ENDIAN, _, _>(w, n)
+    }
+
+    fn write_u128<T: Write>(w: T, n: u128) -> Result<()> {
+        write_number::<BIG_ENDIAN, _, _>(w, n)
+    }
+
+    fn write_f32<T: Write>(w: T, n: f32) -> Result<()> {
+        write_number::<BIG_ENDIAN, _, _>(w, n)
+    }
+
+    fn write_f64<T: Write>(w: T, n: f64) -> Result<()> {
+        write_number::<BIG_ENDIAN, _, _>(w, n)
+    }
+
+}
+
+/// Byte order chosen at runtime, for formats that announce it in their header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    #[cfg(target_endian = "little")]
+    pub const NATIVE: Endian = Endian::Little;
+
+    #[cfg(target_endian = "big")]
+    pub const NATIVE: Endian = Endian::Big;
+
+    pub fn read_u16<T: Read>(self, r: T) -> Result<u16> {
+        match self {
+            Endian::Little => LittleEndian::read_u16(r),
+            Endian::Big => BigEndian::read_u16(r),
+        }
+    }
+
+    pub fn read_u32<T: Read>(self, r: T) -> Result<u32> {
+        match self {
+            Endian::Little => LittleEndian::read_u32(r),
+            Endian::Big => BigEndian::read_u32(r),
+        }
+    }
+
+    pub fn read_u64<T: Read>(self, r: T) -> Result<u64> {
+        match self {
+            Endian::Little => LittleEndian::read_u64(r),
+            Endian::Big => BigEndian::read_u64(r),
+        }
+    }
+
+    pub fn read_i16<T: Read>(self, r: T) -> Result<i16> {
+        Ok(self.read_u16(r)? as i16)
+    }
+
+    pub fn read_i32<T: Read>(self, r: T) -> Result<i32> {
+        Ok(self.read_u32(r)? as i32)
+    }
+
+    pub fn read_i64<T: Read>(self, r: T) -> Result<i64> {
+        Ok(self.read_u64(r)? as i64)
+    }
+
+    pub fn read_f32<T: Read>(self, r: T) -> Result<f32> {
+        Ok(f32::from_bits(self.read_u32(r)?))
+    }
+
+    pub fn read_f64<T: Read>(self, r: T) -> Result<f64> {
+        Ok(f64::from_bits(self.read_u64(r)?))
+    }
 }
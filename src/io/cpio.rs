@@ -0,0 +1,185 @@
+use std::io::{self, Read};
+use std::time::SystemTime;
+
+use super::error::Result;
+use super::tar::{read_record, skip, EntryKind};
+use super::time;
+
+const HEADER_SIZE: usize = 110;
+const TRAILER: &str = "TRAILER!!!";
+
+// PATH_MAX on Linux; names and link targets are paths, so longer sizes mean a corrupt header
+const MAX_PATH: u32 = 4096;
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+const fn pad4(n: u64) -> u64 {
+    (4 - n % 4) % 4
+}
+
+/// Header of a newc (`070701`) or crc (`070702`) cpio member.
+#[derive(Debug, Clone)]
+pub struct CpioHeader {
+    pub name        : String,
+    pub kind        : EntryKind,
+    pub ino         : u32,
+    pub mode        : u32,
+    pub uid         : u32,
+    pub gid         : u32,
+    pub nlink       : u32,
+    pub mtime       : SystemTime,
+    pub size        : u64,
+    pub dev_major   : u32,
+    pub dev_minor   : u32,
+    pub rdev_major  : u32,
+    pub rdev_minor  : u32,
+
+    /// Sum of the data bytes for the `070702` format, 0 otherwise.
+    pub check       : u32,
+
+    /// Target of a symlink, whose data is consumed into this field.
+    pub link_name   : Option<String>,
+}
+
+/// Streaming reader for newc cpio archives, as used by initramfs and RPM payloads.
+pub struct CpioReader<R> {
+    reader      : R,
+    remaining   : u64,
+    padding     : u64,
+    done        : bool,
+}
+
+impl<R: Read> CpioReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            remaining: 0,
+            padding: 0,
+            done: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Advances to the next member, skipping any unread data of the current one.
+    pub fn next_entry(&mut self) -> Result<Option<CpioEntry<'_, R>>> {
+        skip(&mut self.reader, self.remaining + self.padding)?;
+        self.remaining = 0;
+        self.padding = 0;
+
+        if self.done {
+            return Ok(None);
+        }
+
+        let mut raw = [0u8; HEADER_SIZE];
+        if !read_record(&mut self.reader, &mut raw)? {
+            self.done = true;
+            return Ok(None);
+        }
+
+        let magic = &raw[0..6];
+        if magic != b"070701" && magic != b"070702" {
+            return Err(invalid_data(format!("unsupported cpio magic: {:?}", String::from_utf8_lossy(magic))));
+        }
+
+        let mut fields = [0u32; 13];
+        for (i, f) in fields.iter_mut().enumerate() {
+            let s = std::str::from_utf8(&raw[6 + i * 8..14 + i * 8]).map_err(|_| invalid_data("invalid cpio header"))?;
+            *f = u32::from_str_radix(s, 16).map_err(|_| invalid_data(format!("invalid cpio field: {s:?}")))?;
+        }
+
+        let [ino, mode, uid, gid, nlink, mtime, size, dev_major, dev_minor, rdev_major, rdev_minor, name_size, check] = fields;
+
+        if name_size > MAX_PATH {
+            return Err(invalid_data(format!("cpio name size {name_size} exceeds {MAX_PATH}")));
+        }
+
+        let mut name = vec![0u8; name_size as usize];
+        self.reader.read_exact(&mut name)?;
+        skip(&mut self.reader, pad4(HEADER_SIZE as u64 + name_size as u64))?;
+
+        if name.last() == Some(&0) {
+            name.pop();
+        }
+
+        let name = String::from_utf8_lossy(&name).into_owned();
+        let size = size as u64;
+
+        if name == TRAILER {
+            self.done = true;
+            return Ok(None);
+        }
+
+        let mut header = CpioHeader {
+            name,
+            kind: EntryKind::from_mode(mode),
+            ino,
+            mode,
+            uid,
+            gid,
+            nlink,
+            mtime: time::unix_to_system_time(mtime as i64)?,
+            size,
+            dev_major,
+            dev_minor,
+            rdev_major,
+            rdev_minor,
+            check,
+            link_name: None,
+        };
+
+        if header.kind == EntryKind::Symlink {
+            if size > MAX_PATH as u64 {
+                return Err(invalid_data(format!("cpio symlink target of {} is {size} bytes, over {MAX_PATH}", header.name)));
+            }
+
+            let mut target = vec![0u8; size as usize];
+            self.reader.read_exact(&mut target)?;
+            skip(&mut self.reader, pad4(size))?;
+            header.link_name = Some(String::from_utf8_lossy(&target).into_owned());
+        } else {
+            self.remaining = size;
+            self.padding = pad4(size);
+        }
+
+        Ok(Some(CpioEntry { header, archive: self }))
+    }
+}
+
+/// A cpio member; reading yields exactly its data.
+pub struct CpioEntry<'a, R> {
+    header  : CpioHeader,
+    archive : &'a mut CpioReader<R>,
+}
+
+impl<R> CpioEntry<'_, R> {
+    pub const fn header(&self) -> &CpioHeader {
+        &self.header
+    }
+
+    /// Unread bytes of the entry data.
+    pub fn remaining(&self) -> u64 {
+        self.archive.remaining
+    }
+}
+
+impl<R: Read> Read for CpioEntry<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let max = self.archive.remaining.min(buf.len() as u64) as usize;
+        if max == 0 {
+            return Ok(0);
+        }
+
+        let n = self.archive.reader.read(&mut buf[..max])?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        self.archive.remaining -= n as u64;
+        Ok(n)
+    }
+}
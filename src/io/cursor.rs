@@ -0,0 +1,101 @@
+use super::error::{Error, ErrorKind, Result};
+use super::stream::Read;
+
+/// A reader over a borrowed byte slice that keeps its position, for parsing
+/// buffers in place with [`ReadExt`](super::ReadExt) with or without `std`.
+///
+/// Unlike reading from `&[u8]` directly, the position can be queried and moved
+/// back, and [`SliceCursor::take`] hands out sub-slices without copying.
+#[derive(Debug, Clone)]
+pub struct SliceCursor<'a> {
+    data    : &'a [u8],
+    pos     : usize,
+}
+
+impl<'a> SliceCursor<'a> {
+    pub const fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    pub const fn get_ref(&self) -> &'a [u8] {
+        self.data
+    }
+
+    pub const fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// Moves to `pos`, which may be at most the length of the data.
+    pub fn set_pos(&mut self, pos: usize) -> Result<()> {
+        if pos > self.data.len() {
+            return Err(Error::new(ErrorKind::InvalidInput, "position past the end of the data"));
+        }
+
+        self.pos = pos;
+        Ok(())
+    }
+
+    pub const fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// The bytes after the position.
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+
+    /// Returns the next `n` bytes as a sub-slice and moves past them.
+    pub fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        let rest = self.remaining();
+        if n > rest.len() {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "not enough data left"));
+        }
+
+        self.pos += n;
+        Ok(&rest[..n])
+    }
+
+    pub fn skip(&mut self, n: usize) -> Result<()> {
+        self.take(n).map(|_| ())
+    }
+}
+
+impl Read for SliceCursor<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        let rest = self.remaining();
+        let n = rest.len().min(buf.len());
+        buf[..n].copy_from_slice(&rest[..n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::io::Seek for SliceCursor<'_> {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> Result<u64> {
+        let pos = match pos {
+            std::io::SeekFrom::Start(pos) => Some(pos),
+            std::io::SeekFrom::Current(delta) => (self.pos as u64).checked_add_signed(delta),
+            std::io::SeekFrom::End(delta) => (self.data.len() as u64).checked_add_signed(delta),
+        };
+
+        let pos = pos.ok_or_else(|| Error::new(ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position"))?;
+        self.set_pos(usize::try_from(pos).unwrap_or(usize::MAX))?;
+        Ok(pos)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::io::BufRead for SliceCursor<'_> {
+    fn fill_buf(&mut self) -> Result<&[u8]> {
+        Ok(self.remaining())
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.data.len());
+    }
+}
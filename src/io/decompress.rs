@@ -0,0 +1,204 @@
+use std::io::{self, BufRead, BufReader, Read};
+use super::error::Result;
+
+/// Compression formats understood by [`Decoder`].
+///
+/// Each format needs its cargo feature: `zlib` (zlib, gzip, raw deflate), `lz4` (LZ4 frame),
+/// `zstd`, `lzma` (xz and legacy `.lzma`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// Not compressed, bytes are passed through.
+    None,
+    Zlib,
+    Gzip,
+    Deflate,
+    Lz4,
+    Zstd,
+    Xz,
+    Lzma,
+}
+
+impl Compression {
+    /// Guesses the format from the first bytes of a stream.
+    ///
+    /// Raw deflate has no header and is never detected.
+    pub fn detect(magic: &[u8]) -> Option<Self> {
+        match magic {
+            [0x1F, 0x8B, ..]                            => Some(Self::Gzip),
+            [0x04, 0x22, 0x4D, 0x18, ..]                => Some(Self::Lz4),
+            [0x28, 0xB5, 0x2F, 0xFD, ..]                => Some(Self::Zstd),
+            [0xFD, b'7', b'z', b'X', b'Z', 0x00, ..]    => Some(Self::Xz),
+
+            // properties byte 0x5D (lc=3, lp=0, pb=2) followed by a power-of-two dictionary size
+            [0x5D, 0x00, 0x00, d3, d4, ..] if d3.count_ones() + d4.count_ones() <= 1 => Some(Self::Lzma),
+
+            // CMF 0x78 is deflate with the 32K window every common encoder writes; FLG must not ask
+            // for a preset dictionary and its FCHECK makes CMF:FLG a multiple of 31, and the first
+            // deflate block, when present, must not use the reserved block type
+            [0x78, flg, rest @ ..] if flg & 0x20 == 0
+                && (0x7800 + *flg as u16) % 31 == 0
+                && rest.first().is_none_or(|b| (b >> 1) & 3 != 3) => Some(Self::Zlib),
+
+            _ => None,
+        }
+    }
+}
+
+enum Inner<R: Read> {
+    None(BufReader<R>),
+
+    #[cfg(feature = "zlib")]
+    Zlib(flate2::bufread::ZlibDecoder<BufReader<R>>),
+
+    #[cfg(feature = "zlib")]
+    Gzip(flate2::bufread::MultiGzDecoder<BufReader<R>>),
+
+    #[cfg(feature = "zlib")]
+    Deflate(flate2::bufread::DeflateDecoder<BufReader<R>>),
+
+    #[cfg(feature = "lz4")]
+    Lz4(lz4_flex::frame::FrameDecoder<BufReader<R>>),
+
+    #[cfg(feature = "zstd")]
+    Zstd(Box<ruzstd::decoding::StreamingDecoder<BufReader<R>, ruzstd::decoding::FrameDecoder>>),
+
+    #[cfg(feature = "lzma")]
+    Xz(xz2::bufread::XzDecoder<BufReader<R>>),
+}
+
+/// Decompresses a stream as it is read, so [`ReadExt`](super::ReadExt) can parse the output directly.
+///
+/// Wraps any [`Read`], including a [`SubReader`](super::SubReader) over a compressed block
+/// inside a larger file. The decoder stops at the end of the compressed data of a single stream
+/// (gzip and xz members are concatenated as their formats define).
+pub struct Decoder<R: Read> {
+    inner       : Inner<R>,
+    compression : Compression,
+    pos         : u64,
+}
+
+impl<R: Read> Decoder<R> {
+    pub fn new(reader: R, compression: Compression) -> Result<Self> {
+        Self::from_buf_reader(BufReader::new(reader), compression)
+    }
+
+    /// Sniffs the magic bytes and picks the matching decoder, passing unknown data through.
+    ///
+    /// The magic is taken from the first buffered read of `reader`.
+    pub fn detect(reader: R) -> Result<Self> {
+        let mut reader = BufReader::new(reader);
+        let compression = Compression::detect(reader.fill_buf()?).unwrap_or(Compression::None);
+
+        Self::from_buf_reader(reader, compression)
+    }
+
+    fn from_buf_reader(reader: BufReader<R>, compression: Compression) -> Result<Self> {
+        let inner = match compression {
+            Compression::None => Inner::None(reader),
+
+            #[cfg(feature = "zlib")]
+            Compression::Zlib => Inner::Zlib(flate2::bufread::ZlibDecoder::new(reader)),
+
+            #[cfg(feature = "zlib")]
+            Compression::Gzip => Inner::Gzip(flate2::bufread::MultiGzDecoder::new(reader)),
+
+            #[cfg(feature = "zlib")]
+            Compression::Deflate => Inner::Deflate(flate2::bufread::DeflateDecoder::new(reader)),
+
+            #[cfg(feature = "lz4")]
+            Compression::Lz4 => Inner::Lz4(lz4_flex::frame::FrameDecoder::new(reader)),
+
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                let d = ruzstd::decoding::StreamingDecoder::new(reader)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+                Inner::Zstd(Box::new(d))
+            },
+
+            #[cfg(feature = "lzma")]
+            Compression::Xz => Inner::Xz(xz2::bufread::XzDecoder::new_multi_decoder(reader)),
+
+            #[cfg(feature = "lzma")]
+            Compression::Lzma => {
+                let stream = xz2::stream::Stream::new_lzma_decoder(u64::MAX).map_err(io::Error::from)?;
+                Inner::Xz(xz2::bufread::XzDecoder::new_stream(reader, stream))
+            },
+
+            #[allow(unreachable_patterns)]
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("{compression:?} decompression is not enabled"),
+                ));
+            },
+        };
+
+        Ok(Self {
+            inner,
+            compression,
+            pos: 0,
+        })
+    }
+
+    pub const fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// Number of decompressed bytes read so far.
+    pub const fn pos(&self) -> u64 {
+        self.pos
+    }
+
+    pub fn into_inner(self) -> R {
+        match self.inner {
+            Inner::None(r) => r.into_inner(),
+
+            #[cfg(feature = "zlib")]
+            Inner::Zlib(d) => d.into_inner().into_inner(),
+
+            #[cfg(feature = "zlib")]
+            Inner::Gzip(d) => d.into_inner().into_inner(),
+
+            #[cfg(feature = "zlib")]
+            Inner::Deflate(d) => d.into_inner().into_inner(),
+
+            #[cfg(feature = "lz4")]
+            Inner::Lz4(d) => d.into_inner().into_inner(),
+
+            #[cfg(feature = "zstd")]
+            Inner::Zstd(d) => d.into_inner().into_inner(),
+
+            #[cfg(feature = "lzma")]
+            Inner::Xz(d) => d.into_inner().into_inner(),
+        }
+    }
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = match &mut self.inner {
+            Inner::None(r) => r.read(buf)?,
+
+            #[cfg(feature = "zlib")]
+            Inner::Zlib(d) => d.read(buf)?,
+
+            #[cfg(feature = "zlib")]
+            Inner::Gzip(d) => d.read(buf)?,
+
+            #[cfg(feature = "zlib")]
+            Inner::Deflate(d) => d.read(buf)?,
+
+            #[cfg(feature = "lz4")]
+            Inner::Lz4(d) => d.read(buf)?,
+
+            #[cfg(feature = "zstd")]
+            Inner::Zstd(d) => d.read(buf)?,
+
+            #[cfg(feature = "lzma")]
+            Inner::Xz(d) => d.read(buf)?,
+        };
+
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
@@ -0,0 +1,492 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use super::byteorder::Endian;
+use super::error::Result;
+
+const ELF_MAGIC: &[u8; 4] = b"\x7FELF";
+
+const ELFCLASS32: u8    = 1;
+const ELFCLASS64: u8    = 2;
+const ELFDATA2LSB: u8   = 1;
+const ELFDATA2MSB: u8   = 2;
+
+const ET_CORE: u16  = 4;
+const PN_XNUM: u16  = 0xFFFF;
+
+const PT_LOAD: u32  = 1;
+const PT_NOTE: u32  = 4;
+
+const NT_PRSTATUS: u32  = 1;
+const NT_FILE: u32      = 0x4649_4C45;
+
+pub const EM_386: u16       = 3;
+pub const EM_ARM: u16       = 40;
+pub const EM_X86_64: u16    = 62;
+pub const EM_AARCH64: u16   = 183;
+
+pub const PF_X: u32 = 1;
+pub const PF_W: u32 = 2;
+pub const PF_R: u32 = 4;
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn unmapped(va: u64) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("address {va:#x} is not mapped"))
+}
+
+const fn align4(n: usize) -> usize {
+    n.saturating_add(3) & !3
+}
+
+/// A range of the dumped address space and where its bytes live in the file.
+///
+/// Bytes past `file_size` were not written to the dump and read as zeros.
+#[derive(Debug, Clone)]
+pub struct MemoryRange {
+    pub va          : u64,
+    pub size        : u64,
+    pub offset      : u64,
+    pub file_size   : u64,
+    pub flags       : u32,
+}
+
+impl MemoryRange {
+    /// Saturates rather than wrapping for ranges a corrupt file places at the top of the address space.
+    pub const fn end(&self) -> u64 {
+        self.va.saturating_add(self.size)
+    }
+
+    pub const fn contains(&self, va: u64) -> bool {
+        va >= self.va && va - self.va < self.size
+    }
+}
+
+/// Reads `buf.len()` bytes at `va` from `ranges`, which must be sorted by address.
+pub(crate) fn read_ranges<R: Read + Seek>(reader: &mut R, ranges: &[MemoryRange], mut va: u64, mut buf: &mut [u8]) -> Result<()> {
+    while !buf.is_empty() {
+        let index = ranges.partition_point(|r| r.end() <= va);
+        let range = ranges.get(index).filter(|r| r.contains(va)).ok_or_else(|| unmapped(va))?;
+
+        let delta = va - range.va;
+        let n = (range.size - delta).min(buf.len() as u64) as usize;
+        let (chunk, rest) = buf.split_at_mut(n);
+
+        let stored = range.file_size.saturating_sub(delta).min(n as u64) as usize;
+        if stored > 0 {
+            let offset = range.offset.checked_add(delta).ok_or_else(|| invalid_data(format!("file offset of {va:#x} overflows")))?;
+            reader.seek(SeekFrom::Start(offset))?;
+            reader.read_exact(&mut chunk[..stored])?;
+        }
+        chunk[stored..].fill(0);
+
+        va += n as u64;
+        buf = rest;
+    }
+
+    Ok(())
+}
+
+/// The fields of the ELF file header needed to walk the program headers.
+#[derive(Debug, Clone)]
+pub(crate) struct ElfHeader {
+    pub endian      : Endian,
+    pub is_64       : bool,
+    pub e_type      : u16,
+    pub machine     : u16,
+    pub phoff       : u64,
+    pub phentsize   : u64,
+    pub phnum       : u32,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct ProgramHeader {
+    pub p_type      : u32,
+    pub flags       : u32,
+    pub offset      : u64,
+    pub va          : u64,
+    pub file_size   : u64,
+    pub size        : u64,
+}
+
+impl ProgramHeader {
+    pub const fn is_load(&self) -> bool {
+        self.p_type == PT_LOAD && self.size > 0
+    }
+
+    pub fn range(&self) -> MemoryRange {
+        MemoryRange {
+            va          : self.va,
+            size        : self.size,
+            offset      : self.offset,
+            file_size   : self.file_size,
+            flags       : self.flags,
+        }
+    }
+}
+
+impl ElfHeader {
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Self> {
+        reader.seek(SeekFrom::Start(0))?;
+
+        let mut ident = [0u8; 16];
+        reader.read_exact(&mut ident)?;
+
+        if &ident[..4] != ELF_MAGIC {
+            return Err(invalid_data("not an ELF file"));
+        }
+
+        let is_64 = match ident[4] {
+            ELFCLASS32 => false,
+            ELFCLASS64 => true,
+            c => return Err(invalid_data(format!("invalid ELF class: {c}"))),
+        };
+
+        let e = match ident[5] {
+            ELFDATA2LSB => Endian::Little,
+            ELFDATA2MSB => Endian::Big,
+            d => return Err(invalid_data(format!("invalid ELF data encoding: {d}"))),
+        };
+
+        let word = |r: &mut R| if is_64 { e.read_u64(r) } else { e.read_u32(r).map(u64::from) };
+
+        let e_type      = e.read_u16(&mut *reader)?;
+        let machine     = e.read_u16(&mut *reader)?;
+        let _version    = e.read_u32(&mut *reader)?;
+        let _entry      = word(reader)?;
+        let phoff       = word(reader)?;
+        let shoff       = word(reader)?;
+        let _flags      = e.read_u32(&mut *reader)?;
+        let _ehsize     = e.read_u16(&mut *reader)?;
+        let phentsize   = e.read_u16(&mut *reader)? as u64;
+        let mut phnum   = e.read_u16(&mut *reader)? as u32;
+
+        // with more than 0xFFFE segments the real count is in sh_info of section 0
+        if phnum == PN_XNUM as u32 {
+            let sh_info = shoff.checked_add(if is_64 { 44 } else { 28 }).ok_or_else(|| invalid_data("section header offset overflows"))?;
+            reader.seek(SeekFrom::Start(sh_info))?;
+            phnum = e.read_u32(&mut *reader)?;
+        }
+
+        Ok(Self { endian: e, is_64, e_type, machine, phoff, phentsize, phnum })
+    }
+
+    pub fn program_headers<R: Read + Seek>(&self, reader: &mut R) -> Result<Vec<ProgramHeader>> {
+        let e = self.endian;
+
+        if self.phnum > 0 && self.phentsize < if self.is_64 { 56 } else { 32 } {
+            return Err(invalid_data(format!("program header entry size {} is too small", self.phentsize)));
+        }
+
+        // phnum may come from sh_info, so check the table fits in the file before allocating for it
+        let file_size = reader.seek(SeekFrom::End(0))?;
+        let table_end = (self.phnum as u64).checked_mul(self.phentsize).and_then(|n| self.phoff.checked_add(n));
+        if table_end.is_none_or(|end| end > file_size) {
+            return Err(invalid_data(format!("{} program headers at {:#x} run past the end of the file", self.phnum, self.phoff)));
+        }
+
+        let mut headers = Vec::with_capacity(self.phnum as usize);
+
+        for i in 0..self.phnum as u64 {
+            reader.seek(SeekFrom::Start(self.phoff + i * self.phentsize))?;
+
+            let p_type = e.read_u32(&mut *reader)?;
+            let (flags, offset, va, file_size, size) = if self.is_64 {
+                let flags       = e.read_u32(&mut *reader)?;
+                let offset      = e.read_u64(&mut *reader)?;
+                let vaddr       = e.read_u64(&mut *reader)?;
+                let _paddr      = e.read_u64(&mut *reader)?;
+                let filesz      = e.read_u64(&mut *reader)?;
+                let memsz       = e.read_u64(&mut *reader)?;
+                (flags, offset, vaddr, filesz, memsz)
+            } else {
+                let offset      = e.read_u32(&mut *reader)? as u64;
+                let vaddr       = e.read_u32(&mut *reader)? as u64;
+                let _paddr      = e.read_u32(&mut *reader)?;
+                let filesz      = e.read_u32(&mut *reader)? as u64;
+                let memsz       = e.read_u32(&mut *reader)? as u64;
+                let flags       = e.read_u32(&mut *reader)?;
+                (flags, offset, vaddr, filesz, memsz)
+            };
+
+            headers.push(ProgramHeader { p_type, flags, offset, va, file_size, size });
+        }
+
+        Ok(headers)
+    }
+}
+
+/// A thread from an `NT_PRSTATUS` note.
+#[derive(Debug, Clone)]
+pub struct Thread {
+    pub pid         : u32,
+    pub signal      : u16,
+
+    /// General purpose registers in the `user_regs_struct` order of the dumped machine.
+    pub registers   : Vec<u64>,
+}
+
+/// A file mapping from the `NT_FILE` note.
+#[derive(Debug, Clone)]
+pub struct MappedFile {
+    pub start   : u64,
+    pub end     : u64,
+    pub offset  : u64,
+    pub path    : String,
+}
+
+/// An ELF core file, as written by the kernel or `gcore`.
+pub struct ElfCore<R> {
+    reader  : R,
+    endian  : Endian,
+    is_64   : bool,
+    machine : u16,
+    ranges  : Vec<MemoryRange>,
+    threads : Vec<Thread>,
+    files   : Vec<MappedFile>,
+}
+
+impl<R: Read + Seek> ElfCore<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let header = ElfHeader::read(&mut reader)?;
+
+        if header.e_type != ET_CORE {
+            return Err(invalid_data(format!("not a core file: e_type {}", header.e_type)));
+        }
+
+        let mut ranges = Vec::new();
+        let mut notes = Vec::new();
+
+        for ph in header.program_headers(&mut reader)? {
+            match ph.p_type {
+                PT_LOAD if ph.size > 0 => ranges.push(ph.range()),
+                PT_NOTE => notes.push((ph.offset, ph.file_size)),
+                _ => {},
+            }
+        }
+
+        ranges.sort_by_key(|r| r.va);
+
+        let mut core = Self {
+            reader,
+            endian: header.endian,
+            is_64: header.is_64,
+            machine: header.machine,
+            ranges,
+            threads: Vec::new(),
+            files: Vec::new(),
+        };
+
+        let file_size = core.reader.seek(SeekFrom::End(0))?;
+
+        for (offset, size) in notes {
+            // the note size comes from the header, so check it before allocating
+            if offset.checked_add(size).is_none_or(|end| end > file_size) {
+                return Err(invalid_data(format!("note segment at {offset:#x} of {size:#x} bytes runs past the end of the file")));
+            }
+
+            core.reader.seek(SeekFrom::Start(offset))?;
+            let mut data = vec![0u8; size as usize];
+            core.reader.read_exact(&mut data)?;
+            core.parse_notes(&data)?;
+        }
+
+        Ok(core)
+    }
+
+    fn parse_notes(&mut self, data: &[u8]) -> Result<()> {
+        let e = self.endian;
+        let mut pos = 0;
+
+        while pos + 12 <= data.len() {
+            let mut h = &data[pos..pos + 12];
+            let namesz = e.read_u32(&mut h)? as usize;
+            let descsz = e.read_u32(&mut h)? as usize;
+            let n_type = e.read_u32(&mut h)?;
+
+            let truncated = || invalid_data("truncated note");
+            let name_end = (pos + 12).checked_add(namesz).ok_or_else(truncated)?;
+            let desc_pos = (pos + 12).checked_add(align4(namesz)).ok_or_else(truncated)?;
+            let desc_end = desc_pos.checked_add(descsz).ok_or_else(truncated)?;
+
+            let name = data.get(pos + 12..name_end).ok_or_else(truncated)?;
+            let desc = data.get(desc_pos..desc_end).ok_or_else(truncated)?;
+
+            if name.starts_with(b"CORE") {
+                match n_type {
+                    NT_PRSTATUS => self.threads.push(self.parse_prstatus(desc)?),
+                    NT_FILE     => self.files = self.parse_file_note(desc)?,
+                    _ => {},
+                }
+            }
+
+            pos = desc_pos.saturating_add(align4(descsz));
+        }
+
+        Ok(())
+    }
+
+    fn parse_prstatus(&self, desc: &[u8]) -> Result<Thread> {
+        let e = self.endian;
+        let word_size = if self.is_64 { 8 } else { 4 };
+
+        // elf_siginfo, pr_cursig and padding, then two sigset words before pr_pid
+        let pid_offset = 16 + 2 * word_size;
+
+        // pr_pid, pr_ppid, pr_pgrp, pr_sid and four timevals of two words each
+        let regs_offset = pid_offset + 16 + 8 * word_size;
+
+        if desc.len() < regs_offset + 4 {
+            return Err(invalid_data("truncated NT_PRSTATUS"));
+        }
+
+        let signal = e.read_u16(&mut &desc[12..14])?;
+        let pid = e.read_u32(&mut &desc[pid_offset..pid_offset + 4])?;
+
+        // pr_reg is followed by the 4-byte pr_fpvalid
+        let count = (desc.len() - regs_offset - 4) / word_size;
+        let mut regs = &desc[regs_offset..];
+
+        let mut registers = Vec::with_capacity(count);
+        for _ in 0..count {
+            registers.push(if self.is_64 { e.read_u64(&mut regs)? } else { e.read_u32(&mut regs)? as u64 });
+        }
+
+        Ok(Thread { pid, signal, registers })
+    }
+
+    fn parse_file_note(&self, desc: &[u8]) -> Result<Vec<MappedFile>> {
+        let e = self.endian;
+        let is_64 = self.is_64;
+        let mut d = desc;
+
+        let word = |d: &mut &[u8]| if is_64 { e.read_u64(d) } else { e.read_u32(d).map(u64::from) };
+
+        let count = word(&mut d)?;
+        let page_size = word(&mut d)?;
+
+        let mut files = Vec::new();
+        for _ in 0..count {
+            let start = word(&mut d)?;
+            let end = word(&mut d)?;
+            let offset = word(&mut d)?.checked_mul(page_size).ok_or_else(|| invalid_data("NT_FILE offset overflows"))?;
+            files.push(MappedFile { start, end, offset, path: String::new() });
+        }
+
+        for (file, name) in files.iter_mut().zip(d.split(|&b| b == 0)) {
+            file.path = String::from_utf8_lossy(name).into_owned();
+        }
+
+        Ok(files)
+    }
+
+    /// Reads `buf.len()` bytes of the dumped process memory at `va`.
+    pub fn read_at(&mut self, va: u64, buf: &mut [u8]) -> Result<()> {
+        read_ranges(&mut self.reader, &self.ranges, va, buf)
+    }
+}
+
+impl<R> ElfCore<R> {
+    pub const fn machine(&self) -> u16 {
+        self.machine
+    }
+
+    pub const fn is_64(&self) -> bool {
+        self.is_64
+    }
+
+    pub const fn endian(&self) -> Endian {
+        self.endian
+    }
+
+    /// The `PT_LOAD` segments sorted by address.
+    pub fn ranges(&self) -> &[MemoryRange] {
+        &self.ranges
+    }
+
+    pub fn threads(&self) -> &[Thread] {
+        &self.threads
+    }
+
+    pub fn files(&self) -> &[MappedFile] {
+        &self.files
+    }
+
+    /// The mapped file backing `va`, if any.
+    pub fn file_at(&self, va: u64) -> Option<&MappedFile> {
+        self.files.iter().find(|f| va >= f.start && va < f.end)
+    }
+
+    /// Instruction pointer of `thread` for the machines we know the register layout of.
+    pub fn pc(&self, thread: &Thread) -> Option<u64> {
+        let index = match self.machine {
+            EM_X86_64   => 16,
+            EM_386      => 12,
+            EM_AARCH64  => 32,
+            EM_ARM      => 15,
+            _ => return None,
+        };
+
+        thread.registers.get(index).copied()
+    }
+
+    /// Stack pointer of `thread`, see [`ElfCore::pc`].
+    pub fn sp(&self, thread: &Thread) -> Option<u64> {
+        let index = match self.machine {
+            EM_X86_64   => 19,
+            EM_386      => 15,
+            EM_AARCH64  => 31,
+            EM_ARM      => 13,
+            _ => return None,
+        };
+
+        thread.registers.get(index).copied()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// A little-endian ELF64 core header with the given program header table fields.
+    fn header(phoff: u64, shoff: u64, phentsize: u16, phnum: u16) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(ELF_MAGIC);
+        data.extend_from_slice(&[ELFCLASS64, ELFDATA2LSB, 1]);
+        data.resize(16, 0);
+        data.extend_from_slice(&ET_CORE.to_le_bytes());
+        data.extend_from_slice(&EM_X86_64.to_le_bytes());
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.extend_from_slice(&phoff.to_le_bytes());
+        data.extend_from_slice(&shoff.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&64u16.to_le_bytes());
+        data.extend_from_slice(&phentsize.to_le_bytes());
+        data.extend_from_slice(&phnum.to_le_bytes());
+        data.resize(64, 0);
+        data
+    }
+
+    fn error_kind(data: Vec<u8>) -> io::ErrorKind {
+        ElfCore::new(Cursor::new(data)).err().expect("crafted core should be rejected").kind()
+    }
+
+    #[test]
+    fn rejects_extended_count_offset_overflow() {
+        assert_eq!(error_kind(header(64, u64::MAX - 8, 56, PN_XNUM)), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_program_headers_past_end() {
+        assert_eq!(error_kind(header(64, 0, 56, 100)), io::ErrorKind::InvalidData);
+        assert_eq!(error_kind(header(64, 0, 0, 100)), io::ErrorKind::InvalidData);
+    }
+}
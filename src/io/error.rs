@@ -0,0 +1,91 @@
+//! The error type of this module: `std::io::Error` with the `std` feature, a minimal
+//! equivalent without it, so parsing code reports errors the same way in both.
+
+#[cfg(feature = "std")]
+pub use std::io::{Error, ErrorKind};
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[cfg(not(feature = "std"))]
+pub use self::bare::{Error, ErrorKind};
+
+#[cfg(not(feature = "std"))]
+mod bare {
+    use alloc::string::String;
+    use core::fmt;
+
+    /// The subset of `std::io::ErrorKind` the parsers produce.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[non_exhaustive]
+    pub enum ErrorKind {
+        NotFound,
+        PermissionDenied,
+        InvalidInput,
+        InvalidData,
+        UnexpectedEof,
+        WriteZero,
+        Interrupted,
+        Unsupported,
+        Other,
+    }
+
+    impl ErrorKind {
+        fn as_str(self) -> &'static str {
+            match self {
+                ErrorKind::NotFound => "entity not found",
+                ErrorKind::PermissionDenied => "permission denied",
+                ErrorKind::InvalidInput => "invalid input parameter",
+                ErrorKind::InvalidData => "invalid data",
+                ErrorKind::UnexpectedEof => "unexpected end of file",
+                ErrorKind::WriteZero => "write zero",
+                ErrorKind::Interrupted => "operation interrupted",
+                ErrorKind::Unsupported => "unsupported",
+                ErrorKind::Other => "other error",
+            }
+        }
+    }
+
+    impl fmt::Display for ErrorKind {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.as_str())
+        }
+    }
+
+    /// An error kind with an optional message, constructed like `std::io::Error`.
+    #[derive(Debug)]
+    pub struct Error {
+        kind    : ErrorKind,
+        message : Option<String>,
+    }
+
+    impl Error {
+        pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+            Self { kind, message: Some(message.into()) }
+        }
+
+        pub fn other(message: impl Into<String>) -> Self {
+            Self::new(ErrorKind::Other, message)
+        }
+
+        pub const fn kind(&self) -> ErrorKind {
+            self.kind
+        }
+    }
+
+    impl From<ErrorKind> for Error {
+        fn from(kind: ErrorKind) -> Self {
+            Self { kind, message: None }
+        }
+    }
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match &self.message {
+                Some(message) => f.write_str(message),
+                None => f.write_str(self.kind.as_str()),
+            }
+        }
+    }
+
+    impl core::error::Error for Error {}
+}
@@ -1,8 +1,7 @@
 use std::fs;
 use std::io::{self, Seek as _};
 use std::path::Path;
-
-pub type Result<T> = io::Result<T>;
+use super::error::Result;
 
 pub struct File {
     inner: fs::File,
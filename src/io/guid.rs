@@ -0,0 +1,64 @@
+use core::fmt;
+
+/// A GUID in its logical form.
+///
+/// On disk the first three fields follow the byte order of the format (little endian
+/// for the Microsoft mixed-endian layout, big endian for RFC 4122), `data4` is raw bytes.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Guid {
+    pub data1 : u32,
+    pub data2 : u16,
+    pub data3 : u16,
+    pub data4 : [u8; 8],
+}
+
+impl Guid {
+    pub const fn new(data1: u32, data2: u16, data3: u16, data4: [u8; 8]) -> Self {
+        Self { data1, data2, data3, data4 }
+    }
+
+    pub const fn nil() -> Self {
+        Self::new(0, 0, 0, [0; 8])
+    }
+
+    pub fn is_nil(&self) -> bool {
+        *self == Self::nil()
+    }
+
+    /// Returns the RFC 4122 (big endian) byte representation.
+    pub fn to_be_bytes(&self) -> [u8; 16] {
+        let mut b = [0u8; 16];
+        b[0..4].copy_from_slice(&self.data1.to_be_bytes());
+        b[4..6].copy_from_slice(&self.data2.to_be_bytes());
+        b[6..8].copy_from_slice(&self.data3.to_be_bytes());
+        b[8..16].copy_from_slice(&self.data4);
+        b
+    }
+
+    /// Returns the Microsoft mixed-endian byte representation.
+    pub fn to_le_bytes(&self) -> [u8; 16] {
+        let mut b = [0u8; 16];
+        b[0..4].copy_from_slice(&self.data1.to_le_bytes());
+        b[4..6].copy_from_slice(&self.data2.to_le_bytes());
+        b[6..8].copy_from_slice(&self.data3.to_le_bytes());
+        b[8..16].copy_from_slice(&self.data4);
+        b
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let d = &self.data4;
+        write!(
+            f,
+            "{:08X}-{:04X}-{:04X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}",
+            self.data1, self.data2, self.data3, d[0], d[1], d[2], d[3], d[4], d[5], d[6], d[7],
+        )
+    }
+}
+
+impl fmt::Debug for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{{}}}", self)
+    }
+}
@@ -0,0 +1,368 @@
+use std::fmt;
+use std::io::{self, Read};
+use super::error::Result;
+
+/// A running checksum or digest fed by [`HashingReader`].
+pub trait Checksum {
+    type Output: PartialEq + fmt::Debug;
+
+    fn update(&mut self, data: &[u8]);
+
+    /// Returns the value over all bytes seen so far without resetting the state.
+    fn finish(&self) -> Self::Output;
+}
+
+const fn make_crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+
+        while k < 8 {
+            c = if c & 1 != 0 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+
+        table[i] = c;
+        i += 1;
+    }
+
+    table
+}
+
+const CRC32_TABLE: [u32; 256] = make_crc32_table();
+
+/// CRC-32 (ISO-HDLC), as used by zlib, gzip, ZIP and PNG.
+#[derive(Debug, Clone)]
+pub struct Crc32(u32);
+
+impl Crc32 {
+    pub const fn new() -> Self {
+        Self(0xFFFF_FFFF)
+    }
+}
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Checksum for Crc32 {
+    type Output = u32;
+
+    fn update(&mut self, data: &[u8]) {
+        let mut c = self.0;
+
+        for &b in data {
+            c = CRC32_TABLE[((c ^ b as u32) & 0xFF) as usize] ^ (c >> 8);
+        }
+
+        self.0 = c;
+    }
+
+    fn finish(&self) -> u32 {
+        !self.0
+    }
+}
+
+/// Adler-32, as used by zlib.
+#[derive(Debug, Clone)]
+pub struct Adler32 {
+    a: u32,
+    b: u32,
+}
+
+impl Adler32 {
+    pub const fn new() -> Self {
+        Self { a: 1, b: 0 }
+    }
+}
+
+impl Default for Adler32 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Checksum for Adler32 {
+    type Output = u32;
+
+    fn update(&mut self, data: &[u8]) {
+        const MOD: u32 = 65521;
+
+        // 5552 is the largest n for which the sums cannot overflow before the modulo
+        for chunk in data.chunks(5552) {
+            for &x in chunk {
+                self.a += x as u32;
+                self.b += self.a;
+            }
+
+            self.a %= MOD;
+            self.b %= MOD;
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        (self.b << 16) | self.a
+    }
+}
+
+/// Parameters of a CRC-16 variant in the Rocksoft model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Crc16Params {
+    pub poly    : u16,
+    pub init    : u16,
+    pub refin   : bool,
+    pub refout  : bool,
+    pub xorout  : u16,
+}
+
+impl Crc16Params {
+    /// CRC-16/ARC (IBM), used by LHA and many legacy formats.
+    pub const ARC: Self             = Self { poly: 0x8005, init: 0x0000, refin: true,  refout: true,  xorout: 0x0000 };
+    /// CRC-16/MODBUS.
+    pub const MODBUS: Self          = Self { poly: 0x8005, init: 0xFFFF, refin: true,  refout: true,  xorout: 0x0000 };
+    /// CRC-16/XMODEM.
+    pub const XMODEM: Self          = Self { poly: 0x1021, init: 0x0000, refin: false, refout: false, xorout: 0x0000 };
+    /// CRC-16/IBM-3740, commonly called CCITT-FALSE.
+    pub const CCITT_FALSE: Self     = Self { poly: 0x1021, init: 0xFFFF, refin: false, refout: false, xorout: 0x0000 };
+    /// CRC-16/KERMIT, the reflected CCITT variant.
+    pub const KERMIT: Self          = Self { poly: 0x1021, init: 0x0000, refin: true,  refout: true,  xorout: 0x0000 };
+    /// CRC-16/IBM-SDLC (X.25).
+    pub const X25: Self             = Self { poly: 0x1021, init: 0xFFFF, refin: true,  refout: true,  xorout: 0xFFFF };
+}
+
+/// A bitwise CRC-16 over any [`Crc16Params`].
+#[derive(Debug, Clone)]
+pub struct Crc16 {
+    params  : Crc16Params,
+    crc     : u16,
+}
+
+impl Crc16 {
+    pub const fn new(params: Crc16Params) -> Self {
+        let crc = if params.refin { params.init.reverse_bits() } else { params.init };
+        Self { params, crc }
+    }
+}
+
+impl Checksum for Crc16 {
+    type Output = u16;
+
+    fn update(&mut self, data: &[u8]) {
+        let p = &self.params;
+        let mut c = self.crc;
+
+        if p.refin {
+            let poly = p.poly.reverse_bits();
+
+            for &b in data {
+                c ^= b as u16;
+                for _ in 0..8 {
+                    c = if c & 1 != 0 { (c >> 1) ^ poly } else { c >> 1 };
+                }
+            }
+        } else {
+            for &b in data {
+                c ^= (b as u16) << 8;
+                for _ in 0..8 {
+                    c = if c & 0x8000 != 0 { (c << 1) ^ p.poly } else { c << 1 };
+                }
+            }
+        }
+
+        self.crc = c;
+    }
+
+    fn finish(&self) -> u16 {
+        let c = if self.params.refin != self.params.refout { self.crc.reverse_bits() } else { self.crc };
+        c ^ self.params.xorout
+    }
+}
+
+#[cfg(feature = "xxhash")]
+pub use self::xxhash::{XxHash32, XxHash64};
+
+#[cfg(feature = "xxhash")]
+mod xxhash {
+    use super::Checksum;
+
+    /// xxHash32 with a seed.
+    #[derive(Clone)]
+    pub struct XxHash32(xxhash_rust::xxh32::Xxh32);
+
+    impl XxHash32 {
+        pub fn new(seed: u32) -> Self {
+            Self(xxhash_rust::xxh32::Xxh32::new(seed))
+        }
+    }
+
+    impl Checksum for XxHash32 {
+        type Output = u32;
+
+        fn update(&mut self, data: &[u8]) {
+            self.0.update(data);
+        }
+
+        fn finish(&self) -> u32 {
+            self.0.digest()
+        }
+    }
+
+    /// xxHash64 with a seed.
+    #[derive(Clone)]
+    pub struct XxHash64(xxhash_rust::xxh64::Xxh64);
+
+    impl XxHash64 {
+        pub fn new(seed: u64) -> Self {
+            Self(xxhash_rust::xxh64::Xxh64::new(seed))
+        }
+    }
+
+    impl Checksum for XxHash64 {
+        type Output = u64;
+
+        fn update(&mut self, data: &[u8]) {
+            self.0.update(data);
+        }
+
+        fn finish(&self) -> u64 {
+            self.0.digest()
+        }
+    }
+}
+
+#[cfg(feature = "sha2")]
+/// SHA-256.
+#[derive(Clone, Default)]
+pub struct Sha256(sha2::Sha256);
+
+#[cfg(feature = "sha2")]
+impl Sha256 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(feature = "sha2")]
+impl Checksum for Sha256 {
+    type Output = [u8; 32];
+
+    fn update(&mut self, data: &[u8]) {
+        sha2::Digest::update(&mut self.0, data);
+    }
+
+    fn finish(&self) -> [u8; 32] {
+        sha2::Digest::finalize(self.0.clone()).into()
+    }
+}
+
+/// Feeds every byte read through it into a [`Checksum`].
+///
+/// Hashing can be paused around fields that must be excluded, such as the checksum
+/// field itself; bytes read while paused are passed through without being hashed.
+pub struct HashingReader<R, H> {
+    inner   : R,
+    hasher  : H,
+    paused  : bool,
+    pos     : u64,
+    hashed  : u64,
+}
+
+impl<R: Read, H: Checksum> HashingReader<R, H> {
+    pub fn new(inner: R, hasher: H) -> Self {
+        Self {
+            inner,
+            hasher,
+            paused: false,
+            pos: 0,
+            hashed: 0,
+        }
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    pub const fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Runs `f` with hashing paused, e.g. to read a checksum field.
+    pub fn unhashed<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let paused = self.paused;
+        self.paused = true;
+        let r = f(self);
+        self.paused = paused;
+        r
+    }
+
+    /// Number of bytes read through this reader.
+    pub const fn pos(&self) -> u64 {
+        self.pos
+    }
+
+    /// Number of bytes that went into the checksum.
+    pub const fn hashed(&self) -> u64 {
+        self.hashed
+    }
+
+    pub fn finish(&self) -> H::Output {
+        self.hasher.finish()
+    }
+
+    /// Compares the checksum over the bytes consumed so far against `expected`.
+    pub fn verify(&self, expected: H::Output) -> Result<()> {
+        let actual = self.hasher.finish();
+
+        if actual != expected {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("checksum mismatch: expected {expected:X?}, got {actual:X?}"),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub const fn hasher(&self) -> &H {
+        &self.hasher
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    pub fn into_parts(self) -> (R, H) {
+        (self.inner, self.hasher)
+    }
+
+    pub const fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+}
+
+impl<R: Read, H: Checksum> Read for HashingReader<R, H> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+
+        if !self.paused {
+            self.hasher.update(&buf[..n]);
+            self.hashed += n as u64;
+        }
+
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
@@ -0,0 +1,342 @@
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+
+use super::address_space::AddressSpace;
+use super::error::Result;
+
+const ANSI_COLORS: [u8; 12] = [31, 32, 33, 34, 35, 36, 91, 92, 93, 94, 95, 96];
+const HTML_COLORS: [&str; 12] = [
+    "#c0392b", "#27ae60", "#b7950b", "#2e86c1", "#8e44ad", "#16a085",
+    "#e74c3c", "#2ecc71", "#d4ac0d", "#5dade2", "#af7ac5", "#48c9b0",
+];
+
+/// Rendering target of a [`HexDump`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Output {
+    Plain,
+
+    /// Fields colored with ANSI escape sequences for a terminal.
+    Ansi,
+
+    /// A `<pre>` block with fields as colored `<span>`s carrying the name as a tooltip.
+    Html,
+}
+
+/// A named range of bytes highlighted in the dump, at an absolute address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name    : String,
+    pub offset  : u64,
+    pub len     : u64,
+}
+
+impl Field {
+    pub fn new(name: impl Into<String>, offset: u64, len: u64) -> Self {
+        Self { name: name.into(), offset, len }
+    }
+
+    pub const fn end(&self) -> u64 {
+        self.offset.saturating_add(self.len)
+    }
+
+    pub const fn contains(&self, addr: u64) -> bool {
+        addr >= self.offset && addr - self.offset < self.len
+    }
+}
+
+/// Classic offset / hex / ASCII view of bytes, with optional field overlays.
+///
+/// ```text
+/// 00000000  4d 5a 90 00 03 00 00 00  04 00 00 00 ff ff 00 00  |MZ..............|  e_magic, e_cblp
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HexDump {
+    width   : usize,
+    group   : usize,
+    base    : u64,
+    output  : Output,
+    fields  : Vec<Field>,
+}
+
+impl HexDump {
+    pub const fn new() -> Self {
+        Self {
+            width   : 16,
+            group   : 8,
+            base    : 0,
+            output  : Output::Plain,
+            fields  : Vec::new(),
+        }
+    }
+
+    /// Bytes per row.
+    pub const fn width(mut self, width: usize) -> Self {
+        self.width = if width == 0 { 1 } else { width };
+        self
+    }
+
+    /// Bytes per group, separated by an extra space. 0 disables grouping.
+    pub const fn group(mut self, group: usize) -> Self {
+        self.group = group;
+        self
+    }
+
+    /// Address of the first byte.
+    pub const fn base(mut self, base: u64) -> Self {
+        self.base = base;
+        self
+    }
+
+    pub const fn output(mut self, output: Output) -> Self {
+        self.output = output;
+        self
+    }
+
+    pub fn field(mut self, name: impl Into<String>, offset: u64, len: u64) -> Self {
+        self.fields.push(Field::new(name, offset, len));
+        self
+    }
+
+    pub fn fields(mut self, fields: impl IntoIterator<Item = Field>) -> Self {
+        self.fields.extend(fields);
+        self
+    }
+
+    pub fn render(&self, data: &[u8]) -> String {
+        let mut out = String::new();
+        self.begin(&mut out);
+
+        for (i, row) in data.chunks(self.width).enumerate() {
+            self.render_row(&mut out, self.base.saturating_add(i as u64 * self.width as u64), row);
+        }
+
+        self.end(&mut out);
+        out
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W, data: &[u8]) -> Result<()> {
+        out.write_all(self.render(data).as_bytes())
+    }
+
+    /// Dumps `reader` until EOF one row at a time, returning the number of bytes read.
+    pub fn write_reader<R: Read, W: Write>(&self, out: &mut W, mut reader: R) -> Result<u64> {
+        let mut line = String::new();
+        let mut row = vec![0u8; self.width];
+        let mut total = 0u64;
+
+        self.begin(&mut line);
+
+        loop {
+            let mut n = 0;
+            while n < row.len() {
+                match reader.read(&mut row[n..]) {
+                    Ok(0) => break,
+                    Ok(read) => n += read,
+                    Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {},
+                    Err(e) => return Err(e),
+                }
+            }
+
+            if n == 0 {
+                break;
+            }
+
+            self.render_row(&mut line, self.base.saturating_add(total), &row[..n]);
+            out.write_all(line.as_bytes())?;
+            line.clear();
+            total += n as u64;
+
+            if n < row.len() {
+                break;
+            }
+        }
+
+        self.end(&mut line);
+        out.write_all(line.as_bytes())?;
+        Ok(total)
+    }
+
+    /// Dumps `len` bytes of `space` at `va`; the addresses shown are virtual addresses.
+    pub fn write_space<A: AddressSpace + ?Sized, W: Write>(&self, out: &mut W, space: &mut A, va: u64, len: usize) -> Result<()> {
+        let mut data = vec![0u8; len];
+        space.read_at(va, &mut data)?;
+        self.clone().base(va).write(out, &data)
+    }
+
+    fn begin(&self, out: &mut String) {
+        if self.output == Output::Html {
+            out.push_str("<pre class=\"hexdump\">\n");
+        }
+    }
+
+    fn end(&self, out: &mut String) {
+        if self.output == Output::Html {
+            out.push_str("</pre>\n");
+        }
+    }
+
+    /// Index of the innermost field covering `addr`, so nested overlays stay visible.
+    fn field_at(&self, addr: u64) -> Option<usize> {
+        self.fields
+            .iter()
+            .enumerate()
+            .filter(|(_, f)| f.contains(addr))
+            .min_by_key(|(_, f)| f.len)
+            .map(|(i, _)| i)
+    }
+
+    fn open(&self, out: &mut String, field: usize) {
+        match self.output {
+            Output::Plain => {},
+            Output::Ansi => {
+                let _ = write!(out, "\x1b[{}m", ANSI_COLORS[field % ANSI_COLORS.len()]);
+            },
+            Output::Html => {
+                let _ = write!(
+                    out,
+                    "<span class=\"f{field}\" style=\"color:{}\" title=\"{}\">",
+                    HTML_COLORS[field % HTML_COLORS.len()],
+                    html_escape(&self.fields[field].name),
+                );
+            },
+        }
+    }
+
+    fn close(&self, out: &mut String) {
+        match self.output {
+            Output::Plain => {},
+            Output::Ansi => out.push_str("\x1b[0m"),
+            Output::Html => out.push_str("</span>"),
+        }
+    }
+
+    fn push_char(&self, out: &mut String, c: char) {
+        match (self.output, c) {
+            (Output::Html, '<') => out.push_str("&lt;"),
+            (Output::Html, '>') => out.push_str("&gt;"),
+            (Output::Html, '&') => out.push_str("&amp;"),
+            _ => out.push(c),
+        }
+    }
+
+    fn render_row(&self, out: &mut String, addr: u64, row: &[u8]) {
+        let owners: Vec<_> = (0..row.len()).map(|i| self.field_at(addr.saturating_add(i as u64))).collect();
+
+        let _ = write!(out, "{addr:08x}  ");
+
+        // hex column, separators inside a field run share its color
+        let mut current = None;
+        for i in 0..self.width {
+            let owner = owners.get(i).copied().flatten();
+
+            if owner != current {
+                if current.is_some() {
+                    self.close(out);
+                }
+                if let Some(f) = owner {
+                    self.open(out, f);
+                }
+                current = owner;
+            }
+
+            match row.get(i) {
+                Some(b) => { let _ = write!(out, "{b:02x}"); },
+                None => out.push_str("  "),
+            }
+
+            if i + 1 < self.width {
+                let continues = owners.get(i + 1).copied().flatten() == current && current.is_some();
+                if !continues && current.is_some() {
+                    self.close(out);
+                    current = None;
+                }
+
+                out.push(' ');
+                if self.group > 0 && (i + 1) % self.group == 0 {
+                    out.push(' ');
+                }
+            }
+        }
+
+        if current.is_some() {
+            self.close(out);
+        }
+
+        // ASCII column
+        out.push_str("  |");
+        let mut current = None;
+        for (i, &b) in row.iter().enumerate() {
+            if owners[i] != current {
+                if current.is_some() {
+                    self.close(out);
+                }
+                if let Some(f) = owners[i] {
+                    self.open(out, f);
+                }
+                current = owners[i];
+            }
+
+            self.push_char(out, if (0x20..0x7F).contains(&b) { b as char } else { '.' });
+        }
+
+        if current.is_some() {
+            self.close(out);
+        }
+        out.push('|');
+
+        // names of the fields starting here, or still running at the first row
+        let end = addr.saturating_add(row.len() as u64);
+        let mut first = true;
+
+        for (i, field) in self.fields.iter().enumerate() {
+            let starts = field.offset >= addr && field.offset < end;
+            let continued = addr == self.base && field.offset < addr && field.end() > addr;
+
+            if !starts && !continued {
+                continue;
+            }
+
+            out.push_str(if first { "  " } else { ", " });
+            first = false;
+
+            self.open(out, i);
+            for c in field.name.chars() {
+                self.push_char(out, c);
+            }
+            if continued {
+                out.push_str(" (cont.)");
+            }
+            self.close(out);
+        }
+
+        out.push('\n');
+    }
+}
+
+impl Default for HexDump {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_at_the_top_of_the_address_space() {
+        let field = Field::new("tail", u64::MAX - 1, u64::MAX);
+        assert_eq!(field.end(), u64::MAX);
+        assert!(field.contains(u64::MAX - 1));
+        assert!(!field.contains(0));
+
+        let out = HexDump::new().width(4).base(u64::MAX - 5).fields([field]).render(&[0u8; 12]);
+        assert_eq!(out.lines().count(), 3);
+        assert!(out.starts_with("fffffffffffffffa  "));
+        assert!(out.contains("tail"));
+    }
+}
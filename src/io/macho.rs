@@ -0,0 +1,658 @@
+use std::collections::HashSet;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use super::byteorder::{BigEndian, Endian};
+use super::error::Result;
+use super::guid::Guid;
+use super::read::ReadExt;
+use super::sub::SubReader;
+
+const MH_MAGIC: u32     = 0xFEED_FACE;
+const MH_CIGAM: u32     = 0xCEFA_EDFE;
+const MH_MAGIC_64: u32  = 0xFEED_FACF;
+const MH_CIGAM_64: u32  = 0xCFFA_EDFE;
+const FAT_MAGIC: u32    = 0xCAFE_BABE;
+const FAT_MAGIC_64: u32 = 0xCAFE_BABF;
+const FAT_CIGAM: u32    = 0xBEBA_FECA;
+const FAT_CIGAM_64: u32 = 0xBFBA_FECA;
+
+// Java class files share FAT_MAGIC; real universal binaries have few slices
+const MAX_FAT_ARCHS: u32 = 64;
+
+const LC_REQ_DYLD: u32              = 0x8000_0000;
+const LC_SEGMENT: u32               = 0x01;
+const LC_SYMTAB: u32                = 0x02;
+const LC_DYSYMTAB: u32              = 0x0B;
+const LC_SEGMENT_64: u32            = 0x19;
+const LC_UUID: u32                  = 0x1B;
+const LC_CODE_SIGNATURE: u32        = 0x1D;
+const LC_DYLD_INFO: u32             = 0x22;
+const LC_DYLD_INFO_ONLY: u32        = 0x22 | LC_REQ_DYLD;
+const LC_DYLD_EXPORTS_TRIE: u32     = 0x33 | LC_REQ_DYLD;
+
+const CSMAGIC_EMBEDDED_SIGNATURE: u32 = 0xFADE_0CC0;
+
+const EXPORT_SYMBOL_FLAGS_REEXPORT: u64             = 0x08;
+const EXPORT_SYMBOL_FLAGS_STUB_AND_RESOLVER: u64    = 0x10;
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Reads `len` bytes at the current position, refusing lengths past the end of the input
+/// so a corrupt header cannot make us allocate gigabytes.
+fn read_bounded<R: Read + Seek>(r: &mut R, len: u64, what: &str) -> Result<Vec<u8>> {
+    let pos = r.stream_position()?;
+    let end = r.seek(SeekFrom::End(0))?;
+    r.seek(SeekFrom::Start(pos))?;
+
+    if pos.checked_add(len).is_none_or(|e| e > end) {
+        return Err(invalid_data(format!("{what} at 0x{pos:X} claims {len} bytes, past the end of the file")));
+    }
+
+    r.read_bytes(len as usize)
+}
+
+fn fixed_str(b: &[u8]) -> String {
+    let end = b.iter().position(|&c| c == 0).unwrap_or(b.len());
+    String::from_utf8_lossy(&b[..end]).into_owned()
+}
+
+fn read_uleb(data: &[u8], pos: &mut usize) -> Result<u64> {
+    let mut value: u64 = 0;
+    let mut shift = 0;
+
+    loop {
+        let b = *data.get(*pos).ok_or_else(|| invalid_data("truncated uleb128"))?;
+        *pos += 1;
+
+        if shift >= 64 {
+            return Err(invalid_data("uleb128 overflow"));
+        }
+
+        value |= ((b & 0x7F) as u64) << shift;
+        shift += 7;
+
+        if b & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+fn read_cstr(data: &[u8], pos: &mut usize) -> Result<Vec<u8>> {
+    let rest = data.get(*pos..).ok_or_else(|| invalid_data("string out of range"))?;
+    let end = rest.iter().position(|&b| b == 0).ok_or_else(|| invalid_data("unterminated string"))?;
+    *pos += end + 1;
+    Ok(rest[..end].to_vec())
+}
+
+/// One slice of a universal binary.
+#[derive(Debug, Clone)]
+pub struct FatArch {
+    pub cputype     : i32,
+    pub cpusubtype  : i32,
+    pub offset      : u64,
+    pub size        : u64,
+    pub align       : u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct MachHeader {
+    pub magic       : u32,
+    pub cputype     : i32,
+    pub cpusubtype  : i32,
+    pub filetype    : u32,
+    pub ncmds       : u32,
+    pub sizeofcmds  : u32,
+    pub flags       : u32,
+}
+
+/// A load command as found in the header, `offset` is relative to the image start.
+#[derive(Debug, Clone)]
+pub struct LoadCommand {
+    pub cmd     : u32,
+    pub offset  : u64,
+    pub size    : u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub sectname    : String,
+    pub segname     : String,
+    pub addr        : u64,
+    pub size        : u64,
+    pub offset      : u32,
+    pub align       : u32,
+    pub reloff      : u32,
+    pub nreloc      : u32,
+    pub flags       : u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Segment {
+    pub segname     : String,
+    pub vmaddr      : u64,
+    pub vmsize      : u64,
+    pub fileoff     : u64,
+    pub filesize    : u64,
+    pub maxprot     : i32,
+    pub initprot    : i32,
+    pub flags       : u32,
+    pub sections    : Vec<Section>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Symtab {
+    pub symoff  : u32,
+    pub nsyms   : u32,
+    pub stroff  : u32,
+    pub strsize : u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Nlist {
+    pub name    : String,
+    pub n_type  : u8,
+    pub n_sect  : u8,
+    pub n_desc  : u16,
+    pub n_value : u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Dysymtab {
+    pub ilocalsym       : u32,
+    pub nlocalsym       : u32,
+    pub iextdefsym      : u32,
+    pub nextdefsym      : u32,
+    pub iundefsym       : u32,
+    pub nundefsym       : u32,
+    pub tocoff          : u32,
+    pub ntoc            : u32,
+    pub modtaboff       : u32,
+    pub nmodtab         : u32,
+    pub extrefsymoff    : u32,
+    pub nextrefsyms     : u32,
+    pub indirectsymoff  : u32,
+    pub nindirectsyms   : u32,
+    pub extreloff       : u32,
+    pub nextrel         : u32,
+    pub locreloff       : u32,
+    pub nlocrel         : u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct DyldInfo {
+    pub rebase_off      : u32,
+    pub rebase_size     : u32,
+    pub bind_off        : u32,
+    pub bind_size       : u32,
+    pub weak_bind_off   : u32,
+    pub weak_bind_size  : u32,
+    pub lazy_bind_off   : u32,
+    pub lazy_bind_size  : u32,
+    pub export_off      : u32,
+    pub export_size     : u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExportKind {
+    /// Address relative to the image base.
+    Regular { address: u64 },
+    Reexport { ordinal: u64, name: String },
+    StubAndResolver { stub: u64, resolver: u64 },
+}
+
+#[derive(Debug, Clone)]
+pub struct Export {
+    pub name    : String,
+    pub flags   : u64,
+    pub kind    : ExportKind,
+}
+
+/// A blob of the embedded code signature, offsets are relative to the image start.
+#[derive(Debug, Clone)]
+pub struct CodeSignatureBlob {
+    pub slot    : u32,
+    pub magic   : u32,
+    pub offset  : u64,
+    pub length  : u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct CodeSignature {
+    pub offset  : u64,
+    pub size    : u32,
+    pub blobs   : Vec<CodeSignatureBlob>,
+}
+
+/// A parsed 32 or 64-bit Mach-O image.
+#[derive(Debug, Clone)]
+pub struct MachO {
+    pub endian          : Endian,
+    pub is_64           : bool,
+    pub header          : MachHeader,
+    pub commands        : Vec<LoadCommand>,
+    pub segments        : Vec<Segment>,
+    pub symtab          : Option<Symtab>,
+    pub symbols         : Vec<Nlist>,
+    pub dysymtab        : Option<Dysymtab>,
+    pub dyld_info       : Option<DyldInfo>,
+    pub exports         : Vec<Export>,
+    pub uuid            : Option<Guid>,
+    pub code_signature  : Option<CodeSignature>,
+}
+
+/// Either a single image or the slices of a universal binary.
+#[derive(Debug, Clone)]
+pub enum MachFile {
+    Thin(Box<MachO>),
+    Fat(Vec<(FatArch, MachO)>),
+}
+
+impl MachFile {
+    pub fn parse<R: Read + Seek>(mut r: R) -> Result<Self> {
+        match parse_fat(&mut r)? {
+            Some(archs) => {
+                let mut images = Vec::with_capacity(archs.len());
+
+                for arch in archs {
+                    let sub = SubReader::new(&mut r, arch.offset, arch.size)?;
+                    let image = MachO::parse(sub)?;
+                    images.push((arch, image));
+                }
+
+                Ok(MachFile::Fat(images))
+            },
+
+            None => Ok(MachFile::Thin(Box::new(MachO::parse(r)?))),
+        }
+    }
+
+    pub fn images(&self) -> Vec<&MachO> {
+        match self {
+            MachFile::Thin(m) => vec![m],
+            MachFile::Fat(v) => v.iter().map(|(_, m)| m).collect(),
+        }
+    }
+}
+
+/// Reads the fat header, which is big endian as written by Apple's tools, but
+/// byte-swapped headers are accepted too. Returns `None` for a thin image.
+pub fn parse_fat<R: Read + Seek>(r: &mut R) -> Result<Option<Vec<FatArch>>> {
+    r.seek(SeekFrom::Start(0))?;
+
+    let (e, is_64) = match r.read_u32::<BigEndian>()? {
+        FAT_MAGIC       => (Endian::Big, false),
+        FAT_MAGIC_64    => (Endian::Big, true),
+        FAT_CIGAM       => (Endian::Little, false),
+        FAT_CIGAM_64    => (Endian::Little, true),
+        _ => return Ok(None),
+    };
+
+    let count = e.read_u32(&mut *r)?;
+    if count == 0 || count > MAX_FAT_ARCHS {
+        return Ok(None);
+    }
+
+    let mut archs = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let cputype = e.read_i32(&mut *r)?;
+        let cpusubtype = e.read_i32(&mut *r)?;
+
+        let arch = if is_64 {
+            let offset = e.read_u64(&mut *r)?;
+            let size = e.read_u64(&mut *r)?;
+            let align = e.read_u32(&mut *r)?;
+            let _reserved = e.read_u32(&mut *r)?;
+            FatArch { cputype, cpusubtype, offset, size, align }
+        } else {
+            let offset = e.read_u32(&mut *r)? as u64;
+            let size = e.read_u32(&mut *r)? as u64;
+            let align = e.read_u32(&mut *r)?;
+            FatArch { cputype, cpusubtype, offset, size, align }
+        };
+
+        archs.push(arch);
+    }
+
+    Ok(Some(archs))
+}
+
+impl MachO {
+    /// Parses an image starting at offset 0 of `r`; use a [`SubReader`] for embedded images.
+    pub fn parse<R: Read + Seek>(mut r: R) -> Result<Self> {
+        r.seek(SeekFrom::Start(0))?;
+
+        let raw_magic = r.read_u32::<BigEndian>()?;
+        let (endian, is_64) = match raw_magic {
+            MH_MAGIC    => (Endian::Big, false),
+            MH_MAGIC_64 => (Endian::Big, true),
+            MH_CIGAM    => (Endian::Little, false),
+            MH_CIGAM_64 => (Endian::Little, true),
+            m => return Err(invalid_data(format!("not a Mach-O image: magic 0x{m:08X}"))),
+        };
+
+        let e = endian;
+        let header = MachHeader {
+            magic       : if is_64 { MH_MAGIC_64 } else { MH_MAGIC },
+            cputype     : e.read_i32(&mut r)?,
+            cpusubtype  : e.read_i32(&mut r)?,
+            filetype    : e.read_u32(&mut r)?,
+            ncmds       : e.read_u32(&mut r)?,
+            sizeofcmds  : e.read_u32(&mut r)?,
+            flags       : e.read_u32(&mut r)?,
+        };
+
+        if is_64 {
+            let _reserved = e.read_u32(&mut r)?;
+        }
+
+        let header_size: u64 = if is_64 { 32 } else { 28 };
+        let cmds = read_bounded(&mut r, header.sizeofcmds as u64, "load commands")?;
+
+        let mut image = MachO {
+            endian,
+            is_64,
+            header,
+            commands: Vec::new(),
+            segments: Vec::new(),
+            symtab: None,
+            symbols: Vec::new(),
+            dysymtab: None,
+            dyld_info: None,
+            exports: Vec::new(),
+            uuid: None,
+            code_signature: None,
+        };
+
+        let mut export_trie: Option<(u32, u32)> = None;
+        let mut signature: Option<(u32, u32)> = None;
+        let mut pos = 0usize;
+
+        for _ in 0..image.header.ncmds {
+            let mut c = cmds.get(pos..).ok_or_else(|| invalid_data("load command out of range"))?;
+            let cmd = e.read_u32(&mut c)?;
+            let size = e.read_u32(&mut c)?;
+
+            if size < 8 || pos + size as usize > cmds.len() {
+                return Err(invalid_data(format!("invalid load command size: {size}")));
+            }
+
+            let mut body = &cmds[pos + 8..pos + size as usize];
+            image.commands.push(LoadCommand { cmd, offset: header_size + pos as u64, size });
+
+            match cmd {
+                LC_SEGMENT | LC_SEGMENT_64 => {
+                    image.segments.push(Self::parse_segment(&mut body, e, cmd == LC_SEGMENT_64)?);
+                },
+
+                LC_SYMTAB => {
+                    image.symtab = Some(Symtab {
+                        symoff  : e.read_u32(&mut body)?,
+                        nsyms   : e.read_u32(&mut body)?,
+                        stroff  : e.read_u32(&mut body)?,
+                        strsize : e.read_u32(&mut body)?,
+                    });
+                },
+
+                LC_DYSYMTAB => {
+                    let mut f = [0u32; 18];
+                    for v in f.iter_mut() {
+                        *v = e.read_u32(&mut body)?;
+                    }
+
+                    image.dysymtab = Some(Dysymtab {
+                        ilocalsym: f[0], nlocalsym: f[1], iextdefsym: f[2], nextdefsym: f[3],
+                        iundefsym: f[4], nundefsym: f[5], tocoff: f[6], ntoc: f[7],
+                        modtaboff: f[8], nmodtab: f[9], extrefsymoff: f[10], nextrefsyms: f[11],
+                        indirectsymoff: f[12], nindirectsyms: f[13], extreloff: f[14], nextrel: f[15],
+                        locreloff: f[16], nlocrel: f[17],
+                    });
+                },
+
+                LC_DYLD_INFO | LC_DYLD_INFO_ONLY => {
+                    let mut f = [0u32; 10];
+                    for v in f.iter_mut() {
+                        *v = e.read_u32(&mut body)?;
+                    }
+
+                    let info = DyldInfo {
+                        rebase_off: f[0], rebase_size: f[1], bind_off: f[2], bind_size: f[3],
+                        weak_bind_off: f[4], weak_bind_size: f[5], lazy_bind_off: f[6], lazy_bind_size: f[7],
+                        export_off: f[8], export_size: f[9],
+                    };
+
+                    export_trie = Some((info.export_off, info.export_size));
+                    image.dyld_info = Some(info);
+                },
+
+                LC_DYLD_EXPORTS_TRIE => {
+                    export_trie = Some((e.read_u32(&mut body)?, e.read_u32(&mut body)?));
+                },
+
+                LC_UUID => {
+                    // UUID bytes are stored in order regardless of the image byte order
+                    image.uuid = Some(body.read_guid::<BigEndian>()?);
+                },
+
+                LC_CODE_SIGNATURE => {
+                    signature = Some((e.read_u32(&mut body)?, e.read_u32(&mut body)?));
+                },
+
+                _ => {},
+            }
+
+            pos += size as usize;
+        }
+
+        if let Some(symtab) = image.symtab {
+            image.symbols = Self::read_symbols(&mut r, e, is_64, &symtab)?;
+        }
+
+        if let Some((off, size)) = export_trie {
+            if size > 0 {
+                r.seek(SeekFrom::Start(off as u64))?;
+                let trie = read_bounded(&mut r, size as u64, "export trie")?;
+                image.exports = Self::parse_export_trie(&trie)?;
+            }
+        }
+
+        if let Some((off, size)) = signature {
+            image.code_signature = Some(Self::read_code_signature(&mut r, off, size)?);
+        }
+
+        Ok(image)
+    }
+
+    fn parse_segment(body: &mut &[u8], e: Endian, is_64: bool) -> Result<Segment> {
+        let segname = fixed_str(&ReadExt::read_array::<16>(body)?);
+
+        let (vmaddr, vmsize, fileoff, filesize) = if is_64 {
+            (e.read_u64(&mut *body)?, e.read_u64(&mut *body)?, e.read_u64(&mut *body)?, e.read_u64(&mut *body)?)
+        } else {
+            (
+                e.read_u32(&mut *body)? as u64,
+                e.read_u32(&mut *body)? as u64,
+                e.read_u32(&mut *body)? as u64,
+                e.read_u32(&mut *body)? as u64,
+            )
+        };
+
+        let maxprot = e.read_i32(&mut *body)?;
+        let initprot = e.read_i32(&mut *body)?;
+        let nsects = e.read_u32(&mut *body)?;
+        let flags = e.read_u32(&mut *body)?;
+
+        let mut sections = Vec::new();
+        for _ in 0..nsects {
+            let sectname = fixed_str(&ReadExt::read_array::<16>(body)?);
+            let segname = fixed_str(&ReadExt::read_array::<16>(body)?);
+
+            let (addr, size) = if is_64 {
+                (e.read_u64(&mut *body)?, e.read_u64(&mut *body)?)
+            } else {
+                (e.read_u32(&mut *body)? as u64, e.read_u32(&mut *body)? as u64)
+            };
+
+            let section = Section {
+                sectname,
+                segname,
+                addr,
+                size,
+                offset  : e.read_u32(&mut *body)?,
+                align   : e.read_u32(&mut *body)?,
+                reloff  : e.read_u32(&mut *body)?,
+                nreloc  : e.read_u32(&mut *body)?,
+                flags   : e.read_u32(&mut *body)?,
+            };
+
+            // reserved1, reserved2 (and reserved3 for 64-bit)
+            let reserved = if is_64 { 12 } else { 8 };
+            *body = body.get(reserved..).ok_or_else(|| invalid_data("truncated section header"))?;
+
+            sections.push(section);
+        }
+
+        Ok(Segment { segname, vmaddr, vmsize, fileoff, filesize, maxprot, initprot, flags, sections })
+    }
+
+    fn read_symbols<R: Read + Seek>(r: &mut R, e: Endian, is_64: bool, symtab: &Symtab) -> Result<Vec<Nlist>> {
+        r.seek(SeekFrom::Start(symtab.stroff as u64))?;
+        let strings = read_bounded(r, symtab.strsize as u64, "string table")?;
+
+        let entry_size: u64 = if is_64 { 16 } else { 12 };
+        r.seek(SeekFrom::Start(symtab.symoff as u64))?;
+        let table_size = (symtab.nsyms as u64).checked_mul(entry_size).ok_or_else(|| invalid_data("symbol table size overflows"))?;
+        let table = read_bounded(r, table_size, "symbol table")?;
+        let mut t = table.as_slice();
+
+        let mut symbols = Vec::with_capacity(symtab.nsyms as usize);
+        for _ in 0..symtab.nsyms {
+            let strx = e.read_u32(&mut t)? as usize;
+            let n_type = t.read_u8()?;
+            let n_sect = t.read_u8()?;
+            let n_desc = e.read_u16(&mut t)?;
+            let n_value = if is_64 { e.read_u64(&mut t)? } else { e.read_u32(&mut t)? as u64 };
+
+            let name = strings.get(strx..).map(fixed_str).unwrap_or_default();
+            symbols.push(Nlist { name, n_type, n_sect, n_desc, n_value });
+        }
+
+        Ok(symbols)
+    }
+
+    fn parse_export_trie(trie: &[u8]) -> Result<Vec<Export>> {
+        let mut exports = Vec::new();
+        let mut visited = HashSet::new();
+        let mut stack: Vec<(usize, Vec<u8>)> = vec![(0, Vec::new())];
+
+        while let Some((node, prefix)) = stack.pop() {
+            if !visited.insert(node) {
+                return Err(invalid_data("export trie loop"));
+            }
+
+            let mut pos = node;
+            let terminal_size = read_uleb(trie, &mut pos)?;
+            let children_pos = usize::try_from(terminal_size).ok()
+                .and_then(|size| pos.checked_add(size))
+                .filter(|&p| p <= trie.len())
+                .ok_or_else(|| invalid_data("export trie terminal size out of range"))?;
+
+            if terminal_size > 0 {
+                let flags = read_uleb(trie, &mut pos)?;
+
+                let kind = if flags & EXPORT_SYMBOL_FLAGS_REEXPORT != 0 {
+                    let ordinal = read_uleb(trie, &mut pos)?;
+                    let name = String::from_utf8_lossy(&read_cstr(trie, &mut pos)?).into_owned();
+                    ExportKind::Reexport { ordinal, name }
+                } else if flags & EXPORT_SYMBOL_FLAGS_STUB_AND_RESOLVER != 0 {
+                    let stub = read_uleb(trie, &mut pos)?;
+                    let resolver = read_uleb(trie, &mut pos)?;
+                    ExportKind::StubAndResolver { stub, resolver }
+                } else {
+                    ExportKind::Regular { address: read_uleb(trie, &mut pos)? }
+                };
+
+                exports.push(Export {
+                    name: String::from_utf8_lossy(&prefix).into_owned(),
+                    flags,
+                    kind,
+                });
+            }
+
+            let mut pos = children_pos;
+            let count = *trie.get(pos).ok_or_else(|| invalid_data("export trie out of range"))?;
+            pos += 1;
+
+            for _ in 0..count {
+                let label = read_cstr(trie, &mut pos)?;
+                let child = read_uleb(trie, &mut pos)? as usize;
+
+                let mut name = prefix.clone();
+                name.extend_from_slice(&label);
+                stack.push((child, name));
+            }
+        }
+
+        exports.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(exports)
+    }
+
+    /// The embedded signature super blob is big endian on every architecture.
+    fn read_code_signature<R: Read + Seek>(r: &mut R, offset: u32, size: u32) -> Result<CodeSignature> {
+        let base = offset as u64;
+        r.seek(SeekFrom::Start(base))?;
+
+        let mut signature = CodeSignature { offset: base, size, blobs: Vec::new() };
+
+        if r.read_u32::<BigEndian>()? != CSMAGIC_EMBEDDED_SIGNATURE {
+            return Ok(signature);
+        }
+
+        let _length = r.read_u32::<BigEndian>()?;
+        let count = r.read_u32::<BigEndian>()?;
+
+        let mut index = Vec::new();
+        for _ in 0..count.min(size / 8) {
+            index.push((r.read_u32::<BigEndian>()?, r.read_u32::<BigEndian>()?));
+        }
+
+        for (slot, blob_offset) in index {
+            r.seek(SeekFrom::Start(base + blob_offset as u64))?;
+
+            signature.blobs.push(CodeSignatureBlob {
+                slot,
+                magic   : r.read_u32::<BigEndian>()?,
+                offset  : base + blob_offset as u64,
+                length  : r.read_u32::<BigEndian>()?,
+            });
+        }
+
+        Ok(signature)
+    }
+
+    pub fn segment(&self, name: &str) -> Option<&Segment> {
+        self.segments.iter().find(|s| s.segname == name)
+    }
+
+    pub fn section(&self, segname: &str, sectname: &str) -> Option<&Section> {
+        self.segment(segname)?.sections.iter().find(|s| s.sectname == sectname)
+    }
+
+    /// Preferred load address, the vmaddr of `__TEXT`.
+    pub fn image_base(&self) -> u64 {
+        self.segment("__TEXT").map_or(0, |s| s.vmaddr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_export_trie_terminal_past_end() {
+        assert!(MachO::parse_export_trie(&[0x00, 0x00]).unwrap().is_empty());
+        assert!(MachO::parse_export_trie(&[0x05, 0x00]).is_err());
+        assert!(MachO::parse_export_trie(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 0x00]).is_err());
+    }
+}
@@ -0,0 +1,556 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use super::byteorder::LittleEndian;
+use super::elfcore::{read_ranges, MemoryRange, PF_R, PF_W, PF_X};
+use super::error::Result;
+use super::guid::Guid;
+use super::read::ReadExt;
+
+const MINIDUMP_SIGNATURE: u32 = 0x504D_444D;
+const CV_SIGNATURE_RSDS: u32 = 0x5344_5352;
+
+const THREAD_LIST_STREAM: u32       = 3;
+const MODULE_LIST_STREAM: u32       = 4;
+const MEMORY_LIST_STREAM: u32       = 5;
+const EXCEPTION_STREAM: u32         = 6;
+const SYSTEM_INFO_STREAM: u32       = 7;
+const MEMORY64_LIST_STREAM: u32     = 9;
+const MEMORY_INFO_LIST_STREAM: u32  = 16;
+
+const MEM_COMMIT: u32 = 0x1000;
+
+pub const PROCESSOR_ARCHITECTURE_INTEL: u16 = 0;
+pub const PROCESSOR_ARCHITECTURE_ARM: u16   = 5;
+pub const PROCESSOR_ARCHITECTURE_AMD64: u16 = 9;
+pub const PROCESSOR_ARCHITECTURE_ARM64: u16 = 12;
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Converts `PAGE_*` protection constants to ELF style `PF_*` flags.
+const fn page_protection_flags(protect: u32) -> u32 {
+    match protect & 0xFF {
+        0x02 => PF_R,
+        0x04 | 0x08 => PF_R | PF_W,
+        0x10 => PF_X,
+        0x20 => PF_R | PF_X,
+        0x40 | 0x80 => PF_R | PF_W | PF_X,
+        _ => 0,
+    }
+}
+
+fn skip_bytes<R: Seek>(r: &mut R, n: u32) -> Result<()> {
+    r.seek(SeekFrom::Current(n as i64)).map(|_| ())
+}
+
+/// Fails unless `count` entries of `entry_size` bytes fit between the current position and `file_len`.
+/// Counts and sizes come from the file, so this runs before anything is allocated for them.
+fn check_fits<R: Seek>(r: &mut R, file_len: u64, count: u64, entry_size: u64, what: &str) -> Result<()> {
+    let pos = r.stream_position()?;
+
+    if count.checked_mul(entry_size).and_then(|n| pos.checked_add(n)).is_none_or(|end| end > file_len) {
+        return Err(invalid_data(format!("{what} at {pos:#x} runs past the end of the file")));
+    }
+
+    Ok(())
+}
+
+/// `MINIDUMP_LOCATION_DESCRIPTOR`: a blob of `size` bytes at file offset `rva`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Location {
+    pub size    : u32,
+    pub rva     : u32,
+}
+
+impl Location {
+    fn read<R: Read>(r: &mut R) -> Result<Self> {
+        Ok(Self {
+            size    : r.read_u32::<LittleEndian>()?,
+            rva     : r.read_u32::<LittleEndian>()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Module {
+    pub base        : u64,
+    pub size        : u32,
+    pub checksum    : u32,
+    pub timestamp   : u32,
+    pub name        : String,
+
+    /// CodeView `RSDS` record, used to look up the matching PDB.
+    pub pdb_guid    : Option<Guid>,
+    pub pdb_age     : u32,
+    pub pdb_name    : Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Thread {
+    pub id          : u32,
+    pub suspend     : u32,
+    pub teb         : u64,
+    pub stack_start : u64,
+
+    /// Raw `CONTEXT` record of the dumped architecture.
+    pub context     : Vec<u8>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Exception {
+    pub thread_id   : u32,
+    pub code        : u32,
+    pub flags       : u32,
+    pub record      : u64,
+    pub address     : u64,
+    pub parameters  : Vec<u64>,
+    pub context     : Vec<u8>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SystemInfo {
+    pub architecture    : u16,
+    pub processors      : u8,
+    pub product_type    : u8,
+    pub major_version   : u32,
+    pub minor_version   : u32,
+    pub build_number    : u32,
+    pub platform_id     : u32,
+}
+
+/// A Windows minidump (`.dmp`) file.
+pub struct Minidump<R> {
+    reader      : R,
+    file_len    : u64,
+    timestamp   : u32,
+    flags       : u64,
+    system      : SystemInfo,
+    modules     : Vec<Module>,
+    threads     : Vec<Thread>,
+    exception   : Option<Exception>,
+    ranges      : Vec<MemoryRange>,
+    memory_info : Vec<(u64, u64, u32)>,
+}
+
+impl<R: Read + Seek> Minidump<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+
+        if reader.read_u32::<LittleEndian>()? != MINIDUMP_SIGNATURE {
+            return Err(invalid_data("not a minidump file"));
+        }
+
+        let _version    = reader.read_u32::<LittleEndian>()?;
+        let num_streams = reader.read_u32::<LittleEndian>()?;
+        let dir_rva     = reader.read_u32::<LittleEndian>()?;
+        let _checksum   = reader.read_u32::<LittleEndian>()?;
+        let timestamp   = reader.read_u32::<LittleEndian>()?;
+        let flags       = reader.read_u64::<LittleEndian>()?;
+
+        reader.seek(SeekFrom::Start(dir_rva as u64))?;
+        check_fits(&mut reader, file_len, num_streams as u64, 12, "stream directory")?;
+
+        let mut streams = Vec::with_capacity(num_streams as usize);
+        for _ in 0..num_streams {
+            let kind = reader.read_u32::<LittleEndian>()?;
+            streams.push((kind, Location::read(&mut reader)?));
+        }
+
+        let mut dump = Self {
+            reader,
+            file_len,
+            timestamp,
+            flags,
+            system: SystemInfo::default(),
+            modules: Vec::new(),
+            threads: Vec::new(),
+            exception: None,
+            ranges: Vec::new(),
+            memory_info: Vec::new(),
+        };
+
+        for (kind, loc) in streams {
+            dump.reader.seek(SeekFrom::Start(loc.rva as u64))?;
+
+            match kind {
+                THREAD_LIST_STREAM      => dump.read_threads()?,
+                MODULE_LIST_STREAM      => dump.read_modules()?,
+                MEMORY_LIST_STREAM      => dump.read_memory_list()?,
+                EXCEPTION_STREAM        => dump.read_exception()?,
+                SYSTEM_INFO_STREAM      => dump.read_system_info()?,
+                MEMORY64_LIST_STREAM    => dump.read_memory64_list()?,
+                MEMORY_INFO_LIST_STREAM => dump.read_memory_info_list()?,
+                _ => {},
+            }
+        }
+
+        dump.ranges.sort_by_key(|r| r.va);
+        dump.apply_protection();
+        Ok(dump)
+    }
+
+    fn read_blob(&mut self, loc: Location) -> Result<Vec<u8>> {
+        self.reader.seek(SeekFrom::Start(loc.rva as u64))?;
+        check_fits(&mut self.reader, self.file_len, 1, loc.size as u64, "blob")?;
+        self.reader.read_bytes(loc.size as usize)
+    }
+
+    /// Reads a `MINIDUMP_STRING`, a byte length followed by UTF-16 code units.
+    fn read_string(&mut self, rva: u32) -> Result<String> {
+        self.reader.seek(SeekFrom::Start(rva as u64))?;
+
+        let len = self.reader.read_u32::<LittleEndian>()? as usize;
+        check_fits(&mut self.reader, self.file_len, 1, len as u64, "string")?;
+        let data = self.reader.read_bytes(len & !1)?;
+        let units = data.chunks_exact(2).map(|c| u16::from_le_bytes([c[0], c[1]]));
+
+        Ok(char::decode_utf16(units).map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER)).collect())
+    }
+
+    fn read_system_info(&mut self) -> Result<()> {
+        let r = &mut self.reader;
+
+        let architecture    = r.read_u16::<LittleEndian>()?;
+        let _level          = r.read_u16::<LittleEndian>()?;
+        let _revision       = r.read_u16::<LittleEndian>()?;
+        let processors      = r.read_u8()?;
+        let product_type    = r.read_u8()?;
+
+        self.system = SystemInfo {
+            architecture,
+            processors,
+            product_type,
+            major_version   : r.read_u32::<LittleEndian>()?,
+            minor_version   : r.read_u32::<LittleEndian>()?,
+            build_number    : r.read_u32::<LittleEndian>()?,
+            platform_id     : r.read_u32::<LittleEndian>()?,
+        };
+
+        Ok(())
+    }
+
+    fn read_threads(&mut self) -> Result<()> {
+        let count = self.reader.read_u32::<LittleEndian>()?;
+        check_fits(&mut self.reader, self.file_len, count as u64, 48, "thread list")?;
+
+        let mut raw = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let r = &mut self.reader;
+
+            let id          = r.read_u32::<LittleEndian>()?;
+            let suspend     = r.read_u32::<LittleEndian>()?;
+            let _priority_class = r.read_u32::<LittleEndian>()?;
+            let _priority   = r.read_u32::<LittleEndian>()?;
+            let teb         = r.read_u64::<LittleEndian>()?;
+            let stack_start = r.read_u64::<LittleEndian>()?;
+            let _stack      = Location::read(r)?;
+            let context     = Location::read(r)?;
+
+            raw.push((id, suspend, teb, stack_start, context));
+        }
+
+        for (id, suspend, teb, stack_start, context) in raw {
+            let context = self.read_blob(context)?;
+            self.threads.push(Thread { id, suspend, teb, stack_start, context });
+        }
+
+        Ok(())
+    }
+
+    fn read_modules(&mut self) -> Result<()> {
+        let count = self.reader.read_u32::<LittleEndian>()?;
+        check_fits(&mut self.reader, self.file_len, count as u64, 108, "module list")?;
+
+        let mut raw = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let r = &mut self.reader;
+
+            let base        = r.read_u64::<LittleEndian>()?;
+            let size        = r.read_u32::<LittleEndian>()?;
+            let checksum    = r.read_u32::<LittleEndian>()?;
+            let timestamp   = r.read_u32::<LittleEndian>()?;
+            let name_rva    = r.read_u32::<LittleEndian>()?;
+            let _version    = ReadExt::read_array::<52>(&mut *r)?;
+            let cv_record   = Location::read(r)?;
+            let _misc       = Location::read(r)?;
+            let _reserved   = ReadExt::read_array::<16>(&mut *r)?;
+
+            raw.push((base, size, checksum, timestamp, name_rva, cv_record));
+        }
+
+        for (base, size, checksum, timestamp, name_rva, cv_record) in raw {
+            let mut module = Module {
+                base,
+                size,
+                checksum,
+                timestamp,
+                name: self.read_string(name_rva)?,
+                pdb_guid: None,
+                pdb_age: 0,
+                pdb_name: None,
+            };
+
+            if cv_record.size >= 24 {
+                let cv = self.read_blob(cv_record)?;
+                let mut c = cv.as_slice();
+
+                if c.read_u32::<LittleEndian>()? == CV_SIGNATURE_RSDS {
+                    module.pdb_guid = Some(c.read_guid::<LittleEndian>()?);
+                    module.pdb_age = c.read_u32::<LittleEndian>()?;
+
+                    let end = c.iter().position(|&b| b == 0).unwrap_or(c.len());
+                    module.pdb_name = Some(String::from_utf8_lossy(&c[..end]).into_owned());
+                }
+            }
+
+            self.modules.push(module);
+        }
+
+        Ok(())
+    }
+
+    fn read_memory_list(&mut self) -> Result<()> {
+        let count = self.reader.read_u32::<LittleEndian>()?;
+
+        for _ in 0..count {
+            let va = self.reader.read_u64::<LittleEndian>()?;
+            let loc = Location::read(&mut self.reader)?;
+
+            self.ranges.push(MemoryRange {
+                va,
+                size        : loc.size as u64,
+                offset      : loc.rva as u64,
+                file_size   : loc.size as u64,
+                flags       : PF_R,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Full-memory dumps store every range back to back starting at a single base RVA.
+    fn read_memory64_list(&mut self) -> Result<()> {
+        let count = self.reader.read_u64::<LittleEndian>()?;
+        let mut offset = self.reader.read_u64::<LittleEndian>()?;
+
+        for _ in 0..count {
+            let va = self.reader.read_u64::<LittleEndian>()?;
+            let size = self.reader.read_u64::<LittleEndian>()?;
+
+            self.ranges.push(MemoryRange { va, size, offset, file_size: size, flags: PF_R });
+            offset = offset.checked_add(size).ok_or_else(|| invalid_data("memory64 list offsets overflow"))?;
+        }
+
+        Ok(())
+    }
+
+    fn read_memory_info_list(&mut self) -> Result<()> {
+        let header_size = self.reader.read_u32::<LittleEndian>()?;
+        let entry_size  = self.reader.read_u32::<LittleEndian>()?;
+        let count       = self.reader.read_u64::<LittleEndian>()?;
+
+        skip_bytes(&mut self.reader, header_size.saturating_sub(16))?;
+        check_fits(&mut self.reader, self.file_len, count, entry_size as u64, "memory info list")?;
+
+        for _ in 0..count {
+            let entry = self.reader.read_bytes(entry_size as usize)?;
+            let mut r = entry.as_slice();
+
+            let base            = r.read_u64::<LittleEndian>()?;
+            let _alloc_base     = r.read_u64::<LittleEndian>()?;
+            let _alloc_protect  = r.read_u32::<LittleEndian>()?;
+            let _alignment      = r.read_u32::<LittleEndian>()?;
+            let size            = r.read_u64::<LittleEndian>()?;
+            let state           = r.read_u32::<LittleEndian>()?;
+            let protect         = r.read_u32::<LittleEndian>()?;
+
+            if state == MEM_COMMIT {
+                self.memory_info.push((base, size, page_protection_flags(protect)));
+            }
+        }
+
+        self.memory_info.sort_by_key(|&(base, _, _)| base);
+        Ok(())
+    }
+
+    /// Replaces the default read-only protection of captured ranges with `MemoryInfoListStream` data.
+    fn apply_protection(&mut self) {
+        for range in &mut self.ranges {
+            let index = self.memory_info.partition_point(|&(base, size, _)| base.saturating_add(size) <= range.va);
+
+            if let Some(&(base, _, flags)) = self.memory_info.get(index) {
+                if base <= range.va {
+                    range.flags = flags;
+                }
+            }
+        }
+    }
+
+    fn read_exception(&mut self) -> Result<()> {
+        let r = &mut self.reader;
+
+        let thread_id       = r.read_u32::<LittleEndian>()?;
+        let _alignment      = r.read_u32::<LittleEndian>()?;
+        let code            = r.read_u32::<LittleEndian>()?;
+        let flags           = r.read_u32::<LittleEndian>()?;
+        let record          = r.read_u64::<LittleEndian>()?;
+        let address         = r.read_u64::<LittleEndian>()?;
+        let num_parameters  = r.read_u32::<LittleEndian>()?;
+        let _alignment      = r.read_u32::<LittleEndian>()?;
+
+        let mut parameters = Vec::with_capacity(15);
+        for _ in 0..15 {
+            parameters.push(r.read_u64::<LittleEndian>()?);
+        }
+        parameters.truncate(num_parameters.min(15) as usize);
+
+        let context = Location::read(r)?;
+        let context = self.read_blob(context)?;
+
+        self.exception = Some(Exception { thread_id, code, flags, record, address, parameters, context });
+        Ok(())
+    }
+
+    /// Reads `buf.len()` bytes of the dumped process memory at `va`.
+    pub fn read_at(&mut self, va: u64, buf: &mut [u8]) -> Result<()> {
+        read_ranges(&mut self.reader, &self.ranges, va, buf)
+    }
+}
+
+impl<R> Minidump<R> {
+    pub const fn timestamp(&self) -> u32 {
+        self.timestamp
+    }
+
+    /// `MINIDUMP_TYPE` flags the dump was written with.
+    pub const fn flags(&self) -> u64 {
+        self.flags
+    }
+
+    pub const fn system_info(&self) -> &SystemInfo {
+        &self.system
+    }
+
+    pub fn modules(&self) -> &[Module] {
+        &self.modules
+    }
+
+    pub fn threads(&self) -> &[Thread] {
+        &self.threads
+    }
+
+    pub const fn exception(&self) -> Option<&Exception> {
+        self.exception.as_ref()
+    }
+
+    /// The captured memory ranges sorted by address.
+    pub fn ranges(&self) -> &[MemoryRange] {
+        &self.ranges
+    }
+
+    pub fn module_at(&self, va: u64) -> Option<&Module> {
+        self.modules.iter().find(|m| va >= m.base && va - m.base < m.size as u64)
+    }
+
+    fn context_offsets(&self) -> Option<(usize, usize, usize)> {
+        // (pc, sp, register size) within CONTEXT
+        match self.system.architecture {
+            PROCESSOR_ARCHITECTURE_AMD64 => Some((0xF8, 0x98, 8)),
+            PROCESSOR_ARCHITECTURE_INTEL => Some((0xB8, 0xC4, 4)),
+            PROCESSOR_ARCHITECTURE_ARM64 => Some((0x108, 0x100, 8)),
+            PROCESSOR_ARCHITECTURE_ARM   => Some((0x40, 0x38, 4)),
+            _ => None,
+        }
+    }
+
+    fn context_reg(context: &[u8], offset: usize, size: usize) -> Option<u64> {
+        let bytes = context.get(offset..offset + size)?;
+        let mut value = [0u8; 8];
+        value[..size].copy_from_slice(bytes);
+        Some(u64::from_le_bytes(value))
+    }
+
+    /// Instruction pointer from a thread or exception `CONTEXT` record.
+    pub fn pc(&self, context: &[u8]) -> Option<u64> {
+        let (pc, _, size) = self.context_offsets()?;
+        Self::context_reg(context, pc, size)
+    }
+
+    /// Stack pointer from a thread or exception `CONTEXT` record.
+    pub fn sp(&self, context: &[u8]) -> Option<u64> {
+        let (_, sp, size) = self.context_offsets()?;
+        Self::context_reg(context, sp, size)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// A 32-byte header followed by `body`, with the stream directory right after the header.
+    fn dump(num_streams: u32, body: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for field in [MINIDUMP_SIGNATURE, 0xA793, num_streams, 32, 0, 0, 0, 0] {
+            data.extend_from_slice(&field.to_le_bytes());
+        }
+        data.extend_from_slice(body);
+        data
+    }
+
+    fn error_kind(data: Vec<u8>) -> io::ErrorKind {
+        Minidump::new(Cursor::new(data)).err().expect("crafted dump should be rejected").kind()
+    }
+
+    #[test]
+    fn rejects_stream_count_past_end() {
+        assert_eq!(error_kind(dump(u32::MAX, &[])), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_thread_count_past_end() {
+        let mut body = Vec::new();
+        for field in [THREAD_LIST_STREAM, 4, 44, u32::MAX] {
+            body.extend_from_slice(&field.to_le_bytes());
+        }
+        assert_eq!(error_kind(dump(1, &body)), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_blob_size_past_end() {
+        // an exception stream whose context descriptor claims 4 GiB
+        let mut body = Vec::new();
+        for field in [EXCEPTION_STREAM, 168, 44] {
+            body.extend_from_slice(&field.to_le_bytes());
+        }
+        body.extend_from_slice(&[0; 160]);
+        body.extend_from_slice(&u32::MAX.to_le_bytes());
+        body.extend_from_slice(&0u32.to_le_bytes());
+        assert_eq!(error_kind(dump(1, &body)), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn module_at_top_of_address_space() {
+        let mut dump = Minidump::new(Cursor::new(dump(0, &[]))).unwrap();
+        dump.modules.push(Module {
+            base: u64::MAX - 1,
+            size: 16,
+            checksum: 0,
+            timestamp: 0,
+            name: String::new(),
+            pdb_guid: None,
+            pdb_age: 0,
+            pdb_name: None,
+        });
+
+        assert!(dump.module_at(u64::MAX).is_some());
+        assert!(dump.module_at(u64::MAX - 2).is_none());
+    }
+}
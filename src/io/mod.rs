@@ -1,9 +1,109 @@
 // #![allow(dead_code)]
 
-pub mod file;
+pub mod error;
+pub mod stream;
+pub mod cursor;
 pub mod read;
+pub mod write;
 pub mod byteorder;
+pub mod guid;
+pub mod pod;
+
+#[cfg(feature = "std")]
+pub mod file;
+#[cfg(feature = "std")]
+pub mod time;
+#[cfg(feature = "std")]
+pub mod sub;
+#[cfg(feature = "std")]
+pub mod decompress;
+#[cfg(feature = "std")]
+pub mod hash;
+#[cfg(feature = "std")]
+pub mod zip;
+#[cfg(feature = "std")]
+pub mod tar;
+#[cfg(feature = "std")]
+pub mod cpio;
+#[cfg(feature = "std")]
+pub mod pdb;
+#[cfg(feature = "std")]
+pub mod macho;
+#[cfg(feature = "std")]
+pub mod elfcore;
+#[cfg(feature = "std")]
+pub mod minidump;
+#[cfg(feature = "std")]
+pub mod address_space;
+#[cfg(feature = "std")]
+pub mod signature;
+#[cfg(feature = "std")]
+pub mod hexdump;
+#[cfg(feature = "std")]
+pub mod search;
+#[cfg(feature = "std")]
+pub mod text;
+#[cfg(feature = "std")]
+pub mod peek;
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub mod process;
 
-pub use file::{File, Result};
+pub use error::{Error, ErrorKind, Result};
+pub use stream::{Read, Write};
+pub use cursor::SliceCursor;
 pub use read::ReadExt;
-pub use byteorder::{ByteOrder, LittleEndian, BigEndian};
+pub use write::WriteExt;
+pub use byteorder::{ByteOrder, LittleEndian, BigEndian, Endian};
+pub use guid::Guid;
+pub use pod::{Pod, U16, U32, U64, U128, I16, I32, I64, I128};
+
+#[cfg(feature = "std")]
+pub use file::File;
+#[cfg(feature = "std")]
+pub use sub::SubReader;
+#[cfg(feature = "std")]
+pub use decompress::{Compression, Decoder};
+#[cfg(feature = "std")]
+pub use hash::{Checksum, HashingReader};
+#[cfg(feature = "std")]
+pub use zip::{ZipArchive, ZipEntry};
+#[cfg(feature = "std")]
+pub use tar::{EntryKind, TarReader};
+#[cfg(feature = "std")]
+pub use cpio::CpioReader;
+#[cfg(feature = "std")]
+pub use pdb::Pdb;
+#[cfg(feature = "std")]
+pub use macho::{MachO, MachFile, FatArch};
+#[cfg(feature = "std")]
+pub use elfcore::{ElfCore, MemoryRange};
+#[cfg(feature = "std")]
+pub use minidump::Minidump;
+#[cfg(feature = "std")]
+pub use address_space::{AddressSpace, BufferSpace, CurrentProcess, MappedImage, Protection, Region};
+#[cfg(feature = "std")]
+pub use signature::Signature;
+#[cfg(feature = "std")]
+pub use hexdump::HexDump;
+#[cfg(feature = "std")]
+pub use search::Searcher;
+#[cfg(feature = "std")]
+pub use text::{Encoding, TextReader};
+#[cfg(feature = "std")]
+pub use peek::PeekReader;
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub use process::ProcessMemory;
+
+#[cfg(feature = "serde")]
+pub mod serde;
+
+#[cfg(feature = "schema")]
+pub mod schema;
+
+#[cfg(feature = "source")]
+pub mod source;
+#[cfg(feature = "source")]
+pub use source::Source;
+
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+pub mod async_read;
@@ -0,0 +1,644 @@
+use std::collections::hash_map::{Entry, HashMap};
+use std::io::{self, Read, Seek, SeekFrom};
+
+use super::byteorder::LittleEndian;
+use super::error::Result;
+use super::guid::Guid;
+use super::read::ReadExt;
+
+const MSF_MAGIC: &[u8; 32] = b"Microsoft C/C++ MSF 7.00\r\n\x1aDS\0\0\0";
+
+const STREAM_PDB_INFO: usize    = 1;
+const STREAM_DBI: usize         = 3;
+
+// indices into the DBI optional debug header
+const DBG_OMAP_FROM_SRC: usize      = 4;
+const DBG_SECTION_HDR: usize        = 5;
+const DBG_SECTION_HDR_ORIG: usize   = 10;
+
+const S_LDATA32: u16    = 0x110C;
+const S_GDATA32: u16    = 0x110D;
+const S_PUB32: u16      = 0x110E;
+const S_LPROC32: u16    = 0x110F;
+const S_GPROC32: u16    = 0x1110;
+const S_PROCREF: u16    = 0x1125;
+const S_LPROCREF: u16   = 0x1127;
+
+const PUBSYM_FUNCTION: u32 = 0x0000_0002;
+
+const NIL_STREAM: u16 = 0xFFFF;
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn cstr(data: &[u8]) -> String {
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    String::from_utf8_lossy(&data[..end]).into_owned()
+}
+
+/// The multi-stream file container underlying a PDB.
+pub struct Msf<R> {
+    reader      : R,
+    block_size  : u32,
+    streams     : Vec<Option<(u32, Vec<u32>)>>,
+}
+
+impl<R: Read + Seek> Msf<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(0))?;
+
+        if &ReadExt::read_array::<32>(&mut reader)? != MSF_MAGIC {
+            return Err(invalid_data("not an MSF 7.00 file"));
+        }
+
+        let block_size      = reader.read_u32::<LittleEndian>()?;
+        let _free_map_block = reader.read_u32::<LittleEndian>()?;
+        let num_blocks      = reader.read_u32::<LittleEndian>()?;
+        let dir_size        = reader.read_u32::<LittleEndian>()?;
+        let _unknown        = reader.read_u32::<LittleEndian>()?;
+        let block_map_addr  = reader.read_u32::<LittleEndian>()?;
+
+        if !matches!(block_size, 512 | 1024 | 2048 | 4096 | 8192 | 16384 | 32768) {
+            return Err(invalid_data(format!("invalid MSF block size: {block_size}")));
+        }
+
+        // with every block index checked against num_blocks, no read goes past the end of the file
+        if num_blocks as u64 * block_size as u64 > file_len {
+            return Err(invalid_data(format!("{num_blocks} blocks of {block_size} bytes do not fit in a {file_len} byte file")));
+        }
+
+        if block_map_addr >= num_blocks {
+            return Err(invalid_data("block map out of range"));
+        }
+
+        let mut msf = Self {
+            reader,
+            block_size,
+            streams: Vec::new(),
+        };
+
+        // the block map lists the blocks holding the stream directory
+        let dir_blocks = dir_size.div_ceil(block_size);
+        if dir_blocks > num_blocks {
+            return Err(invalid_data(format!("stream directory of {dir_size} bytes is larger than the file")));
+        }
+
+        msf.seek_block(block_map_addr)?;
+        let mut blocks = Vec::with_capacity(dir_blocks as usize);
+        for _ in 0..dir_blocks {
+            let b = msf.reader.read_u32::<LittleEndian>()?;
+            if b >= num_blocks {
+                return Err(invalid_data("directory block out of range"));
+            }
+            blocks.push(b);
+        }
+
+        let dir = msf.read_blocks(dir_size, &blocks)?;
+        let mut r = dir.as_slice();
+
+        let num_streams = r.read_u32::<LittleEndian>()? as usize;
+        if num_streams > r.len() / 4 {
+            return Err(invalid_data("stream directory truncated"));
+        }
+
+        let mut sizes = Vec::with_capacity(num_streams);
+        for _ in 0..num_streams {
+            sizes.push(r.read_u32::<LittleEndian>()?);
+        }
+
+        for size in sizes {
+            if size == 0xFFFF_FFFF {
+                msf.streams.push(None);
+                continue;
+            }
+
+            let count = size.div_ceil(block_size);
+            if count > num_blocks || count as usize > r.len() / 4 {
+                return Err(invalid_data(format!("stream of {size} bytes does not fit in the file")));
+            }
+
+            let mut blocks = Vec::with_capacity(count as usize);
+            for _ in 0..count {
+                let b = r.read_u32::<LittleEndian>()?;
+                if b >= num_blocks {
+                    return Err(invalid_data("stream block out of range"));
+                }
+                blocks.push(b);
+            }
+
+            msf.streams.push(Some((size, blocks)));
+        }
+
+        Ok(msf)
+    }
+
+    fn seek_block(&mut self, block: u32) -> Result<()> {
+        self.reader.seek(SeekFrom::Start(block as u64 * self.block_size as u64))?;
+        Ok(())
+    }
+
+    fn read_blocks(&mut self, size: u32, blocks: &[u32]) -> Result<Vec<u8>> {
+        let mut data = Vec::with_capacity(size as usize);
+        let mut left = size as usize;
+
+        for &b in blocks {
+            let n = left.min(self.block_size as usize);
+            self.seek_block(b)?;
+            data.extend_from_slice(&self.reader.read_bytes(n)?);
+            left -= n;
+        }
+
+        Ok(data)
+    }
+
+    pub fn stream_count(&self) -> usize {
+        self.streams.len()
+    }
+
+    pub fn stream_size(&self, index: usize) -> Option<u32> {
+        self.streams.get(index)?.as_ref().map(|(size, _)| *size)
+    }
+
+    /// Reads a whole stream. Returns `None` for missing or nil streams.
+    pub fn read_stream(&mut self, index: usize) -> Result<Option<Vec<u8>>> {
+        let Some(Some((size, blocks))) = self.streams.get(index) else {
+            return Ok(None);
+        };
+
+        let (size, blocks) = (*size, blocks.clone());
+        self.read_blocks(size, &blocks).map(Some)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// A PE section header as stored in the DBI debug streams.
+#[derive(Debug, Clone)]
+pub struct SectionHeader {
+    pub name                : String,
+    pub virtual_size        : u32,
+    pub virtual_address     : u32,
+    pub size_of_raw_data    : u32,
+    pub characteristics     : u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    /// `S_PUB32`, usually a decorated name.
+    Public { function: bool },
+
+    /// `S_GPROC32`/`S_LPROC32` reached through a procedure reference.
+    Procedure { global: bool, len: u32 },
+
+    /// `S_GDATA32`/`S_LDATA32`.
+    Data { global: bool },
+}
+
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name    : String,
+    pub kind    : SymbolKind,
+    pub segment : u16,
+    pub offset  : u32,
+    pub rva     : u32,
+}
+
+struct DbiInfo {
+    sym_record_stream   : u16,
+    modules             : Vec<u16>,
+    dbg_streams         : Vec<u16>,
+}
+
+/// Symbol lookup over a PDB file.
+///
+/// Publics and globals are read from the symbol record stream shared by the public and
+/// global symbol hash streams, procedures are resolved through their module streams.
+/// Addresses go through OMAP when the image was rearranged after linking.
+///
+/// ```ignore
+/// let pdb = Pdb::new(io::File::open("game.pdb")?)?;
+/// let rva = pdb.rva_of("?Update@Player@@QAEXM@Z").unwrap();
+/// inline_hook_jmp(base, rva as usize, hook as usize, Some(&mut ORIGINAL), None)?;
+/// ```
+pub struct Pdb<R> {
+    msf         : Msf<R>,
+    guid        : Guid,
+    age         : u32,
+    sections    : Vec<SectionHeader>,
+    omap        : Vec<(u32, u32)>,
+    symbols     : Vec<Symbol>,
+    by_name     : HashMap<String, usize>,
+}
+
+impl<R: Read + Seek> Pdb<R> {
+    pub fn new(reader: R) -> Result<Self> {
+        let mut msf = Msf::new(reader)?;
+
+        let info = msf.read_stream(STREAM_PDB_INFO)?.ok_or_else(|| invalid_data("missing PDB info stream"))?;
+        let mut r = info.as_slice();
+        let _version    = r.read_u32::<LittleEndian>()?;
+        let _signature  = r.read_u32::<LittleEndian>()?;
+        let age         = r.read_u32::<LittleEndian>()?;
+        let guid        = r.read_guid::<LittleEndian>()?;
+
+        let dbi = msf.read_stream(STREAM_DBI)?.ok_or_else(|| invalid_data("missing DBI stream"))?;
+        let dbi = Self::parse_dbi(&dbi)?;
+
+        let dbg = |msf: &mut Msf<R>, i: usize| -> Result<Option<Vec<u8>>> {
+            match dbi.dbg_streams.get(i) {
+                Some(&s) if s != NIL_STREAM => msf.read_stream(s as usize),
+                _ => Ok(None),
+            }
+        };
+
+        let mut omap = Vec::new();
+        let mut section_data = dbg(&mut msf, DBG_SECTION_HDR)?;
+
+        if let Some(data) = dbg(&mut msf, DBG_OMAP_FROM_SRC)? {
+            let mut r = data.as_slice();
+            while r.len() >= 8 {
+                omap.push((r.read_u32::<LittleEndian>()?, r.read_u32::<LittleEndian>()?));
+            }
+
+            // symbols refer to the pre-OMAP layout
+            if let Some(orig) = dbg(&mut msf, DBG_SECTION_HDR_ORIG)? {
+                section_data = Some(orig);
+            }
+        }
+
+        let sections = Self::parse_sections(section_data.as_deref().unwrap_or_default())?;
+
+        let mut pdb = Self {
+            msf,
+            guid,
+            age,
+            sections,
+            omap,
+            symbols: Vec::new(),
+            by_name: HashMap::new(),
+        };
+
+        pdb.load_symbols(&dbi)?;
+        Ok(pdb)
+    }
+
+    fn parse_dbi(data: &[u8]) -> Result<DbiInfo> {
+        let mut r = data;
+        let signature           = r.read_i32::<LittleEndian>()?;
+        let _version            = r.read_u32::<LittleEndian>()?;
+        let _age                = r.read_u32::<LittleEndian>()?;
+        let _global_stream      = r.read_u16::<LittleEndian>()?;
+        let _build              = r.read_u16::<LittleEndian>()?;
+        let _public_stream      = r.read_u16::<LittleEndian>()?;
+        let _dll_version        = r.read_u16::<LittleEndian>()?;
+        let sym_record_stream   = r.read_u16::<LittleEndian>()?;
+        let _dll_rebuild        = r.read_u16::<LittleEndian>()?;
+        let mod_info_size       = r.read_u32::<LittleEndian>()? as usize;
+        let sec_contrib_size    = r.read_u32::<LittleEndian>()? as usize;
+        let sec_map_size        = r.read_u32::<LittleEndian>()? as usize;
+        let source_info_size    = r.read_u32::<LittleEndian>()? as usize;
+        let type_server_size    = r.read_u32::<LittleEndian>()? as usize;
+        let _mfc_type_server    = r.read_u32::<LittleEndian>()?;
+        let dbg_header_size     = r.read_u32::<LittleEndian>()? as usize;
+        let ec_size             = r.read_u32::<LittleEndian>()? as usize;
+        let _flags              = r.read_u16::<LittleEndian>()?;
+        let _machine            = r.read_u16::<LittleEndian>()?;
+        let _padding            = r.read_u32::<LittleEndian>()?;
+
+        if signature != -1 {
+            return Err(invalid_data("unsupported pre-VC4.1 DBI stream"));
+        }
+
+        let take = |r: &mut &[u8], n: usize| -> Result<Vec<u8>> {
+            if n > r.len() {
+                return Err(invalid_data("DBI substream out of range"));
+            }
+            let (a, b) = r.split_at(n);
+            *r = b;
+            Ok(a.to_vec())
+        };
+
+        let mod_info = take(&mut r, mod_info_size)?;
+        take(&mut r, sec_contrib_size + sec_map_size + source_info_size + type_server_size + ec_size)?;
+        let dbg_header = take(&mut r, dbg_header_size)?;
+
+        let mut modules = Vec::new();
+        let mut m = mod_info.as_slice();
+        while m.len() >= 64 {
+            let start_len = m.len();
+            let _unused             = m.read_u32::<LittleEndian>()?;
+            let _section_contrib    = ReadExt::read_array::<28>(&mut m)?;
+            let _flags              = m.read_u16::<LittleEndian>()?;
+            let stream              = m.read_u16::<LittleEndian>()?;
+            let _sym_size           = m.read_u32::<LittleEndian>()?;
+            let _c11_size           = m.read_u32::<LittleEndian>()?;
+            let _c13_size           = m.read_u32::<LittleEndian>()?;
+            let _file_count         = m.read_u16::<LittleEndian>()?;
+            let _pad                = m.read_u16::<LittleEndian>()?;
+            let _unused2            = m.read_u32::<LittleEndian>()?;
+            let _file_name_index    = m.read_u32::<LittleEndian>()?;
+            let _pdb_path_index     = m.read_u32::<LittleEndian>()?;
+
+            // module name and object file name, then align to 4
+            for _ in 0..2 {
+                let end = m.iter().position(|&b| b == 0).ok_or_else(|| invalid_data("unterminated module name"))?;
+                m = &m[end + 1..];
+            }
+
+            let consumed = start_len - m.len();
+            let pad = (4 - consumed % 4) % 4;
+            m = &m[pad.min(m.len())..];
+
+            modules.push(stream);
+        }
+
+        let mut dbg_streams = Vec::new();
+        let mut d = dbg_header.as_slice();
+        while d.len() >= 2 {
+            dbg_streams.push(d.read_u16::<LittleEndian>()?);
+        }
+
+        Ok(DbiInfo {
+            sym_record_stream,
+            modules,
+            dbg_streams,
+        })
+    }
+
+    fn parse_sections(mut r: &[u8]) -> Result<Vec<SectionHeader>> {
+        let mut sections = Vec::new();
+
+        while r.len() >= 40 {
+            let name                = cstr(&ReadExt::read_array::<8>(&mut r)?);
+            let virtual_size        = r.read_u32::<LittleEndian>()?;
+            let virtual_address     = r.read_u32::<LittleEndian>()?;
+            let size_of_raw_data    = r.read_u32::<LittleEndian>()?;
+            let _raw_ptr            = r.read_u32::<LittleEndian>()?;
+            let _reloc_ptr          = r.read_u32::<LittleEndian>()?;
+            let _line_ptr           = r.read_u32::<LittleEndian>()?;
+            let _reloc_count        = r.read_u16::<LittleEndian>()?;
+            let _line_count         = r.read_u16::<LittleEndian>()?;
+            let characteristics     = r.read_u32::<LittleEndian>()?;
+
+            sections.push(SectionHeader {
+                name,
+                virtual_size,
+                virtual_address,
+                size_of_raw_data,
+                characteristics,
+            });
+        }
+
+        Ok(sections)
+    }
+
+    /// Translates a section:offset pair to an RVA of the final image.
+    fn to_rva(&self, segment: u16, offset: u32) -> Option<u32> {
+        let section = self.sections.get((segment as usize).checked_sub(1)?)?;
+        let rva = section.virtual_address.checked_add(offset)?;
+
+        if self.omap.is_empty() {
+            return Some(rva);
+        }
+
+        let i = self.omap.partition_point(|&(from, _)| from <= rva).checked_sub(1)?;
+        let (from, to) = self.omap[i];
+
+        if to == 0 {
+            return None;
+        }
+
+        to.checked_add(rva - from)
+    }
+
+    fn load_symbols(&mut self, dbi: &DbiInfo) -> Result<()> {
+        let records = match self.msf.read_stream(dbi.sym_record_stream as usize)? {
+            Some(r) => r,
+            None => return Ok(()),
+        };
+
+        let mut module_cache: HashMap<u16, Vec<u8>> = HashMap::new();
+        let mut r = records.as_slice();
+
+        while r.len() >= 4 {
+            let len = r.read_u16::<LittleEndian>()? as usize;
+            if len < 2 || len > r.len() {
+                break;
+            }
+
+            let (record, rest) = r.split_at(len);
+            r = rest;
+
+            let mut rec = record;
+            let kind = rec.read_u16::<LittleEndian>()?;
+
+            let symbol = match kind {
+                S_PUB32 => {
+                    let flags   = rec.read_u32::<LittleEndian>()?;
+                    let offset  = rec.read_u32::<LittleEndian>()?;
+                    let segment = rec.read_u16::<LittleEndian>()?;
+                    Some((cstr(rec), SymbolKind::Public { function: flags & PUBSYM_FUNCTION != 0 }, segment, offset))
+                },
+
+                S_GDATA32 | S_LDATA32 => {
+                    let _type   = rec.read_u32::<LittleEndian>()?;
+                    let offset  = rec.read_u32::<LittleEndian>()?;
+                    let segment = rec.read_u16::<LittleEndian>()?;
+                    Some((cstr(rec), SymbolKind::Data { global: kind == S_GDATA32 }, segment, offset))
+                },
+
+                S_PROCREF | S_LPROCREF => {
+                    let _sum_name   = rec.read_u32::<LittleEndian>()?;
+                    let sym_offset  = rec.read_u32::<LittleEndian>()? as usize;
+                    let module      = rec.read_u16::<LittleEndian>()?;
+                    self.resolve_procedure(dbi, &mut module_cache, module, sym_offset)?
+                },
+
+                _ => None,
+            };
+
+            if let Some((name, kind, segment, offset)) = symbol {
+                if let Some(rva) = self.to_rva(segment, offset) {
+                    self.symbols.push(Symbol { name, kind, segment, offset, rva });
+                }
+            }
+        }
+
+        self.symbols.sort_by_key(|s| s.rva);
+
+        for (i, s) in self.symbols.iter().enumerate() {
+            self.by_name.entry(s.name.clone()).or_insert(i);
+        }
+
+        Ok(())
+    }
+
+    /// Reads the `S_GPROC32`/`S_LPROC32` a procedure reference points at. `module` is 1-based.
+    fn resolve_procedure(&mut self, dbi: &DbiInfo, cache: &mut HashMap<u16, Vec<u8>>, module: u16, sym_offset: usize) -> Result<Option<(String, SymbolKind, u16, u32)>> {
+        let Some(&stream) = dbi.modules.get((module as usize).wrapping_sub(1)) else {
+            return Ok(None);
+        };
+
+        if stream == NIL_STREAM {
+            return Ok(None);
+        }
+
+        if let Entry::Vacant(e) = cache.entry(stream) {
+            e.insert(self.msf.read_stream(stream as usize)?.unwrap_or_default());
+        }
+
+        let data = &cache[&stream];
+        let Some(mut rec) = data.get(sym_offset..) else {
+            return Ok(None);
+        };
+
+        let len = rec.read_u16::<LittleEndian>()? as usize;
+        if len < 2 || len > rec.len() {
+            return Ok(None);
+        }
+
+        let mut rec = &rec[..len];
+        let kind = rec.read_u16::<LittleEndian>()?;
+
+        if kind != S_GPROC32 && kind != S_LPROC32 {
+            return Ok(None);
+        }
+
+        let _parent     = rec.read_u32::<LittleEndian>()?;
+        let _end        = rec.read_u32::<LittleEndian>()?;
+        let _next       = rec.read_u32::<LittleEndian>()?;
+        let proc_len    = rec.read_u32::<LittleEndian>()?;
+        let _dbg_start  = rec.read_u32::<LittleEndian>()?;
+        let _dbg_end    = rec.read_u32::<LittleEndian>()?;
+        let _type       = rec.read_u32::<LittleEndian>()?;
+        let offset      = rec.read_u32::<LittleEndian>()?;
+        let segment     = rec.read_u16::<LittleEndian>()?;
+        let _flags      = rec.read_u8()?;
+
+        let kind = SymbolKind::Procedure { global: kind == S_GPROC32, len: proc_len };
+        Ok(Some((cstr(rec), kind, segment, offset)))
+    }
+
+    /// GUID to match against the RSDS CodeView record of the image.
+    pub const fn guid(&self) -> Guid {
+        self.guid
+    }
+
+    pub const fn age(&self) -> u32 {
+        self.age
+    }
+
+    pub fn matches(&self, guid: &Guid, age: u32) -> bool {
+        self.guid == *guid && self.age == age
+    }
+
+    pub fn sections(&self) -> &[SectionHeader] {
+        &self.sections
+    }
+
+    /// All symbols, sorted by RVA.
+    pub fn symbols(&self) -> &[Symbol] {
+        &self.symbols
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Symbol> {
+        self.by_name.get(name).map(|&i| &self.symbols[i])
+    }
+
+    pub fn rva_of(&self, name: &str) -> Option<u32> {
+        self.find(name).map(|s| s.rva)
+    }
+
+    /// The symbol at or nearest below `rva`, with the distance from its start.
+    pub fn symbol_at(&self, rva: u32) -> Option<(&Symbol, u32)> {
+        let i = self.symbols.partition_point(|s| s.rva <= rva).checked_sub(1)?;
+        let s = &self.symbols[i];
+        Some((s, rva - s.rva))
+    }
+
+    pub fn msf(&mut self) -> &mut Msf<R> {
+        &mut self.msf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    // linked by lld from tests/fixtures/sample.ll: foo and mainCRTStartup in .text, gvar in .data
+    const SAMPLE: &[u8] = include_bytes!("../../tests/fixtures/sample.pdb");
+
+    fn sample() -> Pdb<Cursor<&'static [u8]>> {
+        Pdb::new(Cursor::new(SAMPLE)).unwrap()
+    }
+
+    #[test]
+    fn reads_section_map() {
+        let pdb = sample();
+        assert_eq!(pdb.age(), 1);
+        assert_eq!(pdb.guid().to_string(), "CC17CA76-68DC-585E-4C4C-44205044422E");
+
+        let sections: Vec<_> = pdb.sections().iter().map(|s| (s.name.as_str(), s.virtual_address, s.virtual_size)).collect();
+        assert_eq!(sections, [(".text", 0x1000, 0x24), (".rdata", 0x2000, 0x54), (".data", 0x3000, 4), (".pdata", 0x4000, 0xC)]);
+    }
+
+    #[test]
+    fn reads_public_symbols() {
+        let pdb = sample();
+
+        let publics: Vec<_> = pdb
+            .symbols()
+            .iter()
+            .filter_map(|s| match s.kind {
+                SymbolKind::Public { function } => Some((s.name.as_str(), function, s.segment, s.offset)),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(publics, [("foo", true, 1, 0), ("mainCRTStartup", true, 1, 0x10), ("gvar", false, 3, 0)]);
+    }
+
+    #[test]
+    fn looks_up_rvas() {
+        let pdb = sample();
+        assert_eq!(pdb.rva_of("foo"), Some(0x1000));
+        assert_eq!(pdb.rva_of("mainCRTStartup"), Some(0x1010));
+        assert_eq!(pdb.rva_of("gvar"), Some(0x3000));
+        assert_eq!(pdb.rva_of("missing"), None);
+
+        let (symbol, distance) = pdb.symbol_at(0x1014).unwrap();
+        assert_eq!((symbol.name.as_str(), distance), ("mainCRTStartup", 4));
+        assert!(pdb.symbol_at(0xFFF).is_none());
+    }
+
+    fn msf_error(data: Vec<u8>) -> io::ErrorKind {
+        Msf::new(Cursor::new(data)).err().expect("corrupt MSF should be rejected").kind()
+    }
+
+    fn patch_u32(data: &mut [u8], offset: usize, value: u32) {
+        data[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    #[test]
+    fn rejects_corrupt_msf() {
+        assert_eq!(msf_error(SAMPLE[..SAMPLE.len() / 2].to_vec()), io::ErrorKind::InvalidData);
+
+        let mut data = SAMPLE.to_vec();
+        patch_u32(&mut data, 44, u32::MAX);
+        assert_eq!(msf_error(data), io::ErrorKind::InvalidData);
+
+        // the sample directory fits in one block, and the first entry after the stream sizes is a stream block
+        let u32_at = |offset: usize| u32::from_le_bytes(SAMPLE[offset..offset + 4].try_into().unwrap()) as usize;
+        let block_size = u32_at(32);
+        let dir = u32_at(u32_at(52) * block_size) * block_size;
+        let mut data = SAMPLE.to_vec();
+        patch_u32(&mut data, dir + 4 + u32_at(dir) * 4, 0xFFFF);
+        assert_eq!(msf_error(data), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,226 @@
+use std::io::{self, BufRead, Read};
+
+use super::byteorder::ByteOrder;
+use super::error::Result;
+
+const DEFAULT_CAPACITY: usize = 0x2000;
+
+/// A buffered reader that can look ahead without consuming, for sources that cannot seek.
+///
+/// Up to `capacity` bytes can be peeked at once, and up to `capacity` bytes
+/// already read can be pushed back with [`PeekReader::unread`]. It also
+/// implements [`BufRead`], so `read_line` and friends work as usual. [`PeekReader::pos`]
+/// counts the bytes consumed since creation, so peeks do not move it and
+/// unreads move it back.
+pub struct PeekReader<R> {
+    inner       : R,
+
+    // consumed history followed by the unread lookahead, which starts at head
+    buf         : Vec<u8>,
+    head        : usize,
+    capacity    : usize,
+    pos         : u64,
+}
+
+impl<R: Read> PeekReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_capacity(DEFAULT_CAPACITY, inner)
+    }
+
+    pub fn with_capacity(capacity: usize, inner: R) -> Self {
+        Self {
+            inner,
+            buf         : Vec::new(),
+            head        : 0,
+            capacity    : capacity.max(1),
+            pos         : 0,
+        }
+    }
+
+    pub const fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of bytes consumed so far.
+    pub const fn pos(&self) -> u64 {
+        self.pos
+    }
+
+    /// The lookahead currently buffered.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buf[self.head..]
+    }
+
+    pub const fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Returns the inner reader; buffered lookahead is lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    fn available(&self) -> usize {
+        self.buf.len() - self.head
+    }
+
+    /// Reads until `n` bytes are buffered or the source ends, returning how many are.
+    fn fill_to(&mut self, n: usize) -> Result<usize> {
+        if n > self.capacity {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("cannot look {n} bytes ahead with a capacity of {}", self.capacity)));
+        }
+
+        while self.available() < n {
+            // keep at most `capacity` bytes of history for unread
+            let drop = self.head.saturating_sub(self.capacity);
+            self.buf.drain(..drop);
+            self.head -= drop;
+
+            let len = self.buf.len();
+            self.buf.resize(len + self.capacity, 0);
+
+            let read = loop {
+                match self.inner.read(&mut self.buf[len..]) {
+                    Ok(read) => break read,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                    Err(e) => {
+                        self.buf.truncate(len);
+                        return Err(e);
+                    },
+                }
+            };
+
+            self.buf.truncate(len + read);
+            if read == 0 {
+                break;
+            }
+        }
+
+        Ok(self.available().min(n))
+    }
+
+    /// Up to `n` bytes of lookahead; fewer only at the end of the source.
+    pub fn peek(&mut self, n: usize) -> Result<&[u8]> {
+        let n = self.fill_to(n)?;
+        Ok(&self.buf[self.head..self.head + n])
+    }
+
+    /// `true` once everything has been consumed.
+    pub fn at_eof(&mut self) -> Result<bool> {
+        Ok(self.fill_to(1)? == 0)
+    }
+
+    pub fn peek_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let bytes = self.peek(N)?;
+        bytes.try_into().map_err(|_| io::Error::new(io::ErrorKind::UnexpectedEof, format!("only {} of {N} bytes left to peek", bytes.len())))
+    }
+
+    pub fn peek_u8(&mut self) -> Result<u8> {
+        Ok(self.peek_array::<1>()?[0])
+    }
+
+    pub fn peek_i8(&mut self) -> Result<i8> {
+        Ok(self.peek_u8()? as i8)
+    }
+
+    pub fn peek_u16<T: ByteOrder>(&mut self) -> Result<u16> {
+        T::read_u16(&self.peek_array::<2>()?[..])
+    }
+
+    pub fn peek_i16<T: ByteOrder>(&mut self) -> Result<i16> {
+        T::read_i16(&self.peek_array::<2>()?[..])
+    }
+
+    pub fn peek_u32<T: ByteOrder>(&mut self) -> Result<u32> {
+        T::read_u32(&self.peek_array::<4>()?[..])
+    }
+
+    pub fn peek_i32<T: ByteOrder>(&mut self) -> Result<i32> {
+        T::read_i32(&self.peek_array::<4>()?[..])
+    }
+
+    pub fn peek_u64<T: ByteOrder>(&mut self) -> Result<u64> {
+        T::read_u64(&self.peek_array::<8>()?[..])
+    }
+
+    pub fn peek_i64<T: ByteOrder>(&mut self) -> Result<i64> {
+        T::read_i64(&self.peek_array::<8>()?[..])
+    }
+
+    pub fn peek_u128<T: ByteOrder>(&mut self) -> Result<u128> {
+        T::read_u128(&self.peek_array::<16>()?[..])
+    }
+
+    pub fn peek_i128<T: ByteOrder>(&mut self) -> Result<i128> {
+        T::read_i128(&self.peek_array::<16>()?[..])
+    }
+
+    pub fn peek_f32<T: ByteOrder>(&mut self) -> Result<f32> {
+        T::read_f32(&self.peek_array::<4>()?[..])
+    }
+
+    pub fn peek_f64<T: ByteOrder>(&mut self) -> Result<f64> {
+        T::read_f64(&self.peek_array::<8>()?[..])
+    }
+
+    /// `true` if the lookahead starts with `prefix`, such as a magic number.
+    pub fn starts_with(&mut self, prefix: &[u8]) -> Result<bool> {
+        Ok(self.peek(prefix.len())? == prefix)
+    }
+
+    /// Consumes `n` bytes without copying them out.
+    pub fn skip(&mut self, n: u64) -> Result<()> {
+        let mut left = n;
+
+        while left > 0 {
+            let step = self.fill_to(self.capacity.min(left as usize).max(1))?;
+            if step == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("skipped {} of {n} bytes", n - left)));
+            }
+
+            self.consume(step);
+            left -= step as u64;
+        }
+
+        Ok(())
+    }
+
+    /// Pushes back the last `n` consumed bytes so they are read again.
+    ///
+    /// The position can always go back to `capacity` bytes before the furthest point consumed.
+    pub fn unread(&mut self, n: usize) -> Result<()> {
+        if n > self.head {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("cannot unread {n} bytes, only {} are buffered", self.head)));
+        }
+
+        self.head -= n;
+        self.pos -= n as u64;
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for PeekReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let data = self.fill_buf()?;
+        let n = data.len().min(buf.len());
+        buf[..n].copy_from_slice(&data[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: Read> BufRead for PeekReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.available() == 0 {
+            self.fill_to(1)?;
+        }
+
+        Ok(&self.buf[self.head..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        let amt = amt.min(self.available());
+        self.head += amt;
+        self.pos += amt as u64;
+    }
+}
@@ -0,0 +1,263 @@
+use alloc::format;
+use alloc::string::String;
+use core::any::type_name;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::mem::{align_of, size_of};
+use core::slice;
+
+use super::byteorder::{ByteOrder, Endian};
+use super::error::{Error, ErrorKind, Result};
+
+fn invalid_data(msg: impl Into<String>) -> Error {
+    Error::new(ErrorKind::InvalidData, msg.into())
+}
+
+/// Plain old data: types that can be viewed directly from raw bytes.
+///
+/// Implement it for `#[repr(C)]` structs built from [`U16`], [`U32`] and friends,
+/// byte arrays and other `Pod` types, then view a mapped file or buffer in place:
+///
+/// ```ignore
+/// #[repr(C)]
+/// #[derive(Clone, Copy)]
+/// struct FileHeader {
+///     machine     : U16<LittleEndian>,
+///     sections    : U16<LittleEndian>,
+///     timestamp   : U32<LittleEndian>,
+/// }
+///
+/// unsafe impl Pod for FileHeader {}
+///
+/// let header = FileHeader::view(&data[offset..])?;
+/// println!("{:#x}", header.machine.get());
+/// ```
+///
+/// Since every field of such a struct has an alignment of 1, views of it never fail on alignment.
+///
+/// # Safety
+///
+/// Every bit pattern must be a valid value of the type, and the type must have
+/// no padding bytes. Structs need `#[repr(C)]` or `#[repr(transparent)]`.
+pub unsafe trait Pod: Copy {
+    /// Views the start of `bytes` as `Self`; `bytes` may be longer.
+    fn view(bytes: &[u8]) -> Result<&Self> {
+        Self::view_prefix(bytes).map(|(v, _)| v)
+    }
+
+    /// Views the start of `bytes` as `Self` and returns the remaining bytes too.
+    fn view_prefix(bytes: &[u8]) -> Result<(&Self, &[u8])> {
+        check::<Self>(bytes.as_ptr(), bytes.len(), size_of::<Self>(), align_of::<Self>())?;
+        let (head, rest) = bytes.split_at(size_of::<Self>());
+
+        // SAFETY: size and alignment were checked, and any bytes are a valid Self
+        Ok((unsafe { &*head.as_ptr().cast::<Self>() }, rest))
+    }
+
+    fn view_mut(bytes: &mut [u8]) -> Result<&mut Self> {
+        check::<Self>(bytes.as_ptr(), bytes.len(), size_of::<Self>(), align_of::<Self>())?;
+
+        // SAFETY: as in view_prefix, and the borrow of bytes is exclusive
+        Ok(unsafe { &mut *bytes.as_mut_ptr().cast::<Self>() })
+    }
+
+    /// Views `bytes` as an array of `Self`, which must divide it exactly.
+    fn view_slice(bytes: &[u8]) -> Result<&[Self]> {
+        let size = size_of::<Self>();
+        if size == 0 || bytes.len() % size != 0 {
+            return Err(invalid_data(format!("{} bytes are not a whole number of {}", bytes.len(), type_name::<Self>())));
+        }
+
+        check::<Self>(bytes.as_ptr(), bytes.len(), 0, align_of::<Self>())?;
+
+        // SAFETY: alignment was checked and the length is a multiple of the element size
+        Ok(unsafe { slice::from_raw_parts(bytes.as_ptr().cast::<Self>(), bytes.len() / size) })
+    }
+
+    /// Copies `Self` out of the start of `bytes`, without any alignment requirement.
+    fn read_from(bytes: &[u8]) -> Result<Self> {
+        check::<Self>(bytes.as_ptr(), bytes.len(), size_of::<Self>(), 1)?;
+
+        // SAFETY: length was checked, the read is unaligned and any bytes are a valid Self
+        Ok(unsafe { bytes.as_ptr().cast::<Self>().read_unaligned() })
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        // SAFETY: Self has no padding, so all of its bytes are initialized
+        unsafe { slice::from_raw_parts((self as *const Self).cast::<u8>(), size_of::<Self>()) }
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        // SAFETY: as in as_bytes, and any bytes written back form a valid Self
+        unsafe { slice::from_raw_parts_mut((self as *mut Self).cast::<u8>(), size_of::<Self>()) }
+    }
+}
+
+fn check<T>(ptr: *const u8, len: usize, size: usize, align: usize) -> Result<()> {
+    if len < size {
+        return Err(invalid_data(format!("{} needs {size} bytes, got {len}", type_name::<T>())));
+    }
+
+    if (ptr as usize) % align != 0 {
+        return Err(invalid_data(format!("{ptr:p} is not aligned for {} ({align} bytes)", type_name::<T>())));
+    }
+
+    Ok(())
+}
+
+unsafe impl Pod for u8 {}
+unsafe impl Pod for i8 {}
+unsafe impl Pod for u16 {}
+unsafe impl Pod for i16 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for u64 {}
+unsafe impl Pod for i64 {}
+unsafe impl Pod for u128 {}
+unsafe impl Pod for i128 {}
+unsafe impl Pod for usize {}
+unsafe impl Pod for isize {}
+unsafe impl Pod for f32 {}
+unsafe impl Pod for f64 {}
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+macro_rules! endian_int {
+    ($(#[$doc:meta])* $name:ident, $ty:ty, $size:literal) => {
+        $(#[$doc])*
+        ///
+        /// Stored as bytes in the order `E`, with an alignment of 1 so it can sit at any offset.
+        #[repr(transparent)]
+        pub struct $name<E> {
+            bytes   : [u8; $size],
+            _order  : PhantomData<E>,
+        }
+
+        impl<E: ByteOrder> $name<E> {
+            pub const ZERO: Self = Self::from_bytes([0; $size]);
+
+            pub const fn new(v: $ty) -> Self {
+                Self::from_bytes(match E::ENDIAN {
+                    Endian::Little => v.to_le_bytes(),
+                    Endian::Big => v.to_be_bytes(),
+                })
+            }
+
+            pub const fn get(&self) -> $ty {
+                match E::ENDIAN {
+                    Endian::Little => <$ty>::from_le_bytes(self.bytes),
+                    Endian::Big => <$ty>::from_be_bytes(self.bytes),
+                }
+            }
+
+            pub fn set(&mut self, v: $ty) {
+                *self = Self::new(v);
+            }
+        }
+
+        impl<E> $name<E> {
+            pub const fn from_bytes(bytes: [u8; $size]) -> Self {
+                Self { bytes, _order: PhantomData }
+            }
+
+            pub const fn to_bytes(&self) -> [u8; $size] {
+                self.bytes
+            }
+        }
+
+        impl<E> Clone for $name<E> {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl<E> Copy for $name<E> {}
+
+        impl<E> PartialEq for $name<E> {
+            fn eq(&self, other: &Self) -> bool {
+                self.bytes == other.bytes
+            }
+        }
+
+        impl<E> Eq for $name<E> {}
+
+        impl<E> Hash for $name<E> {
+            fn hash<H: Hasher>(&self, state: &mut H) {
+                self.bytes.hash(state);
+            }
+        }
+
+        impl<E> Default for $name<E> {
+            fn default() -> Self {
+                Self::from_bytes([0; $size])
+            }
+        }
+
+        impl<E: ByteOrder> fmt::Debug for $name<E> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(&self.get(), f)
+            }
+        }
+
+        impl<E: ByteOrder> fmt::Display for $name<E> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(&self.get(), f)
+            }
+        }
+
+        impl<E: ByteOrder> From<$ty> for $name<E> {
+            fn from(v: $ty) -> Self {
+                Self::new(v)
+            }
+        }
+
+        impl<E: ByteOrder> From<$name<E>> for $ty {
+            fn from(v: $name<E>) -> Self {
+                v.get()
+            }
+        }
+
+        // SAFETY: a transparent byte array, PhantomData takes no space
+        unsafe impl<E> Pod for $name<E> {}
+    };
+}
+
+endian_int!(
+    /// A `u16` in byte order `E`.
+    U16, u16, 2
+);
+
+endian_int!(
+    /// A `u32` in byte order `E`.
+    U32, u32, 4
+);
+
+endian_int!(
+    /// A `u64` in byte order `E`.
+    U64, u64, 8
+);
+
+endian_int!(
+    /// A `u128` in byte order `E`.
+    U128, u128, 16
+);
+
+endian_int!(
+    /// An `i16` in byte order `E`.
+    I16, i16, 2
+);
+
+endian_int!(
+    /// An `i32` in byte order `E`.
+    I32, i32, 4
+);
+
+endian_int!(
+    /// An `i64` in byte order `E`.
+    I64, i64, 8
+);
+
+endian_int!(
+    /// An `i128` in byte order `E`.
+    I128, i128, 16
+);
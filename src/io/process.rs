@@ -0,0 +1,467 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::os::unix::fs::FileExt;
+
+use super::address_space::{AddressSpace, Protection, Region};
+use super::error::Result;
+
+// process_vm_readv takes at most IOV_MAX segments per call
+const MAX_IOVECS: usize = 1024;
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn unmapped(va: u64) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, format!("address {va:#x} is not mapped"))
+}
+
+/// End of `len` bytes at `va`; a range that wraps past the top of the address space is unmapped.
+fn range_end(va: u64, len: usize) -> Result<u64> {
+    va.checked_add(len as u64).ok_or_else(|| unmapped(va))
+}
+
+/// Errors that mean the address is not accessible, as opposed to the process being unusable.
+fn is_fault(e: &io::Error) -> bool {
+    matches!(e.raw_os_error(), Some(libc::EFAULT) | Some(libc::EIO) | Some(libc::EINVAL))
+}
+
+fn page_size() -> u64 {
+    match unsafe { libc::sysconf(libc::_SC_PAGESIZE) } {
+        n if n > 0 => n as u64,
+        _ => 0x1000,
+    }
+}
+
+/// One line of `/proc/<pid>/maps`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mapping {
+    pub region  : Region,
+    pub shared  : bool,
+    pub offset  : u64,
+
+    /// Backing file, or a pseudo name like `[heap]` or `[stack]`.
+    pub path    : Option<String>,
+}
+
+/// Parses a `maps` file such as `/proc/self/maps`.
+pub fn read_maps(path: &str) -> Result<Vec<Mapping>> {
+    let maps = std::fs::read_to_string(path)?;
+    let mut mappings = Vec::new();
+
+    for line in maps.lines() {
+        let mut rest = line;
+        let mut field = || {
+            rest = rest.trim_start();
+            let (field, tail) = rest.split_at(rest.find(' ').unwrap_or(rest.len()));
+            rest = tail;
+            field
+        };
+
+        let (range, perms, offset, _dev, _inode) = (field(), field(), field(), field(), field());
+        let bad_line = || invalid_data(format!("invalid maps line: {line}"));
+
+        let (start, end) = range.split_once('-').ok_or_else(bad_line)?;
+        let start = u64::from_str_radix(start, 16).map_err(|_| bad_line())?;
+        let end = u64::from_str_radix(end, 16).map_err(|_| bad_line())?;
+        let offset = u64::from_str_radix(offset, 16).map_err(|_| bad_line())?;
+        let perms = perms.as_bytes();
+        let path = rest.trim_start();
+
+        mappings.push(Mapping {
+            region: Region {
+                start,
+                size: end.saturating_sub(start),
+                protection: Protection {
+                    read    : perms.first() == Some(&b'r'),
+                    write   : perms.get(1) == Some(&b'w'),
+                    execute : perms.get(2) == Some(&b'x'),
+                },
+            },
+            shared  : perms.get(3) == Some(&b's'),
+            offset,
+            path    : (!path.is_empty()).then(|| path.to_string()),
+        });
+    }
+
+    Ok(mappings)
+}
+
+/// The memory of another process on Linux, as a stream positioned by virtual address.
+///
+/// Reads and writes use `process_vm_readv` / `process_vm_writev` and fall back
+/// to `/proc/<pid>/mem` where those are unavailable. Writes through the `mem`
+/// file also reach read-only pages, so code can be patched without changing
+/// its protection. Both need ptrace access to the target, which a parent has
+/// over its children by default.
+///
+/// As a [`Read`] the stream returns what is readable from the position and
+/// fails with [`io::ErrorKind::NotFound`] at an unmapped address, so
+/// [`ReadExt`](super::ReadExt) and the parsers in this module work on live
+/// memory. [`ProcessMemory::read_tolerant`] reads across holes instead. The end
+/// of the stream is the end of the highest mapping.
+#[derive(Debug)]
+pub struct ProcessMemory {
+    pid         : libc::pid_t,
+    pos         : u64,
+    mem         : File,
+    writable    : bool,
+    vm_calls    : bool,
+    page_size   : u64,
+    mappings    : Vec<Mapping>,
+}
+
+impl ProcessMemory {
+    pub fn open(pid: u32) -> Result<Self> {
+        let path = format!("/proc/{pid}/mem");
+        let (mem, writable) = match OpenOptions::new().read(true).write(true).open(&path) {
+            Ok(mem) => (mem, true),
+            Err(_) => (File::open(&path)?, false),
+        };
+
+        let mut process = Self {
+            pid         : pid as libc::pid_t,
+            pos         : 0,
+            mem,
+            writable,
+            vm_calls    : true,
+            page_size   : page_size(),
+            mappings    : Vec::new(),
+        };
+
+        process.refresh()?;
+        Ok(process)
+    }
+
+    pub const fn pid(&self) -> u32 {
+        self.pid as u32
+    }
+
+    /// Reloads the mappings, which are a snapshot taken when opening.
+    pub fn refresh(&mut self) -> Result<()> {
+        self.mappings = read_maps(&format!("/proc/{}/maps", self.pid))?;
+        Ok(())
+    }
+
+    pub fn mappings(&self) -> &[Mapping] {
+        &self.mappings
+    }
+
+    pub fn mapping_at(&self, va: u64) -> Option<&Mapping> {
+        self.mappings.iter().find(|m| m.region.contains(va))
+    }
+
+    /// Lowest address a file is mapped at, such as the load address of a library.
+    pub fn module_base(&self, name: &str) -> Option<u64> {
+        self.mappings
+            .iter()
+            .filter(|m| m.path.as_deref().is_some_and(|p| p == name || p.rsplit('/').next() == Some(name)))
+            .map(|m| m.region.start)
+            .min()
+    }
+
+    /// Splits `[va, va + len)` into per-page iovecs, so a fault only cuts the transfer short.
+    fn remote_iovecs(&self, va: u64, end: u64) -> Vec<libc::iovec> {
+        let mut iovecs = Vec::new();
+        let mut addr = va;
+
+        while addr < end && iovecs.len() < MAX_IOVECS {
+            let next = (addr / self.page_size + 1).saturating_mul(self.page_size);
+            let n = next.min(end) - addr;
+            iovecs.push(libc::iovec { iov_base: addr as usize as *mut libc::c_void, iov_len: n as usize });
+            addr += n;
+        }
+
+        iovecs
+    }
+
+    /// Reads from `va` until the first inaccessible byte, returning how many bytes were read.
+    pub fn read_some(&mut self, va: u64, buf: &mut [u8]) -> Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let end = range_end(va, buf.len())?;
+
+        if self.vm_calls {
+            let remote = self.remote_iovecs(va, end);
+            let len = remote.iter().map(|v| v.iov_len).sum();
+            let local = libc::iovec { iov_base: buf.as_mut_ptr().cast(), iov_len: len };
+
+            let n = unsafe { libc::process_vm_readv(self.pid, &local, 1, remote.as_ptr(), remote.len() as _, 0) };
+            if n >= 0 {
+                return Ok(n as usize);
+            }
+
+            match io::Error::last_os_error() {
+                e if is_fault(&e) => return Ok(0),
+                // blocked by seccomp or an old kernel, the mem file still works
+                e if matches!(e.raw_os_error(), Some(libc::ENOSYS) | Some(libc::EPERM)) => self.vm_calls = false,
+                e => return Err(e),
+            }
+        }
+
+        match self.mem.read_at(buf, va) {
+            Ok(n) => Ok(n),
+            Err(e) if is_fault(&e) => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Writes at `va` until the first inaccessible byte, returning how many bytes were written.
+    pub fn write_some(&mut self, va: u64, data: &[u8]) -> Result<usize> {
+        if data.is_empty() {
+            return Ok(0);
+        }
+
+        let end = range_end(va, data.len())?;
+
+        if self.vm_calls {
+            let remote = self.remote_iovecs(va, end);
+            let len = remote.iter().map(|v| v.iov_len).sum();
+            let local = libc::iovec { iov_base: data.as_ptr() as *mut libc::c_void, iov_len: len };
+
+            let n = unsafe { libc::process_vm_writev(self.pid, &local, 1, remote.as_ptr(), remote.len() as _, 0) };
+            if n > 0 {
+                return Ok(n as usize);
+            }
+
+            // a read-only page faults here but can still be written through the mem file
+            let e = io::Error::last_os_error();
+            if n < 0 && !is_fault(&e) && !matches!(e.raw_os_error(), Some(libc::ENOSYS) | Some(libc::EPERM)) {
+                return Err(e);
+            }
+        }
+
+        if !self.writable {
+            return Err(io::Error::new(io::ErrorKind::PermissionDenied, format!("/proc/{}/mem is not writable", self.pid)));
+        }
+
+        match self.mem.write_at(data, va) {
+            Ok(n) => Ok(n),
+            Err(e) if is_fault(&e) => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Fills `buf` from `va`, zeroing the pages that cannot be read.
+    ///
+    /// Returns the unreadable ranges, merged and in ascending order.
+    pub fn read_tolerant(&mut self, va: u64, buf: &mut [u8]) -> Result<Vec<Range<u64>>> {
+        let end = range_end(va, buf.len())?;
+        let mut gaps: Vec<Range<u64>> = Vec::new();
+        let mut addr = va;
+
+        while addr < end {
+            let offset = (addr - va) as usize;
+            addr += self.read_some(addr, &mut buf[offset..])? as u64;
+
+            if addr < end {
+                let next = (addr / self.page_size + 1).saturating_mul(self.page_size).min(end);
+                buf[(addr - va) as usize..(next - va) as usize].fill(0);
+
+                match gaps.last_mut() {
+                    Some(gap) if gap.end == addr => gap.end = next,
+                    _ => gaps.push(addr..next),
+                }
+
+                addr = next;
+            }
+        }
+
+        Ok(gaps)
+    }
+
+    pub fn write_at(&mut self, va: u64, data: &[u8]) -> Result<()> {
+        let mut done = 0;
+
+        while done < data.len() {
+            let addr = va + done as u64;
+            match self.write_some(addr, &data[done..])? {
+                0 => return Err(unmapped(addr)),
+                n => done += n,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl AddressSpace for ProcessMemory {
+    fn read_at(&mut self, va: u64, buf: &mut [u8]) -> Result<()> {
+        let mut done = 0;
+
+        while done < buf.len() {
+            let addr = va + done as u64;
+            match self.read_some(addr, &mut buf[done..])? {
+                0 => return Err(unmapped(addr)),
+                n => done += n,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn regions(&self) -> Vec<Region> {
+        self.mappings.iter().map(|m| m.region).collect()
+    }
+}
+
+impl Read for ProcessMemory {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.read_some(self.pos, buf)?;
+        if n == 0 && !buf.is_empty() {
+            return Err(unmapped(self.pos));
+        }
+
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Write for ProcessMemory {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let n = self.write_some(self.pos, data)?;
+        if n == 0 && !data.is_empty() {
+            return Err(unmapped(self.pos));
+        }
+
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for ProcessMemory {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let pos = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+            SeekFrom::End(delta) => self.mappings.iter().map(|m| m.region.end()).max().unwrap_or(0).checked_add_signed(delta),
+        };
+
+        self.pos = pos.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position"))?;
+        Ok(self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::{Child, Command};
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+
+    /// A `sleep` child, killed when dropped.
+    struct Sleeper(Child);
+
+    impl Sleeper {
+        fn spawn() -> Self {
+            Self(Command::new("sleep").arg("30").spawn().expect("cannot spawn sleep"))
+        }
+
+        /// Opens the child once exec has mapped `sleep`; spawn can return before that.
+        fn memory(&self) -> ProcessMemory {
+            let mut memory = ProcessMemory::open(self.0.id()).unwrap();
+
+            for _ in 0..500 {
+                if memory.module_base("sleep").is_some() {
+                    return memory;
+                }
+
+                thread::sleep(Duration::from_millis(10));
+                memory.refresh().unwrap();
+            }
+
+            panic!("sleep was never mapped");
+        }
+    }
+
+    impl Drop for Sleeper {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    #[test]
+    fn reads_a_mapped_image() {
+        let child = Sleeper::spawn();
+        let mut memory = child.memory();
+
+        let base = memory.module_base("sleep").unwrap();
+        let mut magic = [0u8; 4];
+        memory.read_at(base, &mut magic).unwrap();
+        assert_eq!(&magic, b"\x7FELF");
+
+        memory.seek(SeekFrom::Start(base)).unwrap();
+        let mut header = [0u8; 16];
+        memory.read_exact(&mut header).unwrap();
+        assert_eq!(header[..4], magic);
+        assert_eq!(memory.stream_position().unwrap(), base + 16);
+    }
+
+    #[test]
+    fn reports_unmapped_gaps() {
+        let child = Sleeper::spawn();
+        let mut memory = child.memory();
+        let page = memory.page_size;
+
+        // a readable mapping followed by at least one unmapped page
+        let end = memory
+            .mappings()
+            .iter()
+            .filter(|m| m.region.protection.read && m.path.as_deref() != Some("[vsyscall]"))
+            .map(|m| m.region.end())
+            .find(|&end| memory.mapping_at(end).is_none())
+            .expect("no mapping is followed by a hole");
+
+        let mut buf = vec![0xAAu8; 2 * page as usize];
+        let gaps = memory.read_tolerant(end - page, &mut buf).unwrap();
+
+        assert_eq!(gaps, vec![end..end + page]);
+        assert!(buf[page as usize..].iter().all(|&b| b == 0));
+        assert_eq!(memory.read_at(end, &mut [0u8; 1]).unwrap_err().kind(), io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn reads_at_the_top_of_the_address_space() {
+        let child = Sleeper::spawn();
+        let mut memory = child.memory();
+
+        let mut buf = [0xAAu8; 8];
+        assert_eq!(memory.read_tolerant(u64::MAX - 8, &mut buf).unwrap(), vec![u64::MAX - 8..u64::MAX]);
+        assert_eq!(memory.read_tolerant(u64::MAX - 2, &mut buf).unwrap_err().kind(), io::ErrorKind::NotFound);
+
+        memory.seek(SeekFrom::Start(u64::MAX - 2)).unwrap();
+        assert!(memory.read_exact(&mut buf).is_err());
+    }
+
+    #[test]
+    fn writes_round_trip() {
+        let child = Sleeper::spawn();
+        let mut memory = child.memory();
+
+        let va = memory
+            .mappings()
+            .iter()
+            .find(|m| m.region.protection.read && m.region.protection.write && !m.shared)
+            .expect("no writable mapping")
+            .region
+            .start;
+
+        let original = memory.read_vec(va, 8).unwrap();
+        let patch = *b"mlpatch!";
+
+        memory.write_at(va, &patch).unwrap();
+        assert_eq!(memory.read_vec(va, 8).unwrap(), patch);
+
+        memory.write_at(va, &original).unwrap();
+        assert_eq!(memory.read_vec(va, 8).unwrap(), original);
+    }
+}
@@ -1,12 +1,19 @@
 
-use std::io::Read;
-use super::file::Result;
+use alloc::{vec, vec::Vec};
+use core::net::{Ipv4Addr, Ipv6Addr};
+#[cfg(feature = "std")]
+use std::time::{Duration, SystemTime};
+use super::error::Result;
+use super::stream::Read;
 use super::byteorder::{ByteOrder, LittleEndian};
+use super::guid::Guid;
+#[cfg(feature = "std")]
+use super::time;
 
 #[allow(unused_imports)]
 use super::byteorder::BigEndian;
 
-/// Extends [`Read`] with methods for reading numbers. (For `std::io`.)
+/// Extends [`Read`] with methods for reading numbers.
 ///
 /// Most of the methods defined here have an unconstrained type parameter that
 /// must be explicitly instantiated. Typically, it is instantiated with either
@@ -14,17 +21,17 @@ use super::byteorder::BigEndian;
 ///
 /// # Errors
 ///
-/// read_\<number\> methods return the same errors as [`Read::read`].
+/// read_\<number\> methods return the same errors as [`Read::read_exact`].
 pub trait ReadExt: Read {
     fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
         let mut buf = [0u8; N];
-        self.read(&mut buf)?;
+        self.read_exact(&mut buf)?;
         Ok(buf)
     }
 
     fn read_bytes(&mut self, size: usize) -> Result<Vec<u8>> {
         let mut buf = vec![0u8; size];
-        self.read(&mut buf)?;
+        self.read_exact(&mut buf)?;
         Ok(buf)
     }
 
@@ -60,6 +67,14 @@ pub trait ReadExt: Read {
         T::read_u64(self)
     }
 
+    fn read_i128<T: ByteOrder>(&mut self) -> Result<i128> {
+        T::read_i128(self)
+    }
+
+    fn read_u128<T: ByteOrder>(&mut self) -> Result<u128> {
+        T::read_u128(self)
+    }
+
     fn read_f32<T: ByteOrder>(&mut self) -> Result<f32> {
         T::read_f32(self)
     }
@@ -68,6 +83,65 @@ pub trait ReadExt: Read {
         T::read_f64(self)
     }
 
+    /// Reads a Windows FILETIME (100ns ticks since 1601-01-01 UTC).
+    #[cfg(feature = "std")]
+    fn read_filetime<T: ByteOrder>(&mut self) -> Result<SystemTime> {
+        time::filetime_to_system_time(T::read_u64(self)?)
+    }
+
+    /// Reads a FILETIME-encoded interval (100ns ticks).
+    #[cfg(feature = "std")]
+    fn read_filetime_duration<T: ByteOrder>(&mut self) -> Result<Duration> {
+        Ok(time::filetime_duration(T::read_u64(self)?))
+    }
+
+    /// Reads a signed 32-bit `time_t`.
+    #[cfg(feature = "std")]
+    fn read_unix_time32<T: ByteOrder>(&mut self) -> Result<SystemTime> {
+        time::unix_to_system_time(T::read_i32(self)? as i64)
+    }
+
+    /// Reads a signed 64-bit `time_t`.
+    #[cfg(feature = "std")]
+    fn read_unix_time64<T: ByteOrder>(&mut self) -> Result<SystemTime> {
+        time::unix_to_system_time(T::read_i64(self)?)
+    }
+
+    /// Reads an MS-DOS time/date pair, time first as laid out in FAT and ZIP.
+    #[cfg(feature = "std")]
+    fn read_dos_datetime<T: ByteOrder>(&mut self) -> Result<SystemTime> {
+        let t = T::read_u16(&mut *self)?;
+        let d = T::read_u16(&mut *self)?;
+        time::dos_to_system_time(d, t)
+    }
+
+    /// Reads a Mac HFS timestamp (seconds since 1904-01-01).
+    #[cfg(feature = "std")]
+    fn read_hfs_time<T: ByteOrder>(&mut self) -> Result<SystemTime> {
+        time::hfs_to_system_time(T::read_u32(self)?)
+    }
+
+    /// Reads a GUID. `LittleEndian` gives the Microsoft mixed-endian layout,
+    /// `BigEndian` the RFC 4122 layout.
+    fn read_guid<T: ByteOrder>(&mut self) -> Result<Guid> {
+        Ok(Guid {
+            data1: T::read_u32(&mut *self)?,
+            data2: T::read_u16(&mut *self)?,
+            data3: T::read_u16(&mut *self)?,
+            data4: ReadExt::read_array(self)?,
+        })
+    }
+
+    /// Reads an IPv4 address stored as a 32-bit integer. Network order is `BigEndian`.
+    fn read_ipv4<T: ByteOrder>(&mut self) -> Result<Ipv4Addr> {
+        Ok(Ipv4Addr::from(T::read_u32(self)?))
+    }
+
+    /// Reads an IPv6 address stored as a 128-bit integer. Network order is `BigEndian`.
+    fn read_ipv6<T: ByteOrder>(&mut self) -> Result<Ipv6Addr> {
+        Ok(Ipv6Addr::from(T::read_u128(self)?))
+    }
+
     fn i8(&mut self) -> i8 {
         self.read_i8().unwrap()
     }
@@ -100,6 +174,14 @@ pub trait ReadExt: Read {
         self.read_u64::<T>().unwrap()
     }
 
+    fn i128<T: ByteOrder>(&mut self) -> i128 {
+        self.read_i128::<T>().unwrap()
+    }
+
+    fn u128<T: ByteOrder>(&mut self) -> u128 {
+        self.read_u128::<T>().unwrap()
+    }
+
     fn f32<T: ByteOrder>(&mut self) -> f32 {
         self.read_f32::<T>().unwrap()
     }
@@ -0,0 +1,434 @@
+use std::collections::HashMap;
+
+use serde_yaml::{Mapping, Value as Yaml};
+
+use crate::io::Endian;
+use super::error::{Error, Result};
+use super::expr::Expr;
+
+/// A primitive number type such as `u4le` or `f8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Prim {
+    pub signed  : bool,
+    pub float   : bool,
+    pub size    : usize,
+    pub endian  : Option<Endian>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TypeRef {
+    Prim(Prim),
+    Str,
+    Strz,
+    User(String),
+    Switch {
+        on      : Expr,
+        cases   : Vec<(Expr, TypeRef)>,
+        default : Option<Box<TypeRef>>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Repeat {
+    Once,
+    Eos,
+    Expr(Expr),
+    Until(Expr),
+}
+
+/// One entry of `seq` or `instances`.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Attr {
+    pub id          : String,
+
+    // None means raw bytes
+    pub ty          : Option<TypeRef>,
+    pub size        : Option<Expr>,
+    pub size_eos    : bool,
+    pub contents    : Option<Vec<u8>>,
+    pub terminator  : Option<u8>,
+    pub include     : bool,
+    pub consume     : bool,
+    pub encoding    : String,
+    pub repeat      : Repeat,
+    pub cond        : Option<Expr>,
+
+    // instances only
+    pub pos         : Option<Expr>,
+    pub value       : Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct TypeDef {
+    pub name        : String,
+    pub endian      : Option<Endian>,
+    pub seq         : Vec<Attr>,
+    pub instances   : Vec<Attr>,
+}
+
+/// A format description loaded from a Kaitai Struct style YAML document.
+///
+/// The supported subset covers `meta` (`id`, `endian`, `encoding` and
+/// documentation keys such as `title` or `license`), `seq`,
+/// `instances` and nested `types`, with attributes using `type`, `size`,
+/// `size-eos`, `contents`, `terminator`, `encoding`, `repeat`, `if`, `pos`
+/// and `value`. Types are the primitives `u1`..`u8`, `s1`..`s8`, `f4`, `f8`
+/// (optionally suffixed with `le` or `be`), `str`, `strz`, user types and
+/// `switch-on` blocks. Unsupported keys are rejected rather than ignored.
+///
+/// ```yaml
+/// meta:
+///   id: archive
+///   endian: le
+/// seq:
+///   - id: magic
+///     contents: "ARC1"
+///   - id: count
+///     type: u4
+///   - id: entries
+///     type: entry
+///     repeat: expr
+///     repeat-expr: count
+/// types:
+///   entry:
+///     seq:
+///       - id: name
+///         type: strz
+///       - id: size
+///         type: u4
+///     instances:
+///       data:
+///         pos: _root._io.size - size
+///         size: size
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Schema {
+    pub(crate) root     : TypeDef,
+    pub(crate) types    : HashMap<String, TypeDef>,
+}
+
+impl Schema {
+    pub fn from_yaml(src: &str) -> Result<Self> {
+        let doc: Yaml = serde_yaml::from_str(src)?;
+        let doc = doc.as_mapping().ok_or_else(|| invalid("document is not a mapping"))?;
+
+        let meta = doc.get("meta").map(|m| m.as_mapping().ok_or_else(|| invalid("meta is not a mapping"))).transpose()?;
+        let id = meta.and_then(|m| m.get("id")).and_then(Yaml::as_str).unwrap_or("root").to_string();
+        let encoding = meta.and_then(|m| m.get("encoding")).and_then(Yaml::as_str).unwrap_or("UTF-8").to_string();
+
+        let mut loader = Loader { encoding, types: HashMap::new() };
+        let root = loader.type_def(&id, doc, None)?;
+
+        let schema = Self { root, types: loader.types };
+        schema.check_types()?;
+        Ok(schema)
+    }
+
+    /// `meta.id` of the document, which is also the type name of the root struct.
+    pub fn id(&self) -> &str {
+        &self.root.name
+    }
+
+    pub(crate) fn type_def(&self, name: &str) -> Result<&TypeDef> {
+        if name == self.root.name {
+            return Ok(&self.root);
+        }
+
+        self.types.get(name).ok_or_else(|| Error::UnknownType(name.to_string()))
+    }
+
+    /// Fails early on references to undefined user types.
+    fn check_types(&self) -> Result<()> {
+        fn check(schema: &Schema, ty: &TypeRef) -> Result<()> {
+            match ty {
+                TypeRef::User(name) => schema.type_def(name).map(|_| ()),
+                TypeRef::Switch { cases, default, .. } => {
+                    cases.iter().map(|(_, t)| t).chain(default.as_deref()).try_for_each(|t| check(schema, t))
+                },
+                _ => Ok(()),
+            }
+        }
+
+        std::iter::once(&self.root)
+            .chain(self.types.values())
+            .flat_map(|t| t.seq.iter().chain(&t.instances))
+            .filter_map(|a| a.ty.as_ref())
+            .try_for_each(|ty| check(self, ty))
+    }
+}
+
+struct Loader {
+    encoding    : String,
+    types       : HashMap<String, TypeDef>,
+}
+
+impl Loader {
+    fn type_def(&mut self, name: &str, map: &Mapping, parent_endian: Option<Endian>) -> Result<TypeDef> {
+        const KEYS: &[&str] = &["meta", "seq", "instances", "types", "doc", "doc-ref"];
+
+        // documentation-only keys are accepted, anything that changes parsing is not
+        const META_KEYS: &[&str] = &[
+            "id", "endian", "encoding", "title", "application", "file-extension", "xref", "license",
+            "tags", "ks-version",
+        ];
+
+        check_keys(map, KEYS, name)?;
+
+        let meta = map.get("meta").map(|m| m.as_mapping().ok_or_else(|| invalid(format!("meta of {name} is not a mapping")))).transpose()?;
+        if let Some(meta) = meta {
+            check_keys(meta, META_KEYS, &format!("meta of {name}"))?;
+        }
+
+        let endian = match meta.and_then(|m| m.get("endian")) {
+            None => parent_endian,
+            Some(e) => Some(endian(e.as_str().unwrap_or_default()).ok_or_else(|| invalid(format!("invalid endian in {name}")))?),
+        };
+
+        let encoding = meta.and_then(|m| m.get("encoding")).and_then(Yaml::as_str).map(str::to_string);
+        let saved = encoding.map(|e| std::mem::replace(&mut self.encoding, e));
+
+        // nested types are visible by name everywhere, like a flat namespace
+        if let Some(types) = map.get("types") {
+            let types = types.as_mapping().ok_or_else(|| invalid(format!("types of {name} is not a mapping")))?;
+
+            for (key, def) in types {
+                let key = key.as_str().ok_or_else(|| invalid("type name is not a string"))?;
+                let def = def.as_mapping().ok_or_else(|| invalid(format!("type {key} is not a mapping")))?;
+
+                let ty = self.type_def(key, def, endian)?;
+                if self.types.insert(key.to_string(), ty).is_some() {
+                    return Err(invalid(format!("type {key} is defined twice")));
+                }
+            }
+        }
+
+        let mut seq = Vec::new();
+        if let Some(items) = map.get("seq") {
+            let items = items.as_sequence().ok_or_else(|| invalid(format!("seq of {name} is not a list")))?;
+
+            for item in items {
+                let item = item.as_mapping().ok_or_else(|| invalid(format!("seq item of {name} is not a mapping")))?;
+                let id = item.get("id").and_then(Yaml::as_str).unwrap_or("_unnamed").to_string();
+                seq.push(self.attr(id, item, false)?);
+            }
+        }
+
+        let mut instances = Vec::new();
+        if let Some(items) = map.get("instances") {
+            let items = items.as_mapping().ok_or_else(|| invalid(format!("instances of {name} is not a mapping")))?;
+
+            for (id, item) in items {
+                let id = id.as_str().ok_or_else(|| invalid("instance name is not a string"))?.to_string();
+                let item = item.as_mapping().ok_or_else(|| invalid(format!("instance {id} is not a mapping")))?;
+                instances.push(self.attr(id, item, true)?);
+            }
+        }
+
+        if let Some(saved) = saved {
+            self.encoding = saved;
+        }
+
+        Ok(TypeDef { name: name.to_string(), endian, seq, instances })
+    }
+
+    fn attr(&self, id: String, map: &Mapping, instance: bool) -> Result<Attr> {
+        const KEYS: &[&str] = &[
+            "id", "type", "size", "size-eos", "contents", "terminator", "include", "consume",
+            "encoding", "repeat", "repeat-expr", "repeat-until", "if", "doc", "doc-ref",
+        ];
+
+        for key in map.keys() {
+            let key = key.as_str().unwrap_or_default();
+            let allowed = KEYS.contains(&key) || (instance && (key == "pos" || key == "value"));
+
+            if !allowed {
+                return Err(invalid(format!("unsupported key {key:?} in {id}")));
+            }
+        }
+
+        let get_expr = |key: &str| map.get(key).map(|v| expr(v, &id, key)).transpose();
+        let get_bool = |key: &str, default: bool| map.get(key).map_or(Ok(default), |v| {
+            v.as_bool().ok_or_else(|| invalid(format!("{key} of {id} is not a boolean")))
+        });
+
+        let ty = match map.get("type") {
+            None => None,
+            Some(Yaml::String(name)) => Some(type_ref(name)?),
+            Some(Yaml::Mapping(switch)) => Some(self.switch(&id, switch)?),
+            Some(_) => return Err(invalid(format!("type of {id} is neither a name nor a switch"))),
+        };
+
+        let contents = match map.get("contents") {
+            None => None,
+            Some(Yaml::String(s)) => Some(s.as_bytes().to_vec()),
+            Some(Yaml::Sequence(items)) => {
+                let mut bytes = Vec::new();
+                for item in items {
+                    match item {
+                        Yaml::String(s) => bytes.extend_from_slice(s.as_bytes()),
+                        Yaml::Number(n) => bytes.push(n.as_u64().and_then(|n| u8::try_from(n).ok()).ok_or_else(|| invalid(format!("contents of {id} has a non-byte value")))?),
+                        _ => return Err(invalid(format!("contents of {id} has a non-byte value"))),
+                    }
+                }
+                Some(bytes)
+            },
+            Some(_) => return Err(invalid(format!("contents of {id} is neither a string nor a list"))),
+        };
+
+        let repeat = match map.get("repeat").and_then(Yaml::as_str) {
+            None => Repeat::Once,
+            Some("eos") => Repeat::Eos,
+            Some("expr") => Repeat::Expr(get_expr("repeat-expr")?.ok_or_else(|| invalid(format!("{id} has no repeat-expr")))?),
+            Some("until") => Repeat::Until(get_expr("repeat-until")?.ok_or_else(|| invalid(format!("{id} has no repeat-until")))?),
+            Some(other) => return Err(invalid(format!("invalid repeat {other:?} in {id}"))),
+        };
+
+        let terminator = match map.get("terminator") {
+            None => matches!(ty, Some(TypeRef::Strz)).then_some(0),
+            Some(v) => Some(v.as_u64().and_then(|n| u8::try_from(n).ok()).ok_or_else(|| invalid(format!("terminator of {id} is not a byte")))?),
+        };
+
+        Ok(Attr {
+            size        : get_expr("size")?,
+            size_eos    : get_bool("size-eos", false)?,
+            include     : get_bool("include", false)?,
+            consume     : get_bool("consume", true)?,
+            encoding    : map.get("encoding").and_then(Yaml::as_str).unwrap_or(&self.encoding).to_string(),
+            cond        : get_expr("if")?,
+            pos         : get_expr("pos")?,
+            value       : get_expr("value")?,
+            id,
+            ty,
+            contents,
+            terminator,
+            repeat,
+        })
+    }
+
+    fn switch(&self, id: &str, map: &Mapping) -> Result<TypeRef> {
+        let on = map.get("switch-on").ok_or_else(|| invalid(format!("type of {id} has no switch-on")))?;
+        let on = expr(on, id, "switch-on")?;
+
+        let cases = map.get("cases").and_then(Yaml::as_mapping).ok_or_else(|| invalid(format!("switch of {id} has no cases")))?;
+        let mut default = None;
+        let mut list = Vec::new();
+
+        for (key, ty) in cases {
+            let ty = type_ref(ty.as_str().ok_or_else(|| invalid(format!("case type of {id} is not a name")))?)?;
+
+            if key.as_str() == Some("_") {
+                default = Some(Box::new(ty));
+            } else {
+                list.push((expr(key, id, "cases")?, ty));
+            }
+        }
+
+        Ok(TypeRef::Switch { on, cases: list, default })
+    }
+}
+
+fn check_keys(map: &Mapping, keys: &[&str], owner: &str) -> Result<()> {
+    for key in map.keys() {
+        let key = key.as_str().unwrap_or_default();
+        if !keys.contains(&key) {
+            return Err(invalid(format!("unsupported key {key:?} in {owner}")));
+        }
+    }
+
+    Ok(())
+}
+
+fn invalid(msg: impl Into<String>) -> Error {
+    Error::Schema(msg.into())
+}
+
+fn endian(s: &str) -> Option<Endian> {
+    match s {
+        "le" => Some(Endian::Little),
+        "be" => Some(Endian::Big),
+        _ => None,
+    }
+}
+
+/// Expressions may be written as YAML numbers and booleans as well as strings.
+fn expr(v: &Yaml, id: &str, key: &str) -> Result<Expr> {
+    match v {
+        Yaml::String(s) => Expr::parse(s),
+        Yaml::Bool(b) => Ok(Expr::Bool(*b)),
+        Yaml::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Ok(Expr::Int(i as i128))
+            } else if let Some(u) = n.as_u64() {
+                Ok(Expr::Int(u as i128))
+            } else {
+                Ok(Expr::Float(n.as_f64().unwrap_or_default()))
+            }
+        },
+        _ => Err(invalid(format!("{key} of {id} is not an expression"))),
+    }
+}
+
+fn type_ref(name: &str) -> Result<TypeRef> {
+    match name {
+        "str" => return Ok(TypeRef::Str),
+        "strz" => return Ok(TypeRef::Strz),
+        _ => {},
+    }
+
+    // a user type may end in "le" or "be" too, so the suffix only counts after a primitive
+    let (base, endian) = match name.split_at_checked(2) {
+        Some((base, "")) => (base, None),
+        Some((base, suffix)) if endian(suffix).is_some() => (base, endian(suffix)),
+        _ => return Ok(TypeRef::User(name.to_string())),
+    };
+
+    let (signed, float) = match base {
+        "u1" | "u2" | "u4" | "u8" => (false, false),
+        "s1" | "s2" | "s4" | "s8" => (true, false),
+        "f4" | "f8" => (true, true),
+        _ => return Ok(TypeRef::User(name.to_string())),
+    };
+
+    Ok(TypeRef::Prim(Prim { signed, float, size: (base.as_bytes()[1] - b'0') as usize, endian }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(src: &str) -> Result<Schema> {
+        Schema::from_yaml(src)
+    }
+
+    #[test]
+    fn accepts_supported_keys() {
+        let schema = load("meta:\n  id: demo\n  title: Demo\n  endian: le\ndoc: x\nseq:\n  - id: a\n    type: u4\ntypes:\n  t:\n    meta:\n      endian: be\n    seq:\n      - id: b\n        type: u2\n").unwrap();
+        assert_eq!(schema.id(), "demo");
+        assert_eq!(schema.type_def("t").unwrap().endian, Some(Endian::Big));
+    }
+
+    #[test]
+    fn rejects_unsupported_type_keys() {
+        for src in [
+            "sequence:\n  - id: a\n    type: u1\n",
+            "seq: []\nenums:\n  e:\n    0: zero\n",
+            "seq: []\nparams:\n  - id: len\n",
+            "types:\n  t:\n    seq: []\n    enums: {}\n",
+        ] {
+            assert!(load(src).unwrap_err().to_string().contains("unsupported key"), "{src}");
+        }
+    }
+
+    #[test]
+    fn rejects_unsupported_meta_keys() {
+        for src in [
+            "meta:\n  id: a\n  imports:\n    - common\n",
+            "meta:\n  id: a\n  bit-endian: le\n",
+            "types:\n  t:\n    meta:\n      bit-endian: be\n",
+        ] {
+            assert!(load(src).unwrap_err().to_string().contains("unsupported key"), "{src}");
+        }
+
+        assert!(load("meta: le\n").is_err());
+    }
+}
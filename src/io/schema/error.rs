@@ -0,0 +1,31 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("io error")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid yaml")]
+    Yaml(#[from] serde_yaml::Error),
+
+    #[error("invalid schema: {0}")]
+    Schema(String),
+
+    #[error("invalid expression {0:?}: {1}")]
+    Syntax(String, String),
+
+    #[error("{0}")]
+    Eval(String),
+
+    #[error("unknown type: {0}")]
+    UnknownType(String),
+
+    #[error("unexpected contents in {field}: expected {expected:02X?}, found {actual:02X?}")]
+    Contents {
+        field       : String,
+        expected    : Vec<u8>,
+        actual      : Vec<u8>,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,436 @@
+use super::error::{Error, Result};
+use super::value::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+    Not,
+    BitNot,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add, Sub, Mul, Div, Rem,
+    Shl, Shr, BitAnd, BitOr, BitXor,
+    Eq, Ne, Lt, Le, Gt, Ge,
+    And, Or,
+}
+
+/// A parsed Kaitai style expression, as used by `size`, `repeat-expr`, `if`, `pos` and `switch-on`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Int(i128),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    Name(String),
+    Member(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Cond(Box<Expr>, Box<Expr>, Box<Expr>),
+}
+
+/// Resolves the free names of an expression.
+pub trait Scope {
+    fn lookup(&self, name: &str) -> Option<Value>;
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Int(i128),
+    Float(f64),
+    Str(String),
+    Ident(String),
+    Op(&'static str),
+}
+
+const OPERATORS: [&str; 27] = [
+    "<<", ">>", "<=", ">=", "==", "!=", "&&", "||",
+    "+", "-", "*", "/", "%", "&", "|", "^", "~", "!", "<", ">", "(", ")", "[", "]", ".", "?", ":",
+];
+
+fn tokenize(src: &str) -> Result<Vec<Token>> {
+    let syntax = |msg: &str| Error::Syntax(src.to_string(), msg.to_string());
+    let bytes = src.as_bytes();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let c = bytes[i];
+
+        if c.is_ascii_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_' || bytes[i] == b'.') {
+                // a dot followed by a letter is member access, not a fraction
+                if bytes[i] == b'.' && !bytes.get(i + 1).is_some_and(u8::is_ascii_digit) {
+                    break;
+                }
+                i += 1;
+            }
+
+            let text = src[start..i].replace('_', "");
+            let (radix, digits) = match text.get(..2) {
+                Some("0x") | Some("0X") => (16, &text[2..]),
+                Some("0b") | Some("0B") => (2, &text[2..]),
+                Some("0o") | Some("0O") => (8, &text[2..]),
+                _ => (10, text.as_str()),
+            };
+
+            if radix == 10 && digits.contains('.') {
+                tokens.push(Token::Float(digits.parse().map_err(|_| syntax("invalid number"))?));
+            } else {
+                tokens.push(Token::Int(i128::from_str_radix(digits, radix).map_err(|_| syntax("invalid number"))?));
+            }
+        } else if c.is_ascii_alphabetic() || c == b'_' {
+            let start = i;
+            while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(src[start..i].to_string()));
+        } else if c == b'"' || c == b'\'' {
+            let end = src[i + 1..].find(c as char).ok_or_else(|| syntax("unterminated string"))?;
+            tokens.push(Token::Str(src[i + 1..i + 1 + end].to_string()));
+            i += end + 2;
+        } else {
+            let op = OPERATORS
+                .iter()
+                .find(|op| src[i..].starts_with(**op))
+                .ok_or_else(|| syntax(&format!("unexpected character {:?}", c as char)))?;
+            tokens.push(Token::Op(op));
+            i += op.len();
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    src     : &'a str,
+    tokens  : Vec<Token>,
+    pos     : usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, msg: &str) -> Error {
+        Error::Syntax(self.src.to_string(), msg.to_string())
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat_op(&mut self, op: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Op(o)) if *o == op) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Ident(w)) if w == word) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect_op(&mut self, op: &str) -> Result<()> {
+        if self.eat_op(op) { Ok(()) } else { Err(self.error(&format!("expected {op:?}"))) }
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        let cond = self.binary(0)?;
+
+        if self.eat_op("?") {
+            let then = self.expr()?;
+            self.expect_op(":")?;
+            let other = self.expr()?;
+            return Ok(Expr::Cond(Box::new(cond), Box::new(then), Box::new(other)));
+        }
+
+        Ok(cond)
+    }
+
+    fn binary_op(&self, level: usize) -> Option<BinaryOp> {
+        let op = match self.peek()? {
+            Token::Op(op) => *op,
+            Token::Ident(w) if w == "or" => "||",
+            Token::Ident(w) if w == "and" => "&&",
+            _ => return None,
+        };
+
+        let (op_level, op) = match op {
+            "||" => (0, BinaryOp::Or),
+            "&&" => (1, BinaryOp::And),
+            "==" => (2, BinaryOp::Eq),
+            "!=" => (2, BinaryOp::Ne),
+            "<"  => (2, BinaryOp::Lt),
+            "<=" => (2, BinaryOp::Le),
+            ">"  => (2, BinaryOp::Gt),
+            ">=" => (2, BinaryOp::Ge),
+            "|"  => (3, BinaryOp::BitOr),
+            "^"  => (4, BinaryOp::BitXor),
+            "&"  => (5, BinaryOp::BitAnd),
+            "<<" => (6, BinaryOp::Shl),
+            ">>" => (6, BinaryOp::Shr),
+            "+"  => (7, BinaryOp::Add),
+            "-"  => (7, BinaryOp::Sub),
+            "*"  => (8, BinaryOp::Mul),
+            "/"  => (8, BinaryOp::Div),
+            "%"  => (8, BinaryOp::Rem),
+            _ => return None,
+        };
+
+        (op_level == level).then_some(op)
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr> {
+        if level > 8 {
+            return self.unary();
+        }
+
+        let mut lhs = self.binary(level + 1)?;
+        while let Some(op) = self.binary_op(level) {
+            self.pos += 1;
+            let rhs = self.binary(level + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat_op("-") {
+            return Ok(Expr::Unary(UnaryOp::Neg, Box::new(self.unary()?)));
+        }
+        if self.eat_op("!") || self.eat_word("not") {
+            return Ok(Expr::Unary(UnaryOp::Not, Box::new(self.unary()?)));
+        }
+        if self.eat_op("~") {
+            return Ok(Expr::Unary(UnaryOp::BitNot, Box::new(self.unary()?)));
+        }
+
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Expr> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.eat_op(".") {
+                match self.tokens.get(self.pos).cloned() {
+                    Some(Token::Ident(name)) => {
+                        self.pos += 1;
+                        expr = Expr::Member(Box::new(expr), name);
+                    },
+                    _ => return Err(self.error("expected member name")),
+                }
+            } else if self.eat_op("[") {
+                let index = self.expr()?;
+                self.expect_op("]")?;
+                expr = Expr::Index(Box::new(expr), Box::new(index));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        let token = self.tokens.get(self.pos).cloned().ok_or_else(|| self.error("unexpected end"))?;
+        self.pos += 1;
+
+        match token {
+            Token::Int(v) => Ok(Expr::Int(v)),
+            Token::Float(v) => Ok(Expr::Float(v)),
+            Token::Str(s) => Ok(Expr::Str(s)),
+            Token::Ident(w) if w == "true" => Ok(Expr::Bool(true)),
+            Token::Ident(w) if w == "false" => Ok(Expr::Bool(false)),
+            Token::Ident(w) => Ok(Expr::Name(w)),
+            Token::Op("(") => {
+                let expr = self.expr()?;
+                self.expect_op(")")?;
+                Ok(expr)
+            },
+            Token::Op(op) => Err(self.error(&format!("unexpected {op:?}"))),
+        }
+    }
+}
+
+impl Expr {
+    pub fn parse(src: &str) -> Result<Self> {
+        let mut parser = Parser { src, tokens: tokenize(src)?, pos: 0 };
+        let expr = parser.expr()?;
+
+        if parser.pos != parser.tokens.len() {
+            return Err(parser.error("unexpected trailing input"));
+        }
+
+        Ok(expr)
+    }
+
+    pub fn eval(&self, scope: &dyn Scope) -> Result<Value> {
+        match self {
+            Expr::Int(v) => int_value(*v),
+            Expr::Float(v) => Ok(Value::Float(*v)),
+            Expr::Bool(v) => Ok(Value::Bool(*v)),
+            Expr::Str(s) => Ok(Value::Str(s.clone())),
+
+            Expr::Name(name) => scope.lookup(name).ok_or_else(|| Error::Eval(format!("unknown name: {name}"))),
+
+            Expr::Member(base, name) => {
+                let base = base.eval(scope)?;
+                member(&base, name)
+            },
+
+            Expr::Index(base, index) => {
+                let base = base.eval(scope)?;
+                let index = index.eval(scope)?.as_u64().ok_or_else(|| Error::Eval("index is not an integer".into()))? as usize;
+
+                let item = match &base {
+                    Value::Array(items) => items.get(index).cloned(),
+                    Value::Bytes(b) => b.get(index).map(|&b| Value::UInt(b as u64)),
+                    _ => return Err(Error::Eval("indexing a non-array value".into())),
+                };
+
+                item.ok_or_else(|| Error::Eval(format!("index {index} out of range")))
+            },
+
+            Expr::Unary(op, inner) => {
+                let v = inner.eval(scope)?;
+                match op {
+                    UnaryOp::Not => Ok(Value::Bool(!truthy(&v)?)),
+                    UnaryOp::Neg => match v {
+                        Value::Float(f) => Ok(Value::Float(-f)),
+                        _ => int_value(-int(&v)?),
+                    },
+                    UnaryOp::BitNot => int_value(!int(&v)?),
+                }
+            },
+
+            Expr::Binary(BinaryOp::And, lhs, rhs) => Ok(Value::Bool(truthy(&lhs.eval(scope)?)? && truthy(&rhs.eval(scope)?)?)),
+            Expr::Binary(BinaryOp::Or, lhs, rhs) => Ok(Value::Bool(truthy(&lhs.eval(scope)?)? || truthy(&rhs.eval(scope)?)?)),
+
+            Expr::Binary(op, lhs, rhs) => binary(*op, &lhs.eval(scope)?, &rhs.eval(scope)?),
+
+            Expr::Cond(cond, then, other) => {
+                if truthy(&cond.eval(scope)?)? { then.eval(scope) } else { other.eval(scope) }
+            },
+        }
+    }
+}
+
+/// `v` as a signed value if it fits, else unsigned; anything wider is an overflow.
+fn int_value(v: i128) -> Result<Value> {
+    match (i64::try_from(v), u64::try_from(v)) {
+        (Ok(v), _) => Ok(Value::Int(v)),
+        (_, Ok(v)) => Ok(Value::UInt(v)),
+        _ => Err(Error::Eval(format!("arithmetic overflow: {v} does not fit 64 bits"))),
+    }
+}
+
+fn int(v: &Value) -> Result<i128> {
+    v.as_int().ok_or_else(|| Error::Eval(format!("expected an integer, found {v}")))
+}
+
+pub(crate) fn truthy(v: &Value) -> Result<bool> {
+    match v {
+        Value::Bool(b) => Ok(*b),
+        _ => Ok(int(v)? != 0),
+    }
+}
+
+fn member(base: &Value, name: &str) -> Result<Value> {
+    if let Some(v) = base.get(name) {
+        return Ok(v.clone());
+    }
+
+    let len = match base {
+        Value::Array(items) => Some(items.len()),
+        Value::Bytes(b) => Some(b.len()),
+        Value::Str(s) => Some(s.len()),
+        _ => None,
+    };
+
+    match (name, len, base) {
+        ("size" | "length", Some(len), _) => Ok(Value::UInt(len as u64)),
+        ("first", _, Value::Array(items)) => items.first().cloned().ok_or_else(|| Error::Eval("first of empty array".into())),
+        ("last", _, Value::Array(items)) => items.last().cloned().ok_or_else(|| Error::Eval("last of empty array".into())),
+        ("to_i", _, Value::Float(f)) => int_value(*f as i128),
+        ("to_i", _, Value::Bool(b)) => Ok(Value::Int(*b as i64)),
+        ("to_i", _, Value::Str(s)) => int_value(s.trim().parse::<i128>().map_err(|_| Error::Eval(format!("{s:?} is not a number")))?),
+        _ => Err(Error::Eval(format!("no member {name} in {base}"))),
+    }
+}
+
+pub(crate) fn binary(op: BinaryOp, lhs: &Value, rhs: &Value) -> Result<Value> {
+    use BinaryOp::*;
+
+    // strings and byte arrays compare and concatenate, everything else is numeric
+    if let (Some(a), Some(b)) = (lhs.as_bytes(), rhs.as_bytes()) {
+        return match op {
+            Eq => Ok(Value::Bool(a == b)),
+            Ne => Ok(Value::Bool(a != b)),
+            Lt => Ok(Value::Bool(a < b)),
+            Le => Ok(Value::Bool(a <= b)),
+            Gt => Ok(Value::Bool(a > b)),
+            Ge => Ok(Value::Bool(a >= b)),
+            Add => match (lhs, rhs) {
+                (Value::Str(a), Value::Str(b)) => Ok(Value::Str(format!("{a}{b}"))),
+                _ => Ok(Value::Bytes([a, b].concat())),
+            },
+            _ => Err(Error::Eval(format!("invalid operands for {op:?}"))),
+        };
+    }
+
+    if matches!(lhs, Value::Float(_)) || matches!(rhs, Value::Float(_)) {
+        let (a, b) = (
+            lhs.as_f64().ok_or_else(|| Error::Eval(format!("expected a number, found {lhs}")))?,
+            rhs.as_f64().ok_or_else(|| Error::Eval(format!("expected a number, found {rhs}")))?,
+        );
+
+        return match op {
+            Add => Ok(Value::Float(a + b)),
+            Sub => Ok(Value::Float(a - b)),
+            Mul => Ok(Value::Float(a * b)),
+            Div => Ok(Value::Float(a / b)),
+            Eq => Ok(Value::Bool(a == b)),
+            Ne => Ok(Value::Bool(a != b)),
+            Lt => Ok(Value::Bool(a < b)),
+            Le => Ok(Value::Bool(a <= b)),
+            Gt => Ok(Value::Bool(a > b)),
+            Ge => Ok(Value::Bool(a >= b)),
+            _ => Err(Error::Eval(format!("invalid float operands for {op:?}"))),
+        };
+    }
+
+    let (a, b) = (int(lhs)?, int(rhs)?);
+    let overflow = || Error::Eval(format!("arithmetic overflow in {op:?}"));
+
+    let v = match op {
+        Add => a.checked_add(b).ok_or_else(overflow)?,
+        Sub => a.checked_sub(b).ok_or_else(overflow)?,
+        Mul => a.checked_mul(b).ok_or_else(overflow)?,
+        Div => a.checked_div_euclid(b).ok_or_else(|| Error::Eval("division by zero".into()))?,
+        Rem => a.checked_rem_euclid(b).ok_or_else(|| Error::Eval("division by zero".into()))?,
+        Shl => a.checked_shl(u32::try_from(b).map_err(|_| overflow())?).ok_or_else(overflow)?,
+        Shr => a.checked_shr(u32::try_from(b).map_err(|_| overflow())?).ok_or_else(overflow)?,
+        BitAnd => a & b,
+        BitOr => a | b,
+        BitXor => a ^ b,
+        Eq => return Ok(Value::Bool(a == b)),
+        Ne => return Ok(Value::Bool(a != b)),
+        Lt => return Ok(Value::Bool(a < b)),
+        Le => return Ok(Value::Bool(a <= b)),
+        Gt => return Ok(Value::Bool(a > b)),
+        Ge => return Ok(Value::Bool(a >= b)),
+        And | Or => unreachable!("handled before evaluating both sides"),
+    };
+
+    int_value(v).map_err(|_| overflow())
+}
@@ -0,0 +1,25 @@
+//! Runtime interpreter for declarative binary format descriptions.
+//!
+//! A [`Schema`] is loaded from a YAML document in the style of Kaitai Struct:
+//! a sequence of typed fields, endianness, repeat counts and conditions given
+//! as expressions, `switch-on` types and instances at computed offsets. Parsing
+//! any `Read + Seek` with it produces a generic [`Value`] tree, where every
+//! field remembers the byte range it came from so the result can be laid over
+//! a [`HexDump`](crate::io::HexDump) with [`Value::overlays`].
+//!
+//! ```ignore
+//! let schema = Schema::from_yaml(&std::fs::read_to_string("archive.ksy")?)?;
+//! let tree = schema.parse(File::open("data.arc")?)?;
+//! println!("{}", tree.path("entries.0.name").unwrap());
+//! ```
+
+mod def;
+mod error;
+mod expr;
+mod parse;
+mod value;
+
+pub use def::Schema;
+pub use error::{Error, Result};
+pub use expr::{Expr, Scope};
+pub use value::{Field, Struct, Value};
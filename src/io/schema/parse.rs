@@ -0,0 +1,358 @@
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+
+use crate::io::{Endian, ReadExt};
+use super::def::{Attr, Prim, Repeat, Schema, TypeDef, TypeRef};
+use super::error::{Error, Result};
+use super::expr::{self, truthy, BinaryOp, Scope};
+use super::value::{Field, Struct, Value};
+
+trait Stream: Read + Seek {}
+impl<T: Read + Seek> Stream for T {}
+
+/// The stream a struct is parsed from; a sized user type gets its own substream.
+struct Io<'a> {
+    r       : &'a mut dyn Stream,
+
+    // reader offset of position 0, and the absolute offset reported for it
+    origin  : u64,
+    base    : u64,
+    size    : u64,
+}
+
+impl Io<'_> {
+    fn pos(&mut self) -> Result<u64> {
+        Ok(self.r.stream_position()? - self.origin)
+    }
+
+    fn seek(&mut self, pos: u64) -> Result<()> {
+        self.r.seek(SeekFrom::Start(self.origin + pos))?;
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, len: u64) -> Result<Vec<u8>> {
+        // checked up front so a bogus size cannot trigger a huge allocation
+        if len > self.size.saturating_sub(self.pos()?) {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("{len} bytes past the end of the stream")).into());
+        }
+
+        Ok(ReadExt::read_bytes(&mut self.r, len as usize)?)
+    }
+
+    fn read_until(&mut self, term: u8, include: bool, consume: bool) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        let mut chunk = [0u8; 256];
+
+        loop {
+            let n = self.r.read(&mut chunk)?;
+            if n == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, format!("terminator {term:#04x} not found")).into());
+            }
+
+            if let Some(i) = chunk[..n].iter().position(|&b| b == term) {
+                out.extend_from_slice(&chunk[..i + include as usize]);
+
+                let used = i + consume as usize;
+                self.r.seek(SeekFrom::Current(used as i64 - n as i64))?;
+                return Ok(out);
+            }
+
+            out.extend_from_slice(&chunk[..n]);
+        }
+    }
+}
+
+/// A struct under construction together with the state of its stream.
+#[derive(Clone, Copy)]
+struct Frame<'a> {
+    this    : &'a Struct,
+    pos     : u64,
+    size    : u64,
+}
+
+impl Frame<'_> {
+    fn io(&self) -> Value {
+        let mut io = Struct::new("_io");
+        for (name, value) in [("pos", Value::UInt(self.pos)), ("size", Value::UInt(self.size)), ("eof", Value::Bool(self.pos >= self.size))] {
+            io.fields.push(Field { name: name.into(), value, offset: 0, len: 0 });
+        }
+        Value::Struct(io)
+    }
+
+    fn value(&self) -> Value {
+        let mut this = self.this.clone();
+        this.fields.push(Field { name: "_io".into(), value: self.io(), offset: 0, len: 0 });
+        Value::Struct(this)
+    }
+}
+
+struct Env<'a> {
+    frame   : Frame<'a>,
+    parents : &'a [Frame<'a>],
+
+    // the current element while repeating
+    item    : Option<&'a Value>,
+    index   : Option<usize>,
+}
+
+impl Scope for Env<'_> {
+    fn lookup(&self, name: &str) -> Option<Value> {
+        match name {
+            "_" => self.item.cloned(),
+            "_index" => self.index.map(|i| Value::UInt(i as u64)),
+            "_io" => Some(self.frame.io()),
+            "_parent" => self.parents.last().map(Frame::value),
+            "_root" => Some(self.parents.first().unwrap_or(&self.frame).value()),
+            _ => self.frame.this.get(name).cloned(),
+        }
+    }
+}
+
+impl Schema {
+    /// Parses `reader` from its current position as the root type.
+    ///
+    /// Field offsets are absolute positions in `reader`, while `_io.pos` in
+    /// expressions counts from where parsing started, or from the start of the
+    /// enclosing substream for user types with a `size`. Instances are
+    /// evaluated after `seq`, in declaration order.
+    pub fn parse<R: Read + Seek>(&self, reader: R) -> Result<Value> {
+        self.parse_type(self.id(), reader)
+    }
+
+    /// Like [`Schema::parse`], starting with one of the schema's named types.
+    pub fn parse_type<R: Read + Seek>(&self, name: &str, mut reader: R) -> Result<Value> {
+        let def = self.type_def(name)?;
+
+        let origin = reader.stream_position()?;
+        let end = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(origin))?;
+
+        let mut io = Io { r: &mut reader, origin, base: origin, size: end.saturating_sub(origin) };
+        Ok(Value::Struct(self.parse_struct(def, &mut io, &[])?))
+    }
+
+    fn parse_struct(&self, def: &TypeDef, io: &mut Io, parents: &[Frame]) -> Result<Struct> {
+        let mut this = Struct::new(&def.name);
+
+        for attr in &def.seq {
+            self.parse_attr(def, attr, io, parents, &mut this)?;
+        }
+
+        for attr in &def.instances {
+            let saved = io.pos()?;
+            self.parse_attr(def, attr, io, parents, &mut this)?;
+            io.seek(saved)?;
+        }
+
+        Ok(this)
+    }
+
+    fn parse_attr(&self, def: &TypeDef, attr: &Attr, io: &mut Io, parents: &[Frame], this: &mut Struct) -> Result<()> {
+        let frame = Frame { this, pos: io.pos()?, size: io.size };
+        let env = Env { frame, parents, item: None, index: None };
+
+        if let Some(cond) = &attr.cond {
+            if !truthy(&cond.eval(&env)?)? {
+                return Ok(());
+            }
+        }
+
+        if let Some(value) = &attr.value {
+            let value = value.eval(&env)?;
+            let offset = io.base + frame.pos;
+            this.fields.push(Field { name: attr.id.clone(), value, offset, len: 0 });
+            return Ok(());
+        }
+
+        if let Some(pos) = &attr.pos {
+            io.seek(to_u64(&pos.eval(&env)?)?)?;
+        }
+
+        let start = io.pos()?;
+
+        let value = match &attr.repeat {
+            Repeat::Once => self.parse_item(def, attr, io, parents, this, None)?,
+
+            Repeat::Expr(count) => {
+                let count = to_u64(&count.eval(&Env { frame: Frame { this, pos: start, size: io.size }, parents, item: None, index: None })?)?;
+                let mut items = Vec::new();
+
+                for i in 0..count {
+                    let pos = io.pos()?;
+                    items.push(self.parse_item(def, attr, io, parents, this, Some(i as usize))?);
+
+                    // an untrusted count of items that read nothing would spin without bound
+                    if io.pos()? == pos && i + 1 < count {
+                        return Err(no_progress(&attr.id, i));
+                    }
+                }
+
+                Value::Array(items)
+            },
+
+            Repeat::Eos => {
+                let mut items = Vec::new();
+
+                loop {
+                    let pos = io.pos()?;
+                    if pos >= io.size {
+                        break;
+                    }
+
+                    items.push(self.parse_item(def, attr, io, parents, this, Some(items.len()))?);
+
+                    // an item that reads nothing would repeat forever
+                    if io.pos()? == pos {
+                        break;
+                    }
+                }
+
+                Value::Array(items)
+            },
+
+            Repeat::Until(until) => {
+                let mut items = Vec::new();
+
+                loop {
+                    let pos = io.pos()?;
+                    let item = self.parse_item(def, attr, io, parents, this, Some(items.len()))?;
+                    let frame = Frame { this, pos: io.pos()?, size: io.size };
+                    let done = truthy(&until.eval(&Env { frame, parents, item: Some(&item), index: Some(items.len()) })?)?;
+
+                    items.push(item);
+                    if done {
+                        break;
+                    }
+
+                    if frame.pos == pos {
+                        return Err(no_progress(&attr.id, items.len() as u64 - 1));
+                    }
+                }
+
+                Value::Array(items)
+            },
+        };
+
+        let len = io.pos()?.saturating_sub(start);
+        this.fields.push(Field { name: attr.id.clone(), value, offset: io.base + start, len });
+        Ok(())
+    }
+
+    fn parse_item(&self, def: &TypeDef, attr: &Attr, io: &mut Io, parents: &[Frame], this: &Struct, index: Option<usize>) -> Result<Value> {
+        if let Some(expected) = &attr.contents {
+            let actual = io.read_bytes(expected.len() as u64)?;
+            if &actual != expected {
+                return Err(Error::Contents { field: attr.id.clone(), expected: expected.clone(), actual });
+            }
+            return Ok(Value::Bytes(actual));
+        }
+
+        let frame = Frame { this, pos: io.pos()?, size: io.size };
+        let env = Env { frame, parents, item: None, index };
+
+        let size = match &attr.size {
+            Some(size) => Some(to_u64(&size.eval(&env)?)?),
+            None if attr.size_eos => Some(io.size.saturating_sub(frame.pos)),
+            None => None,
+        };
+
+        let ty = match &attr.ty {
+            Some(TypeRef::Switch { on, cases, default }) => {
+                let on = on.eval(&env)?;
+                let mut chosen = default.as_deref();
+
+                for (case, ty) in cases {
+                    if truthy(&expr::binary(BinaryOp::Eq, &case.eval(&env)?, &on)?)? {
+                        chosen = Some(ty);
+                        break;
+                    }
+                }
+
+                chosen
+            },
+            ty => ty.as_ref(),
+        };
+
+        match ty {
+            Some(TypeRef::Prim(prim)) => read_prim(io, *prim, def.endian, &attr.id),
+
+            Some(TypeRef::User(name)) => {
+                let child = self.type_def(name)?;
+                let mut chain = parents.to_vec();
+                chain.push(frame);
+
+                match size {
+                    None => Ok(Value::Struct(self.parse_struct(child, io, &chain)?)),
+                    Some(size) => {
+                        let base = io.base + frame.pos;
+                        let mut sub = Cursor::new(io.read_bytes(size)?);
+                        let mut sub = Io { r: &mut sub, origin: 0, base, size };
+                        Ok(Value::Struct(self.parse_struct(child, &mut sub, &chain)?))
+                    },
+                }
+            },
+
+            // str, strz, raw bytes, and switches without a matching case
+            ty => {
+                let bytes = match (size, attr.terminator) {
+                    (Some(size), term) => {
+                        let mut bytes = io.read_bytes(size)?;
+                        if let Some(i) = term.and_then(|t| bytes.iter().position(|&b| b == t)) {
+                            bytes.truncate(i + attr.include as usize);
+                        }
+                        bytes
+                    },
+                    (None, Some(term)) => io.read_until(term, attr.include, attr.consume)?,
+                    (None, None) => return Err(Error::Schema(format!("{} has neither a type nor a size", attr.id))),
+                };
+
+                match ty {
+                    Some(TypeRef::Str | TypeRef::Strz) => Ok(Value::Str(decode(&bytes, &attr.encoding)?)),
+                    _ => Ok(Value::Bytes(bytes)),
+                }
+            },
+        }
+    }
+}
+
+fn read_prim(io: &mut Io, prim: Prim, default: Option<Endian>, id: &str) -> Result<Value> {
+    let endian = match prim.endian.or(default) {
+        Some(endian) => endian,
+        None if prim.size == 1 => Endian::NATIVE,
+        None => return Err(Error::Schema(format!("no endianness for {id}"))),
+    };
+
+    let r = &mut io.r;
+
+    Ok(match (prim.float, prim.signed, prim.size) {
+        (true, _, 4) => Value::Float(endian.read_f32(r)? as f64),
+        (true, _, _) => Value::Float(endian.read_f64(r)?),
+        (false, false, 1) => Value::UInt(ReadExt::read_u8(r)? as u64),
+        (false, false, 2) => Value::UInt(endian.read_u16(r)? as u64),
+        (false, false, 4) => Value::UInt(endian.read_u32(r)? as u64),
+        (false, false, _) => Value::UInt(endian.read_u64(r)?),
+        (false, true, 1) => Value::Int(ReadExt::read_i8(r)? as i64),
+        (false, true, 2) => Value::Int(endian.read_i16(r)? as i64),
+        (false, true, 4) => Value::Int(endian.read_i32(r)? as i64),
+        (false, true, _) => Value::Int(endian.read_i64(r)?),
+    })
+}
+
+fn decode(bytes: &[u8], encoding: &str) -> Result<String> {
+    let units = |f: fn([u8; 2]) -> u16| bytes.chunks_exact(2).map(|c| f([c[0], c[1]])).collect::<Vec<_>>();
+
+    match encoding.to_ascii_uppercase().replace(['-', '_'], "").as_str() {
+        "UTF8" | "ASCII" => Ok(String::from_utf8_lossy(bytes).into_owned()),
+        "UTF16LE" => Ok(String::from_utf16_lossy(&units(u16::from_le_bytes))),
+        "UTF16BE" => Ok(String::from_utf16_lossy(&units(u16::from_be_bytes))),
+        "ISO88591" | "LATIN1" => Ok(bytes.iter().map(|&b| b as char).collect()),
+        _ => Err(Error::Schema(format!("unsupported encoding {encoding:?}"))),
+    }
+}
+
+fn no_progress(id: &str, index: u64) -> Error {
+    Error::Eval(format!("item {index} of {id} read no bytes, so repeating it would not advance"))
+}
+
+fn to_u64(v: &Value) -> Result<u64> {
+    v.as_u64().ok_or_else(|| Error::Eval(format!("expected a non-negative integer, found {v}")))
+}
@@ -0,0 +1,192 @@
+use std::fmt;
+
+use crate::io::hexdump;
+
+/// A node of the tree produced by interpreting a schema.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Bool(bool),
+    Bytes(Vec<u8>),
+    Str(String),
+    Array(Vec<Value>),
+    Struct(Struct),
+}
+
+/// An instance of a user type, with fields in parse order followed by instances.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Struct {
+    pub type_name   : String,
+    pub fields      : Vec<Field>,
+}
+
+/// A parsed attribute and the absolute byte range it was read from.
+///
+/// Value instances, which are computed rather than read, have a `len` of 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name    : String,
+    pub value   : Value,
+    pub offset  : u64,
+    pub len     : u64,
+}
+
+impl Struct {
+    pub fn new(type_name: impl Into<String>) -> Self {
+        Self { type_name: type_name.into(), fields: Vec::new() }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.field(name).map(|f| &f.value)
+    }
+
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|f| f.name == name)
+    }
+}
+
+impl Value {
+    /// Integer value widened so every integer type compares and computes uniformly.
+    pub fn as_int(&self) -> Option<i128> {
+        match *self {
+            Value::Int(v) => Some(v as i128),
+            Value::UInt(v) => Some(v as i128),
+            Value::Bool(v) => Some(v as i128),
+            _ => None,
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_int().and_then(|v| u64::try_from(v).ok())
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Float(v) => Some(v),
+            _ => self.as_int().map(|v| v as f64),
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match *self {
+            Value::Bool(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Value::Bytes(v) => Some(v),
+            Value::Str(s) => Some(s.as_bytes()),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_struct(&self) -> Option<&Struct> {
+        match self {
+            Value::Struct(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Field of a struct value.
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.as_struct()?.get(name)
+    }
+
+    /// Looks up a dotted path such as `header.entries.2.name`, with numbers indexing arrays.
+    pub fn path(&self, path: &str) -> Option<&Value> {
+        path.split('.').try_fold(self, |v, part| match v {
+            Value::Array(items) => items.get(part.parse::<usize>().ok()?),
+            _ => v.get(part),
+        })
+    }
+
+    /// Every read field as a named byte range, for [`hexdump::HexDump::fields`].
+    pub fn overlays(&self) -> Vec<hexdump::Field> {
+        let mut out = Vec::new();
+        collect_overlays(self, "", &mut out);
+        out
+    }
+}
+
+fn collect_overlays(value: &Value, prefix: &str, out: &mut Vec<hexdump::Field>) {
+    match value {
+        Value::Struct(s) => {
+            for field in &s.fields {
+                let name = if prefix.is_empty() { field.name.clone() } else { format!("{prefix}.{}", field.name) };
+
+                let nested = match &field.value {
+                    Value::Struct(_) => true,
+                    Value::Array(items) => items.iter().any(|i| matches!(i, Value::Struct(_))),
+                    _ => false,
+                };
+
+                if field.len > 0 && !nested {
+                    out.push(hexdump::Field::new(name.clone(), field.offset, field.len));
+                }
+
+                collect_overlays(&field.value, &name, out);
+            }
+        },
+
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                collect_overlays(item, &format!("{prefix}.{i}"), out);
+            }
+        },
+
+        _ => {},
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(v) => write!(f, "{v}"),
+            Value::UInt(v) => write!(f, "{v}"),
+            Value::Float(v) => write!(f, "{v}"),
+            Value::Bool(v) => write!(f, "{v}"),
+            Value::Bytes(v) => write!(f, "{v:02X?}"),
+            Value::Str(v) => write!(f, "{v:?}"),
+
+            Value::Array(items) => {
+                f.write_str("[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{item}")?;
+                }
+                f.write_str("]")
+            },
+
+            Value::Struct(s) => {
+                write!(f, "{} {{", s.type_name)?;
+                for (i, field) in s.fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, " {}: {}", field.name, field.value)?;
+                }
+                f.write_str(" }")
+            },
+        }
+    }
+}
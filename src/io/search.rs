@@ -0,0 +1,282 @@
+use std::collections::VecDeque;
+use std::io::{self, Read};
+
+use super::error::Result;
+use super::signature::Signature;
+
+const SEARCH_CHUNK: usize = 0x10000;
+
+/// A pattern occurrence at an absolute stream offset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Match {
+    pub offset  : u64,
+    pub pattern : usize,
+}
+
+/// Searches a stream for several [`Signature`]s at once without loading it into memory.
+///
+/// Each pattern is anchored on its longest run of fixed bytes, all anchors are
+/// matched in a single pass with an Aho-Corasick automaton, and candidates are
+/// then checked against the full pattern including wildcards. Matches that span
+/// read boundaries are found, and are reported in ascending offset order.
+#[derive(Debug, Clone)]
+pub struct Searcher {
+    patterns        : Vec<Signature>,
+
+    // (offset, len) of the anchor within each pattern
+    anchors         : Vec<(usize, usize)>,
+
+    // DFA transitions and the patterns whose anchor ends in each state
+    delta           : Vec<[u32; 256]>,
+    outputs         : Vec<Vec<usize>>,
+
+    max_len         : usize,
+    max_anchor_end  : usize,
+}
+
+impl Searcher {
+    pub fn new(patterns: impl IntoIterator<Item = Signature>) -> Self {
+        let patterns: Vec<_> = patterns.into_iter().collect();
+        let anchors: Vec<_> = patterns.iter().map(longest_fixed_run).collect();
+
+        let mut searcher = Self {
+            max_len: patterns.iter().map(Signature::len).max().unwrap_or(0),
+            max_anchor_end: anchors.iter().map(|&(o, l)| o + l).max().unwrap_or(0),
+            patterns,
+            anchors,
+            delta: vec![[0; 256]],
+            outputs: vec![Vec::new()],
+        };
+
+        searcher.build();
+        searcher
+    }
+
+    /// Parses every pattern with the [`Signature`] syntax.
+    pub fn from_patterns<S: AsRef<str>>(patterns: &[S]) -> Result<Self> {
+        let patterns = patterns.iter().map(|p| Signature::new(p.as_ref())).collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(patterns))
+    }
+
+    fn build(&mut self) {
+        const NONE: u32 = u32::MAX;
+
+        // trie of the anchors, NONE marks a missing edge
+        let mut goto: Vec<[u32; 256]> = vec![[NONE; 256]];
+        let mut outputs: Vec<Vec<usize>> = vec![Vec::new()];
+
+        for (index, (pattern, &(offset, len))) in self.patterns.iter().zip(&self.anchors).enumerate() {
+            let mut state = 0;
+
+            for &b in &pattern.bytes()[offset..offset + len] {
+                if goto[state][b as usize] == NONE {
+                    goto.push([NONE; 256]);
+                    outputs.push(Vec::new());
+                    goto[state][b as usize] = (goto.len() - 1) as u32;
+                }
+
+                state = goto[state][b as usize] as usize;
+            }
+
+            outputs[state].push(index);
+        }
+
+        // breadth first, turning failure links into full DFA transitions
+        let mut fail = vec![0usize; goto.len()];
+        let mut queue = VecDeque::new();
+
+        for next in goto[0].iter_mut() {
+            match *next {
+                NONE => *next = 0,
+                n => queue.push_back(n as usize),
+            }
+        }
+
+        while let Some(state) = queue.pop_front() {
+            let inherited = outputs[fail[state]].clone();
+            outputs[state].extend(inherited);
+
+            let fallback = goto[fail[state]];
+
+            for (next, &fallback) in goto[state].iter_mut().zip(&fallback) {
+                if *next == NONE {
+                    *next = fallback;
+                } else {
+                    fail[*next as usize] = fallback as usize;
+                    queue.push_back(*next as usize);
+                }
+            }
+        }
+
+        self.delta = goto;
+        self.outputs = outputs;
+    }
+
+    pub fn patterns(&self) -> &[Signature] {
+        &self.patterns
+    }
+
+    /// Iterates over the matches in `reader`; offsets count from where the reader starts.
+    pub fn search<R: Read>(&self, reader: R) -> Matches<'_, R> {
+        Matches {
+            searcher: self,
+            reader,
+            state: 0,
+            chunk: Vec::new(),
+            window: Vec::new(),
+            window_start: 0,
+            pending: Vec::new(),
+            ready: Vec::new(),
+            threshold: 0,
+            eof: false,
+        }
+    }
+
+    pub fn find_all<R: Read>(&self, reader: R) -> Result<Vec<Match>> {
+        self.search(reader).collect()
+    }
+}
+
+fn longest_fixed_run(pattern: &Signature) -> (usize, usize) {
+    let mut best = (0, 0);
+    let mut start = 0;
+
+    for (i, &fixed) in pattern.mask().iter().chain([&false]).enumerate() {
+        if !fixed {
+            if i - start > best.1 {
+                best = (start, i - start);
+            }
+            start = i + 1;
+        }
+    }
+
+    best
+}
+
+/// Iterator returned by [`Searcher::search`].
+pub struct Matches<'a, R> {
+    searcher        : &'a Searcher,
+    reader          : R,
+    state           : usize,
+
+    // reused read buffer, so small reads cost what they return
+    chunk           : Vec<u8>,
+
+    // the unverified tail of the stream, starting at absolute offset `window_start`
+    window          : Vec<u8>,
+    window_start    : u64,
+
+    // candidate (start, pattern) pairs waiting for enough data to verify
+    pending         : Vec<(u64, usize)>,
+
+    // verified matches, sorted descending so the next one pops off the end
+    ready           : Vec<Match>,
+    threshold       : u64,
+    eof             : bool,
+}
+
+impl<R: Read> Matches<'_, R> {
+    fn fill(&mut self) -> Result<()> {
+        let s = self.searcher;
+
+        if self.chunk.is_empty() {
+            self.chunk.resize(SEARCH_CHUNK, 0);
+        }
+
+        let n = loop {
+            match self.reader.read(&mut self.chunk) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(e),
+            }
+        };
+
+        let old_len = self.window.len();
+        self.window.extend_from_slice(&self.chunk[..n]);
+        if n == 0 {
+            self.eof = true;
+        }
+
+        for i in old_len..old_len + n {
+            self.state = s.delta[self.state][self.window[i] as usize] as usize;
+
+            for &pattern in &s.outputs[self.state] {
+                let (offset, len) = s.anchors[pattern];
+                let anchor_end = self.window_start + i as u64 + 1;
+
+                if let Some(start) = anchor_end.checked_sub((offset + len) as u64) {
+                    self.pending.push((start, pattern));
+                }
+            }
+        }
+
+        let window_end = self.window_start + self.window.len() as u64;
+        let window = &self.window;
+        let window_start = self.window_start;
+        let eof = self.eof;
+        let ready = &mut self.ready;
+        let ready_len = ready.len();
+
+        self.pending.retain(|&(start, pattern)| {
+            let sig = &s.patterns[pattern];
+            if start + sig.len() as u64 > window_end {
+                return !eof;
+            }
+
+            if sig.matches(&window[(start - window_start) as usize..]) {
+                ready.push(Match { offset: start, pattern });
+            }
+
+            false
+        });
+
+        if ready.len() > ready_len {
+            ready.sort_unstable_by(|a, b| b.cmp(a));
+        }
+
+        // no candidate found later can start before this
+        let next = (window_end + 1).saturating_sub(s.max_anchor_end as u64);
+        let earliest_pending = self.pending.iter().map(|&(start, _)| start).min();
+
+        self.threshold = if eof { u64::MAX } else { earliest_pending.map_or(next, |p| p.min(next)) };
+
+        // keep what pending candidates and patterns straddling the boundary still need
+        let keep_from = earliest_pending
+            .unwrap_or(window_end)
+            .min(window_end.saturating_sub(s.max_len.saturating_sub(1) as u64))
+            .max(self.window_start);
+
+        // dropping the consumed prefix moves the whole window, so only do it once it is worth it
+        let consumed = (keep_from - self.window_start) as usize;
+        if consumed >= SEARCH_CHUNK {
+            self.window.drain(..consumed);
+            self.window_start = keep_from;
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for Matches<'_, R> {
+    type Item = Result<Match>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(m) = self.ready.last() {
+                if m.offset < self.threshold {
+                    return self.ready.pop().map(Ok);
+                }
+            }
+
+            if self.eof {
+                return None;
+            }
+
+            if let Err(e) = self.fill() {
+                self.eof = true;
+                self.ready.clear();
+                return Some(Err(e));
+            }
+        }
+    }
+}
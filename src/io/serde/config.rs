@@ -0,0 +1,70 @@
+/// Encoding of a length prefix for sequences, maps, strings and byte buffers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LengthEncoding {
+    U8,
+    U16,
+    U32,
+    U64,
+
+    /// Unsigned LEB128.
+    Varint,
+}
+
+/// Encoding of `str` and `String`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringEncoding {
+    /// Length prefix in bytes (per [`Config::len`]) followed by UTF-8 data.
+    LengthPrefixed,
+
+    /// UTF-8 data terminated by a `\0`.
+    NullTerminated,
+
+    /// Exactly `n` bytes, padded with `\0`. Trailing padding is stripped when reading.
+    Fixed(usize),
+}
+
+/// Encoding of the variant index of an enum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TagEncoding {
+    U8,
+    U16,
+    U32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    pub len     : LengthEncoding,
+    pub string  : StringEncoding,
+    pub tag     : TagEncoding,
+}
+
+impl Config {
+    pub const fn new() -> Self {
+        Self {
+            len     : LengthEncoding::U32,
+            string  : StringEncoding::LengthPrefixed,
+            tag     : TagEncoding::U32,
+        }
+    }
+
+    pub const fn len(mut self, len: LengthEncoding) -> Self {
+        self.len = len;
+        self
+    }
+
+    pub const fn string(mut self, string: StringEncoding) -> Self {
+        self.string = string;
+        self
+    }
+
+    pub const fn tag(mut self, tag: TagEncoding) -> Self {
+        self.tag = tag;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::new()
+    }
+}
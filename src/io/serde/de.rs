@@ -0,0 +1,343 @@
+use std::io::Read;
+use std::marker::PhantomData;
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, Visitor};
+
+use crate::io::{ByteOrder, ReadExt};
+use super::config::{Config, LengthEncoding, StringEncoding, TagEncoding};
+use super::error::{Error, Result};
+
+/// Reads serde data model values from a [`Read`] in byte order `E`.
+pub struct Deserializer<R, E> {
+    reader  : R,
+    config  : Config,
+    _order  : PhantomData<E>,
+}
+
+impl<R: Read, E: ByteOrder> Deserializer<R, E> {
+    pub fn new(reader: R) -> Self {
+        Self::with_config(reader, Config::default())
+    }
+
+    pub fn with_config(reader: R, config: Config) -> Self {
+        Self {
+            reader,
+            config,
+            _order: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    fn read_len(&mut self) -> Result<usize> {
+        let len = match self.config.len {
+            LengthEncoding::U8      => self.reader.read_u8()? as u64,
+            LengthEncoding::U16     => self.reader.read_u16::<E>()? as u64,
+            LengthEncoding::U32     => self.reader.read_u32::<E>()? as u64,
+            LengthEncoding::U64     => self.reader.read_u64::<E>()?,
+            LengthEncoding::Varint  => self.read_varint()?,
+        };
+
+        usize::try_from(len).map_err(|_| Error::LengthOverflow(len))
+    }
+
+    fn read_varint(&mut self) -> Result<u64> {
+        let mut value: u64 = 0;
+
+        for shift in (0..64).step_by(7) {
+            let b = self.reader.read_u8()?;
+            value |= ((b & 0x7F) as u64) << shift;
+
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(Error::LengthOverflow(value))
+    }
+
+    fn read_tag(&mut self) -> Result<u32> {
+        Ok(match self.config.tag {
+            TagEncoding::U8     => self.reader.read_u8()? as u32,
+            TagEncoding::U16    => self.reader.read_u16::<E>()? as u32,
+            TagEncoding::U32    => self.reader.read_u32::<E>()?,
+        })
+    }
+
+    fn read_vec(&mut self, len: usize) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        (&mut self.reader).take(len as u64).read_to_end(&mut buf)?;
+
+        if buf.len() != len {
+            return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+        }
+
+        Ok(buf)
+    }
+
+    fn read_string(&mut self) -> Result<String> {
+        let bytes = match self.config.string {
+            StringEncoding::LengthPrefixed => {
+                let len = self.read_len()?;
+                self.read_vec(len)?
+            },
+
+            StringEncoding::NullTerminated => {
+                let mut buf = Vec::new();
+
+                loop {
+                    match self.reader.read_u8()? {
+                        0 => break,
+                        b => buf.push(b),
+                    }
+                }
+
+                buf
+            },
+
+            StringEncoding::Fixed(n) => {
+                let mut buf = self.read_vec(n)?;
+                let end = buf.iter().position(|&b| b == 0).unwrap_or(n);
+                buf.truncate(end);
+                buf
+            },
+        };
+
+        Ok(String::from_utf8(bytes)?)
+    }
+}
+
+/// Deserializes a `T` from `reader` with the default [`Config`].
+pub fn from_reader<E: ByteOrder, T: DeserializeOwned, R: Read>(reader: R) -> Result<T> {
+    T::deserialize(&mut Deserializer::<R, E>::new(reader))
+}
+
+/// Deserializes a `T` from the start of `bytes` with the default [`Config`].
+pub fn from_slice<E: ByteOrder, T: DeserializeOwned>(bytes: &[u8]) -> Result<T> {
+    from_reader::<E, T, _>(bytes)
+}
+
+impl<'de, R: Read, E: ByteOrder> de::Deserializer<'de> for &mut Deserializer<R, E> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::Unsupported("deserialize_any"))
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::Unsupported("deserialize_ignored_any"))
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        Err(Error::Unsupported("deserialize_identifier"))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.reader.read_u8()? {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            b => Err(Error::InvalidBool(b)),
+        }
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i8(self.reader.read_i8()?)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i16(self.reader.read_i16::<E>()?)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i32(self.reader.read_i32::<E>()?)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i64(self.reader.read_i64::<E>()?)
+    }
+
+    fn deserialize_i128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i128(self.reader.read_i128::<E>()?)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u8(self.reader.read_u8()?)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u16(self.reader.read_u16::<E>()?)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u32(self.reader.read_u32::<E>()?)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u64(self.reader.read_u64::<E>()?)
+    }
+
+    fn deserialize_u128<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u128(self.reader.read_u128::<E>()?)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f32(self.reader.read_f32::<E>()?)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f64(self.reader.read_f64::<E>()?)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let c = self.reader.read_u32::<E>()?;
+        visitor.visit_char(char::from_u32(c).ok_or(Error::InvalidChar(c))?)
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(self.read_string()?)
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_string(self.read_string()?)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.read_len()?;
+        visitor.visit_byte_buf(self.read_vec(len)?)
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.read_len()?;
+        visitor.visit_byte_buf(self.read_vec(len)?)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.reader.read_u8()? {
+            0 => visitor.visit_none(),
+            1 => visitor.visit_some(self),
+            b => Err(Error::InvalidOption(b)),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.read_len()?;
+        visitor.visit_seq(Access { de: self, remaining: len })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(Access { de: self, remaining: len })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(Access { de: self, remaining: len })
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let len = self.read_len()?;
+        visitor.visit_map(Access { de: self, remaining: len })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(Access { de: self, remaining: fields.len() })
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        visitor.visit_enum(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+struct Access<'a, R, E> {
+    de          : &'a mut Deserializer<R, E>,
+    remaining   : usize,
+}
+
+impl<'de, R: Read, E: ByteOrder> de::SeqAccess<'de> for Access<'_, R, E> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        // don't trust a length read from the input for preallocation
+        Some(self.remaining.min(0x1000))
+    }
+}
+
+impl<'de, R: Read, E: ByteOrder> de::MapAccess<'de> for Access<'_, R, E> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining.min(0x1000))
+    }
+}
+
+impl<'de, R: Read, E: ByteOrder> de::EnumAccess<'de> for &mut Deserializer<R, E> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let tag = self.read_tag()?;
+        let value = seed.deserialize(IntoDeserializer::<Error>::into_deserializer(tag))?;
+        Ok((value, self))
+    }
+}
+
+impl<'de, R: Read, E: ByteOrder> de::VariantAccess<'de> for &mut Deserializer<R, E> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(Access { de: self, remaining: len })
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(Access { de: self, remaining: fields.len() })
+    }
+}
@@ -0,0 +1,49 @@
+use std::fmt::Display;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("{0}")]
+    Message(String),
+
+    #[error("io error")]
+    Io(#[from] std::io::Error),
+
+    #[error("{0} is not supported by this format")]
+    Unsupported(&'static str),
+
+    #[error("length 0x{0:X} does not fit the length encoding")]
+    LengthOverflow(u64),
+
+    #[error("enum variant index {0} does not fit the tag encoding")]
+    TagOverflow(u32),
+
+    #[error("sequence length must be known in advance")]
+    UnknownLength,
+
+    #[error("invalid bool value: 0x{0:02X}")]
+    InvalidBool(u8),
+
+    #[error("invalid option tag: 0x{0:02X}")]
+    InvalidOption(u8),
+
+    #[error("invalid char value: 0x{0:08X}")]
+    InvalidChar(u32),
+
+    #[error("invalid utf-8 string")]
+    InvalidUtf8(#[from] std::string::FromUtf8Error),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl serde::de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
+
+impl serde::ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Message(msg.to_string())
+    }
+}
@@ -0,0 +1,18 @@
+//! Maps serde data model types directly onto a binary layout.
+//!
+//! Integers and floats are written in the byte order `E`, structs, tuples and
+//! fixed arrays are laid out field by field with no framing. Only sequences,
+//! maps, strings, byte buffers and enums need an encoding, which is selected
+//! through [`Config`].
+//!
+//! The format is not self-describing, so `deserialize_any` and friends are not supported.
+
+mod config;
+mod de;
+mod error;
+mod ser;
+
+pub use config::{Config, LengthEncoding, StringEncoding, TagEncoding};
+pub use de::{from_reader, from_slice, Deserializer};
+pub use error::{Error, Result};
+pub use ser::{to_vec, to_writer, Serializer};
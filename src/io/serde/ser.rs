@@ -0,0 +1,375 @@
+use std::io::Write;
+use std::marker::PhantomData;
+use serde::ser::{self, Serialize};
+
+use crate::io::{ByteOrder, WriteExt};
+use super::config::{Config, LengthEncoding, StringEncoding, TagEncoding};
+use super::error::{Error, Result};
+
+/// Writes serde data model values to a [`Write`] in byte order `E`.
+pub struct Serializer<W, E> {
+    writer  : W,
+    config  : Config,
+    _order  : PhantomData<E>,
+}
+
+impl<W: Write, E: ByteOrder> Serializer<W, E> {
+    pub fn new(writer: W) -> Self {
+        Self::with_config(writer, Config::default())
+    }
+
+    pub fn with_config(writer: W, config: Config) -> Self {
+        Self {
+            writer,
+            config,
+            _order: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    fn write_len(&mut self, len: usize) -> Result<()> {
+        let len = len as u64;
+
+        match self.config.len {
+            LengthEncoding::U8 => {
+                let n = u8::try_from(len).map_err(|_| Error::LengthOverflow(len))?;
+                self.writer.write_u8(n)?;
+            },
+
+            LengthEncoding::U16 => {
+                let n = u16::try_from(len).map_err(|_| Error::LengthOverflow(len))?;
+                self.writer.write_u16::<E>(n)?;
+            },
+
+            LengthEncoding::U32 => {
+                let n = u32::try_from(len).map_err(|_| Error::LengthOverflow(len))?;
+                self.writer.write_u32::<E>(n)?;
+            },
+
+            LengthEncoding::U64 => {
+                self.writer.write_u64::<E>(len)?;
+            },
+
+            LengthEncoding::Varint => {
+                let mut n = len;
+
+                loop {
+                    let b = (n & 0x7F) as u8;
+                    n >>= 7;
+
+                    if n == 0 {
+                        self.writer.write_u8(b)?;
+                        break;
+                    }
+
+                    self.writer.write_u8(b | 0x80)?;
+                }
+            },
+        }
+
+        Ok(())
+    }
+
+    fn write_tag(&mut self, tag: u32) -> Result<()> {
+        match self.config.tag {
+            TagEncoding::U8 => {
+                let n = u8::try_from(tag).map_err(|_| Error::TagOverflow(tag))?;
+                self.writer.write_u8(n)?;
+            },
+
+            TagEncoding::U16 => {
+                let n = u16::try_from(tag).map_err(|_| Error::TagOverflow(tag))?;
+                self.writer.write_u16::<E>(n)?;
+            },
+
+            TagEncoding::U32 => {
+                self.writer.write_u32::<E>(tag)?;
+            },
+        }
+
+        Ok(())
+    }
+
+    fn write_str(&mut self, s: &str) -> Result<()> {
+        match self.config.string {
+            StringEncoding::LengthPrefixed => {
+                self.write_len(s.len())?;
+                self.writer.write_all(s.as_bytes())?;
+            },
+
+            StringEncoding::NullTerminated => {
+                self.writer.write_all(s.as_bytes())?;
+                self.writer.write_u8(0)?;
+            },
+
+            StringEncoding::Fixed(n) => {
+                if s.len() > n {
+                    return Err(Error::LengthOverflow(s.len() as u64));
+                }
+
+                self.writer.write_all(s.as_bytes())?;
+                self.writer.write_all(&vec![0u8; n - s.len()])?;
+            },
+        }
+
+        Ok(())
+    }
+}
+
+/// Serializes `value` into `writer` with the default [`Config`].
+pub fn to_writer<E: ByteOrder, T: Serialize + ?Sized, W: Write>(writer: W, value: &T) -> Result<()> {
+    value.serialize(&mut Serializer::<W, E>::new(writer))
+}
+
+/// Serializes `value` into a new buffer with the default [`Config`].
+pub fn to_vec<E: ByteOrder, T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    to_writer::<E, T, _>(&mut buf, value)?;
+    Ok(buf)
+}
+
+impl<W: Write, E: ByteOrder> ser::Serializer for &mut Serializer<W, E> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        Ok(self.writer.write_u8(v as u8)?)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        Ok(self.writer.write_i8(v)?)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        Ok(self.writer.write_i16::<E>(v)?)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        Ok(self.writer.write_i32::<E>(v)?)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        Ok(self.writer.write_i64::<E>(v)?)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        Ok(self.writer.write_i128::<E>(v)?)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        Ok(self.writer.write_u8(v)?)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        Ok(self.writer.write_u16::<E>(v)?)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        Ok(self.writer.write_u32::<E>(v)?)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        Ok(self.writer.write_u64::<E>(v)?)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        Ok(self.writer.write_u128::<E>(v)?)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        Ok(self.writer.write_f32::<E>(v)?)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        Ok(self.writer.write_f64::<E>(v)?)
+    }
+
+    fn serialize_char(self, v: char) -> Result<()> {
+        Ok(self.writer.write_u32::<E>(v as u32)?)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.write_str(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        self.write_len(v.len())?;
+        Ok(self.writer.write_all(v)?)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        Ok(self.writer.write_u8(0)?)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<()> {
+        self.writer.write_u8(1)?;
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Ok(())
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, variant_index: u32, _variant: &'static str) -> Result<()> {
+        self.write_tag(variant_index)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(self, _name: &'static str, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(self, _name: &'static str, variant_index: u32, _variant: &'static str, value: &T) -> Result<()> {
+        self.write_tag(variant_index)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self> {
+        self.write_len(len.ok_or(Error::UnknownLength)?)?;
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self> {
+        self.write_tag(variant_index)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self> {
+        self.write_len(len.ok_or(Error::UnknownLength)?)?;
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(self, _name: &'static str, variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self> {
+        self.write_tag(variant_index)?;
+        Ok(self)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl<W: Write, E: ByteOrder> ser::SerializeSeq for &mut Serializer<W, E> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write, E: ByteOrder> ser::SerializeTuple for &mut Serializer<W, E> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write, E: ByteOrder> ser::SerializeTupleStruct for &mut Serializer<W, E> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write, E: ByteOrder> ser::SerializeTupleVariant for &mut Serializer<W, E> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write, E: ByteOrder> ser::SerializeMap for &mut Serializer<W, E> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<()> {
+        key.serialize(&mut **self)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write, E: ByteOrder> ser::SerializeStruct for &mut Serializer<W, E> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<W: Write, E: ByteOrder> ser::SerializeStructVariant for &mut Serializer<W, E> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, _key: &'static str, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+use super::address_space::{AddressSpace, Region};
+use super::error::Result;
+
+const SCAN_CHUNK: usize = 0x10000;
+
+/// A byte pattern with wildcards, written as IDA style hex like `48 8B 05 ?? ?? ?? ?? C3`.
+///
+/// A wildcard is `?` or `??`.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Signature {
+    bytes   : Vec<u8>,
+    mask    : Vec<bool>,
+}
+
+impl Signature {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let mut bytes = Vec::new();
+        let mut mask = Vec::new();
+
+        for token in pattern.split_whitespace() {
+            if token == "?" || token == "??" {
+                bytes.push(0);
+                mask.push(false);
+                continue;
+            }
+
+            let byte = (token.len() == 2)
+                .then(|| u8::from_str_radix(token, 16).ok())
+                .flatten()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("invalid signature byte: {token:?}")))?;
+
+            bytes.push(byte);
+            mask.push(true);
+        }
+
+        if !mask.iter().any(|&m| m) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "signature has no fixed bytes"));
+        }
+
+        Ok(Self { bytes, mask })
+    }
+
+    /// A pattern of fixed bytes only, such as a magic value or a string.
+    pub fn literal(bytes: impl AsRef<[u8]>) -> Result<Self> {
+        let bytes = bytes.as_ref().to_vec();
+        if bytes.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "signature has no fixed bytes"));
+        }
+
+        Ok(Self { mask: vec![true; bytes.len()], bytes })
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Pattern bytes, with 0 at wildcard positions.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// `true` for every position that must match exactly.
+    pub fn mask(&self) -> &[bool] {
+        &self.mask
+    }
+
+    pub fn matches(&self, data: &[u8]) -> bool {
+        data.len() >= self.bytes.len()
+            && self.bytes.iter().zip(&self.mask).zip(data).all(|((&b, &m), &d)| !m || b == d)
+    }
+
+    /// Offset of the first match in `data`.
+    pub fn find(&self, data: &[u8]) -> Option<usize> {
+        self.find_iter(data).next()
+    }
+
+    pub fn find_iter<'a>(&'a self, data: &'a [u8]) -> impl Iterator<Item = usize> + 'a {
+        let last = data.len().checked_sub(self.bytes.len()).map_or(0, |n| n + 1);
+        (0..last).filter(move |&i| self.matches(&data[i..]))
+    }
+
+    /// Addresses of every match in the readable regions of `space`.
+    ///
+    /// Regions that turn out to be unreadable, like guard pages of a live process, are skipped.
+    pub fn scan<A: AddressSpace + ?Sized>(&self, space: &mut A) -> Result<Vec<u64>> {
+        self.scan_regions(space, |r| r.protection.read)
+    }
+
+    /// Like [`Signature::scan`], restricted to the regions accepted by `filter`.
+    pub fn scan_regions<A, F>(&self, space: &mut A, filter: F) -> Result<Vec<u64>>
+    where
+        A: AddressSpace + ?Sized,
+        F: Fn(&Region) -> bool,
+    {
+        let mut found = Vec::new();
+        let overlap = self.bytes.len() - 1;
+
+        for region in space.regions().iter().filter(|r| filter(r)) {
+            let mut va = region.start;
+            let mut buf = Vec::new();
+
+            while va < region.end() {
+                // keep the tail of the previous chunk so matches across chunk borders are found
+                let carried = buf.len().min(overlap);
+                buf.drain(..buf.len() - carried);
+
+                let n = (region.end() - va).min(SCAN_CHUNK as u64) as usize;
+                let start = buf.len();
+                buf.resize(start + n, 0);
+                match space.read_at(va, &mut buf[start..]) {
+                    Ok(()) => {},
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => break,
+                    Err(e) => return Err(e),
+                }
+
+                let chunk_va = va - carried as u64;
+                found.extend(self.find_iter(&buf).map(|i| chunk_va + i as u64));
+
+                va += n as u64;
+            }
+        }
+
+        Ok(found)
+    }
+}
+
+impl FromStr for Signature {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::new(s)
+    }
+}
+
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (b, m)) in self.bytes.iter().zip(&self.mask).enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+
+            if *m { write!(f, "{b:02X}")? } else { f.write_str("??")? }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Signature({self})")
+    }
+}
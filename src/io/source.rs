@@ -0,0 +1,438 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use memmap2::Mmap;
+
+use super::error::Result;
+
+// spooled input is kept in memory up to this size, then moved to a temp file
+const SPOOL_MEMORY: usize = 0x10_0000;
+const SPOOL_CHUNK: usize = 0x10000;
+
+fn invalid_input(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg.into())
+}
+
+enum Inner {
+    File(fs::File),
+    Mapped(Cursor<Mmap>),
+    Memory(Cursor<Vec<u8>>),
+    Spooled(Spool),
+
+    #[cfg(feature = "ssh2")]
+    Remote(ssh2::File),
+}
+
+/// An input opened from a command line argument, readable and seekable whatever it is.
+///
+/// | Input                         | Opened as                                         |
+/// |-------------------------------|---------------------------------------------------|
+/// | `path`, `file:path`, `file:///path` | a file                                      |
+/// | `mmap:path`                   | a memory-mapped file, see [`Source::as_slice`]    |
+/// | `mem:4d5a9000`                | the hex bytes given                               |
+/// | `-`                           | stdin, spooled                                    |
+/// | `ssh://user@host:port/path`   | a remote file over SFTP, with the `ssh2` feature  |
+///
+/// Inputs that cannot seek, like stdin, are spooled: what has been read is kept
+/// in memory, or in a temp file past 1 MiB, so seeking back works, and seeking
+/// forward or from the end reads ahead as needed. Piping gigabytes through a
+/// spooled input therefore costs as much temp disk space; callers that only read
+/// forward should use [`Source::open_sequential`] or [`Source::stream`], which keep
+/// nothing and fail with [`io::ErrorKind::Unsupported`] when asked to seek back.
+pub struct Source {
+    inner   : Inner,
+    name    : String,
+}
+
+impl Source {
+    pub fn open(uri: &str) -> Result<Self> {
+        Self::open_with(uri, true)
+    }
+
+    /// Like [`Source::open`], but stdin is read straight through instead of spooled.
+    pub fn open_sequential(uri: &str) -> Result<Self> {
+        Self::open_with(uri, false)
+    }
+
+    fn open_with(uri: &str, spool: bool) -> Result<Self> {
+        if uri == "-" {
+            return Ok(if spool { Self::spool(io::stdin(), "-") } else { Self::stream(io::stdin(), "-") });
+        }
+
+        let Some((scheme, rest)) = split_scheme(uri) else {
+            return Self::file(uri);
+        };
+
+        match scheme.to_ascii_lowercase().as_str() {
+            "file" => Self::file(file_uri_path(rest)?).map(|s| s.named(uri)),
+            "mmap" => Self::map(file_uri_path(rest)?).map(|s| s.named(uri)),
+            "mem" => Ok(Self::from_bytes(decode_hex(rest)?).named(uri)),
+
+            #[cfg(feature = "ssh2")]
+            "ssh" => Self::remote(uri, rest),
+
+            other => Err(invalid_input(format!("unsupported input scheme {other:?} in {uri:?}"))),
+        }
+    }
+
+    pub fn file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self {
+            inner   : Inner::File(fs::File::open(path.as_ref())?),
+            name    : path.as_ref().display().to_string(),
+        })
+    }
+
+    /// Maps the file into memory; it must not be modified while mapped.
+    pub fn map<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let file = fs::File::open(path.as_ref())?;
+
+        // SAFETY: the caller is told not to modify the file while it is mapped
+        let map = unsafe { Mmap::map(&file)? };
+
+        Ok(Self {
+            inner   : Inner::Mapped(Cursor::new(map)),
+            name    : path.as_ref().display().to_string(),
+        })
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Self {
+        Self {
+            inner   : Inner::Memory(Cursor::new(data)),
+            name    : "mem:".to_string(),
+        }
+    }
+
+    /// Wraps a stream that cannot seek, spooling it as it is read.
+    pub fn spool<R: Read + 'static>(reader: R, name: impl Into<String>) -> Self {
+        Self {
+            inner   : Inner::Spooled(Spool::new(Box::new(reader), true)),
+            name    : name.into(),
+        }
+    }
+
+    /// Wraps a stream that cannot seek without keeping what was read; seeking
+    /// forward skips input, seeking back fails.
+    pub fn stream<R: Read + 'static>(reader: R, name: impl Into<String>) -> Self {
+        Self {
+            inner   : Inner::Spooled(Spool::new(Box::new(reader), false)),
+            name    : name.into(),
+        }
+    }
+
+    #[cfg(feature = "ssh2")]
+    fn remote(uri: &str, rest: &str) -> Result<Self> {
+        let rest = rest.strip_prefix("//").ok_or_else(|| invalid_input(format!("expected ssh://[user@]host[:port]/path, got {uri:?}")))?;
+        let (authority, path) = rest.split_at(rest.find('/').ok_or_else(|| invalid_input(format!("no remote path in {uri:?}")))?);
+
+        let (user, host) = match authority.rsplit_once('@') {
+            Some((user, host)) => (Some(user), host),
+            None => (None, authority),
+        };
+
+        // "/~/file" is relative to the remote home, which is where SFTP resolves relative paths
+        let path = path.strip_prefix("/~/").unwrap_or(path);
+
+        // a port after the last colon, unless that colon is inside a bracketed IPv6 address
+        let (host, port) = match host.rsplit_once(':').filter(|(_, port)| !port.contains(']')) {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid_input(format!("invalid port in {uri:?}")))?),
+            None => (host, 22),
+        };
+
+        let mut builder = crate::ssh2::SessionBuilder::new(host.trim_start_matches('[').trim_end_matches(']')).port(port);
+
+        if let Some(user) = user {
+            builder = builder.user(user);
+        }
+
+        let session = builder.agent().connect()?;
+
+        let file = session.sftp().and_then(|sftp| sftp.open(Path::new(path))).map_err(io::Error::from)?;
+
+        Ok(Self {
+            inner   : Inner::Remote(file),
+            name    : uri.to_string(),
+        })
+    }
+
+    fn named(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// The input as given, or the path for sources opened from a path.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The whole input, for memory-mapped and in-memory sources.
+    pub fn as_slice(&self) -> Option<&[u8]> {
+        match &self.inner {
+            Inner::Mapped(c) => Some(c.get_ref()),
+            Inner::Memory(c) => Some(c.get_ref()),
+            _ => None,
+        }
+    }
+
+    /// Total size, which reads a spooled stream to the end.
+    pub fn size(&mut self) -> Result<u64> {
+        let pos = self.stream_position()?;
+        let size = self.seek(SeekFrom::End(0))?;
+        self.seek(SeekFrom::Start(pos))?;
+        Ok(size)
+    }
+}
+
+impl fmt::Debug for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Source").field(&self.name).finish()
+    }
+}
+
+impl Read for Source {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match &mut self.inner {
+            Inner::File(f) => f.read(buf),
+            Inner::Mapped(c) => c.read(buf),
+            Inner::Memory(c) => c.read(buf),
+            Inner::Spooled(s) => s.read(buf),
+
+            #[cfg(feature = "ssh2")]
+            Inner::Remote(f) => f.read(buf),
+        }
+    }
+}
+
+impl Seek for Source {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match &mut self.inner {
+            Inner::File(f) => f.seek(pos),
+            Inner::Mapped(c) => c.seek(pos),
+            Inner::Memory(c) => c.seek(pos),
+            Inner::Spooled(s) => s.seek(pos),
+
+            #[cfg(feature = "ssh2")]
+            Inner::Remote(f) => f.seek(pos),
+        }
+    }
+}
+
+/// `scheme:rest`, where a single letter is a Windows drive rather than a scheme.
+fn split_scheme(uri: &str) -> Option<(&str, &str)> {
+    let (scheme, rest) = uri.split_once(':')?;
+
+    let valid = scheme.len() > 1
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+
+    valid.then_some((scheme, rest))
+}
+
+/// Path of `file:` URIs: `file:rel`, `file:/abs`, `file:///abs` and `file://localhost/abs`.
+fn file_uri_path(rest: &str) -> Result<String> {
+    let path = match rest.strip_prefix("//") {
+        None => rest,
+        Some(authority_path) => {
+            let slash = authority_path.find('/').unwrap_or(authority_path.len());
+            match &authority_path[..slash] {
+                "" | "localhost" => &authority_path[slash..],
+                host => return Err(invalid_input(format!("file URI names a remote host {host:?}"))),
+            }
+        },
+    };
+
+    // percent escapes, mostly for spaces
+    let bytes = path.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%').then(|| path.get(i + 1..i + 3)).flatten().and_then(|h| u8::from_str_radix(h, 16).ok());
+
+        match escaped {
+            Some(b) => {
+                out.push(b);
+                i += 3;
+            },
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            },
+        }
+    }
+
+    String::from_utf8(out).map_err(|_| invalid_input(format!("file URI path is not UTF-8: {path:?}")))
+}
+
+fn decode_hex(s: &str) -> Result<Vec<u8>> {
+    let digits: Vec<u8> = s.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if digits.len() % 2 != 0 {
+        return Err(invalid_input("odd number of hex digits in mem: input"));
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|h| u8::from_str_radix(h, 16).ok())
+                .ok_or_else(|| invalid_input(format!("invalid hex byte {:?} in mem: input", String::from_utf8_lossy(pair))))
+        })
+        .collect()
+}
+
+enum Store {
+    Memory(Vec<u8>),
+    File(fs::File),
+}
+
+/// A non-seekable stream that keeps what it has read so it can seek.
+///
+/// Without `keep` nothing is stored and `len` always equals `pos`.
+struct Spool {
+    reader  : Box<dyn Read>,
+    store   : Store,
+    keep    : bool,
+    len     : u64,
+    pos     : u64,
+    eof     : bool,
+}
+
+impl Spool {
+    fn new(reader: Box<dyn Read>, keep: bool) -> Self {
+        Self {
+            reader,
+            store   : Store::Memory(Vec::new()),
+            keep,
+            len     : 0,
+            pos     : 0,
+            eof     : false,
+        }
+    }
+
+    fn append(&mut self, data: &[u8]) -> Result<()> {
+        match &mut self.store {
+            _ if !self.keep => {},
+            Store::Memory(v) if v.len() + data.len() <= SPOOL_MEMORY => v.extend_from_slice(data),
+            Store::Memory(v) => {
+                let mut file = tempfile::tempfile()?;
+                file.write_all(v)?;
+                file.write_all(data)?;
+                self.store = Store::File(file);
+            },
+            Store::File(f) => {
+                f.seek(SeekFrom::End(0))?;
+                f.write_all(data)?;
+            },
+        }
+
+        self.len += data.len() as u64;
+        Ok(())
+    }
+
+    /// Reads from the source until `target` bytes are stored, or to the end for `None`.
+    fn pull(&mut self, target: Option<u64>) -> Result<()> {
+        let mut chunk = vec![0u8; SPOOL_CHUNK];
+
+        while !self.eof && target.is_none_or(|t| self.len < t) {
+            // unkept input must not be read past the target, it could not be returned later
+            let want = match target {
+                Some(t) if !self.keep => (t - self.len).min(chunk.len() as u64) as usize,
+                _ => chunk.len(),
+            };
+
+            let n = match self.reader.read(&mut chunk[..want]) {
+                Ok(n) => n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+
+            if n == 0 {
+                self.eof = true;
+            } else {
+                self.append(&chunk[..n])?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Read for Spool {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if !self.keep {
+            let n = self.reader.read(buf)?;
+            self.pos = self.pos.saturating_add(n as u64);
+            self.len = self.pos;
+            return Ok(n);
+        }
+
+        if self.pos >= self.len {
+            self.pull(Some(self.pos.saturating_add(1)))?;
+        }
+
+        let available = self.len.saturating_sub(self.pos) as usize;
+        let n = available.min(buf.len());
+        if n == 0 {
+            return Ok(0);
+        }
+
+        match &mut self.store {
+            Store::Memory(v) => buf[..n].copy_from_slice(&v[self.pos as usize..self.pos as usize + n]),
+            Store::File(f) => {
+                f.seek(SeekFrom::Start(self.pos))?;
+                f.read_exact(&mut buf[..n])?;
+            },
+        }
+
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for Spool {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(pos) => Some(pos),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+            SeekFrom::End(delta) => {
+                self.pull(None)?;
+                self.len.checked_add_signed(delta)
+            },
+        };
+
+        let target = target.ok_or_else(|| invalid_input("invalid seek to a negative or overflowing position"))?;
+
+        // everything before `len` is gone, including what seeking to the end skipped
+        if !self.keep && target < self.len {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "cannot seek back in a sequential input"));
+        }
+
+        // like a file, seeking past the end is allowed, reads there return nothing
+        self.pull(Some(target))?;
+        self.pos = target;
+        Ok(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "ssh2")]
+    #[test]
+    fn rejects_ssh_without_authority() {
+        for uri in ["ssh:x", "SSH:host/path", "ssh:/host/path"] {
+            assert_eq!(Source::open(uri).unwrap_err().kind(), io::ErrorKind::InvalidInput, "{uri}");
+        }
+    }
+
+    #[test]
+    fn spool_reads_at_the_last_position() {
+        let mut source = Source::spool(&b"abc"[..], "test");
+        assert_eq!(source.seek(SeekFrom::Start(u64::MAX)).unwrap(), u64::MAX);
+        assert_eq!(source.read(&mut [0u8; 4]).unwrap(), 0);
+        assert_eq!(source.size().unwrap(), 3);
+    }
+}
@@ -0,0 +1,113 @@
+//! The `Read` and `Write` traits the parsing core is written against: those of
+//! `std::io` with the `std` feature, otherwise minimal equivalents implemented
+//! for slices, `Vec<u8>` and [`SliceCursor`](super::SliceCursor).
+
+#[cfg(feature = "std")]
+pub use std::io::{Read, Write};
+
+#[cfg(not(feature = "std"))]
+pub use self::bare::{Read, Write};
+
+#[cfg(not(feature = "std"))]
+mod bare {
+    use alloc::vec::Vec;
+
+    use super::super::error::{Error, ErrorKind, Result};
+
+    /// A source of bytes, the part of `std::io::Read` the parsers use.
+    pub trait Read {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+        fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.read(buf) {
+                    Ok(0) => return Err(Error::new(ErrorKind::UnexpectedEof, "failed to fill whole buffer")),
+                    Ok(n) => buf = &mut buf[n..],
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {},
+                    Err(e) => return Err(e),
+                }
+            }
+
+            Ok(())
+        }
+
+        fn read_to_end(&mut self, out: &mut Vec<u8>) -> Result<usize> {
+            let start = out.len();
+            let mut chunk = [0u8; 0x200];
+
+            loop {
+                match self.read(&mut chunk) {
+                    Ok(0) => return Ok(out.len() - start),
+                    Ok(n) => out.extend_from_slice(&chunk[..n]),
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {},
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+    }
+
+    /// A sink of bytes, the part of `std::io::Write` the writers use.
+    pub trait Write {
+        fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+
+        fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.write(buf) {
+                    Ok(0) => return Err(Error::new(ErrorKind::WriteZero, "failed to write whole buffer")),
+                    Ok(n) => buf = &buf[n..],
+                    Err(e) if e.kind() == ErrorKind::Interrupted => {},
+                    Err(e) => return Err(e),
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    impl<R: Read + ?Sized> Read for &mut R {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            (**self).read(buf)
+        }
+    }
+
+    impl Read for &[u8] {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let n = self.len().min(buf.len());
+            let (head, rest) = self.split_at(n);
+            buf[..n].copy_from_slice(head);
+            *self = rest;
+            Ok(n)
+        }
+    }
+
+    impl<W: Write + ?Sized> Write for &mut W {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            (**self).write(buf)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            (**self).flush()
+        }
+    }
+
+    impl Write for &mut [u8] {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            let n = self.len().min(buf.len());
+            let (head, rest) = core::mem::take(self).split_at_mut(n);
+            head.copy_from_slice(&buf[..n]);
+            *self = rest;
+            Ok(n)
+        }
+    }
+
+    impl Write for Vec<u8> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+}
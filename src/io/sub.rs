@@ -0,0 +1,96 @@
+use std::io::{self, Read, Seek, SeekFrom};
+use super::error::Result;
+
+/// A window of `len` bytes starting at `start` in the underlying stream.
+///
+/// Reads stop at the end of the window and seeking is relative to its start,
+/// so parsers can treat an embedded blob like a file of its own.
+pub struct SubReader<R> {
+    inner   : R,
+    start   : u64,
+    len     : u64,
+    pos     : u64,
+}
+
+impl<R: Read + Seek> SubReader<R> {
+    pub fn new(mut inner: R, start: u64, len: u64) -> Result<Self> {
+        inner.seek(SeekFrom::Start(start))?;
+
+        Ok(Self {
+            inner,
+            start,
+            len,
+            pos: 0,
+        })
+    }
+}
+
+impl<R> SubReader<R> {
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    pub const fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Offset of the window in the underlying stream.
+    pub const fn start(&self) -> u64 {
+        self.start
+    }
+
+    pub const fn pos(&self) -> u64 {
+        self.pos
+    }
+
+    pub const fn size(&self) -> u64 {
+        self.len
+    }
+
+    pub const fn remaining(&self) -> u64 {
+        self.len.saturating_sub(self.pos)
+    }
+}
+
+impl<R: Read> Read for SubReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let max = self.remaining().min(buf.len() as u64) as usize;
+        if max == 0 {
+            return Ok(0);
+        }
+
+        let n = self.inner.read(&mut buf[..max])?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for SubReader<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(n) => Some(n),
+            SeekFrom::End(n) => self.len.checked_add_signed(n),
+            SeekFrom::Current(n) => self.pos.checked_add_signed(n),
+        };
+
+        let new_pos = new_pos.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position")
+        })?;
+
+        let inner_pos = self.start.checked_add(new_pos).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "invalid seek to a negative or overflowing position")
+        })?;
+
+        self.inner.seek(SeekFrom::Start(inner_pos))?;
+        self.pos = new_pos;
+        Ok(new_pos)
+    }
+
+    fn stream_position(&mut self) -> io::Result<u64> {
+        Ok(self.pos)
+    }
+}
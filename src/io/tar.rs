@@ -0,0 +1,487 @@
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+use std::time::SystemTime;
+
+use super::error::Result;
+use super::time;
+
+const BLOCK_SIZE: u64 = 512;
+
+/// Longest GNU long name or link accepted, like `PATH_MAX` on Linux.
+const MAX_PATH: u64 = 4096;
+
+/// Largest PAX extended header accepted.
+const MAX_PAX_SIZE: u64 = 1 << 20;
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Discards `n` bytes, works on non-seekable streams.
+pub(crate) fn skip<R: Read + ?Sized>(r: &mut R, n: u64) -> Result<()> {
+    let copied = io::copy(&mut r.take(n), &mut io::sink())?;
+
+    if copied != n {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(())
+}
+
+/// Fills `buf`, returning `false` on a clean end of stream before the first byte.
+pub(crate) fn read_record<R: Read + ?Sized>(r: &mut R, buf: &mut [u8]) -> Result<bool> {
+    let mut filled = 0;
+
+    while filled < buf.len() {
+        match r.read(&mut buf[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+            Err(e) => return Err(e),
+        }
+    }
+
+    Ok(true)
+}
+
+/// File type of an archive member, shared by the tar and cpio readers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    Regular,
+    HardLink,
+    Symlink,
+    CharDevice,
+    BlockDevice,
+    Directory,
+    Fifo,
+    Socket,
+    Other(u8),
+}
+
+impl EntryKind {
+    fn from_tar(flag: u8) -> Self {
+        match flag {
+            b'0' | b'\0' | b'7' => Self::Regular,
+            b'1'                => Self::HardLink,
+            b'2'                => Self::Symlink,
+            b'3'                => Self::CharDevice,
+            b'4'                => Self::BlockDevice,
+            b'5'                => Self::Directory,
+            b'6'                => Self::Fifo,
+            f                   => Self::Other(f),
+        }
+    }
+
+    /// From the `S_IFMT` bits of a Unix mode.
+    pub(crate) fn from_mode(mode: u32) -> Self {
+        match mode & 0o170000 {
+            0o100000 => Self::Regular,
+            0o120000 => Self::Symlink,
+            0o020000 => Self::CharDevice,
+            0o060000 => Self::BlockDevice,
+            0o040000 => Self::Directory,
+            0o010000 => Self::Fifo,
+            0o140000 => Self::Socket,
+            _        => Self::Other(0),
+        }
+    }
+}
+
+/// Header of a tar member with GNU long names and PAX records already applied.
+#[derive(Debug, Clone)]
+pub struct TarHeader {
+    pub name        : String,
+    pub kind        : EntryKind,
+    pub mode        : u32,
+    pub uid         : u64,
+    pub gid         : u64,
+    pub size        : u64,
+    pub mtime       : SystemTime,
+    pub link_name   : Option<String>,
+    pub uname       : String,
+    pub gname       : String,
+    pub dev_major   : u32,
+    pub dev_minor   : u32,
+
+    /// PAX extended header records (global then per-file), including `SCHILY.xattr.*` attributes.
+    pub pax         : BTreeMap<String, String>,
+}
+
+fn cstr(field: &[u8]) -> &[u8] {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    &field[..end]
+}
+
+fn string(field: &[u8]) -> String {
+    String::from_utf8_lossy(cstr(field)).into_owned()
+}
+
+/// Parses an octal field, or a GNU base-256 field when the high bit of the first byte is set.
+///
+/// Base-256 fields are big-endian two's complement, negative when bit 0x40 of the first byte is set.
+fn signed_number(field: &[u8]) -> Result<i64> {
+    if field.first().is_some_and(|&b| b & 0x80 != 0) {
+        let negative = field[0] & 0x40 != 0;
+        let mut n: i64 = if negative { -1 } else { 0 };
+
+        for (i, &b) in field.iter().enumerate() {
+            // the first byte carries the marker bit, replace it with the sign
+            let b = match i {
+                0 if negative => b | 0x80,
+                0 => b & 0x7F,
+                _ => b,
+            };
+
+            if (n << 8) >> 8 != n {
+                return Err(invalid_data("base-256 number overflow"));
+            }
+            n = (n << 8) | b as i64;
+        }
+
+        return Ok(n);
+    }
+
+    let s = std::str::from_utf8(cstr(field)).map_err(|_| invalid_data("invalid octal field"))?;
+    let s = s.trim_matches(|c| c == ' ' || c == '\0');
+
+    if s.is_empty() {
+        return Ok(0);
+    }
+
+    u64::from_str_radix(s, 8).ok()
+        .and_then(|n| i64::try_from(n).ok())
+        .ok_or_else(|| invalid_data(format!("invalid octal field: {s:?}")))
+}
+
+fn number(field: &[u8]) -> Result<u64> {
+    let n = signed_number(field)?;
+    u64::try_from(n).map_err(|_| invalid_data(format!("negative value {n} in an unsigned field")))
+}
+
+fn verify_checksum(block: &[u8; BLOCK_SIZE as usize]) -> Result<()> {
+    let expected = number(&block[148..156])?;
+
+    let (mut unsigned, mut signed) = (0u64, 0i64);
+    for (i, &b) in block.iter().enumerate() {
+        let b = if (148..156).contains(&i) { b' ' } else { b };
+        unsigned += b as u64;
+        signed += b as i8 as i64;
+    }
+
+    if expected != unsigned && expected as i64 != signed {
+        return Err(invalid_data(format!("header checksum mismatch: expected 0o{expected:o}, got 0o{unsigned:o}")));
+    }
+
+    Ok(())
+}
+
+/// Parses `"<len> <key>=<value>\n"` records.
+fn parse_pax(data: &[u8], out: &mut BTreeMap<String, String>) -> Result<()> {
+    let mut rest = data;
+
+    while !rest.is_empty() {
+        let space = rest.iter().position(|&b| b == b' ').ok_or_else(|| invalid_data("malformed pax record"))?;
+        let len: usize = std::str::from_utf8(&rest[..space]).ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|&n| n > space && n <= rest.len())
+            .ok_or_else(|| invalid_data("malformed pax record length"))?;
+
+        let record = &rest[space + 1..len];
+        let record = record.strip_suffix(b"\n").unwrap_or(record);
+        let eq = record.iter().position(|&b| b == b'=').ok_or_else(|| invalid_data("malformed pax record"))?;
+
+        let key = String::from_utf8_lossy(&record[..eq]).into_owned();
+        let value = String::from_utf8_lossy(&record[eq + 1..]).into_owned();
+
+        // an empty value deletes the key
+        if value.is_empty() {
+            out.remove(&key);
+        } else {
+            out.insert(key, value);
+        }
+
+        rest = &rest[len..];
+    }
+
+    Ok(())
+}
+
+fn pax_time(s: &str) -> Option<SystemTime> {
+    let (secs, frac) = s.split_once('.').unwrap_or((s, ""));
+    let secs: i64 = secs.parse().ok()?;
+
+    let mut nanos = 0u32;
+    for (i, c) in frac.chars().take(9).enumerate() {
+        nanos += c.to_digit(10)? * 10u32.pow(8 - i as u32);
+    }
+
+    let t = time::unix_to_system_time(secs).ok()?;
+    if secs < 0 {
+        t.checked_sub(std::time::Duration::from_nanos(nanos as u64))
+    } else {
+        t.checked_add(std::time::Duration::from_nanos(nanos as u64))
+    }
+}
+
+/// Streaming reader for ustar, GNU and PAX tar archives.
+///
+/// Only reads forward, so it works over pipes and [`Decoder`](super::Decoder)s.
+pub struct TarReader<R> {
+    reader      : R,
+    remaining   : u64,
+    padding     : u64,
+    global_pax  : BTreeMap<String, String>,
+    done        : bool,
+}
+
+impl<R: Read> TarReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            remaining: 0,
+            padding: 0,
+            global_pax: BTreeMap::new(),
+            done: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Advances to the next member, skipping any unread data of the current one.
+    pub fn next_entry(&mut self) -> Result<Option<TarEntry<'_, R>>> {
+        let unread = self.remaining.checked_add(self.padding).ok_or_else(|| invalid_data("entry size overflows"))?;
+        skip(&mut self.reader, unread)?;
+        self.remaining = 0;
+        self.padding = 0;
+
+        let mut pax = self.global_pax.clone();
+        let mut long_name: Option<String> = None;
+        let mut long_link: Option<String> = None;
+
+        loop {
+            if self.done {
+                return Ok(None);
+            }
+
+            let mut block = [0u8; BLOCK_SIZE as usize];
+            if !read_record(&mut self.reader, &mut block)? || block.iter().all(|&b| b == 0) {
+                self.done = true;
+                return Ok(None);
+            }
+
+            verify_checksum(&block)?;
+
+            let flag = block[156];
+            let size = number(&block[124..136])?;
+            let padding = (BLOCK_SIZE - size % BLOCK_SIZE) % BLOCK_SIZE;
+
+            match flag {
+                b'x' | b'g' | b'L' | b'K' => {
+                    let limit = if matches!(flag, b'L' | b'K') { MAX_PATH } else { MAX_PAX_SIZE };
+                    if size > limit {
+                        return Err(invalid_data(format!("{} header of {size} bytes exceeds {limit}", flag as char)));
+                    }
+
+                    let mut data = Vec::new();
+                    (&mut self.reader).take(size).read_to_end(&mut data)?;
+                    if data.len() as u64 != size {
+                        return Err(io::ErrorKind::UnexpectedEof.into());
+                    }
+                    skip(&mut self.reader, padding)?;
+
+                    match flag {
+                        b'x' => parse_pax(&data, &mut pax)?,
+                        b'g' => {
+                            parse_pax(&data, &mut self.global_pax)?;
+                            parse_pax(&data, &mut pax)?;
+                        },
+                        b'L' => long_name = Some(string(&data)),
+                        _    => long_link = Some(string(&data)),
+                    }
+
+                    continue;
+                },
+
+                _ => {},
+            }
+
+            let magic = &block[257..263];
+            let is_ustar = magic == b"ustar\0";
+
+            let mut name = string(&block[0..100]);
+            if is_ustar && block[345] != 0 {
+                name = format!("{}/{}", string(&block[345..500]), name);
+            }
+
+            let link = string(&block[157..257]);
+
+            let mut header = TarHeader {
+                name,
+                kind        : EntryKind::from_tar(flag),
+                mode        : number(&block[100..108])? as u32,
+                uid         : number(&block[108..116])?,
+                gid         : number(&block[116..124])?,
+                size,
+                mtime       : time::unix_to_system_time(signed_number(&block[136..148])?)?,
+                link_name   : if link.is_empty() { None } else { Some(link) },
+                uname       : string(&block[265..297]),
+                gname       : string(&block[297..329]),
+                dev_major   : number(&block[329..337])? as u32,
+                dev_minor   : number(&block[337..345])? as u32,
+                pax         : BTreeMap::new(),
+            };
+
+            if let Some(n) = long_name {
+                header.name = n;
+            }
+
+            if let Some(l) = long_link {
+                header.link_name = Some(l);
+            }
+
+            Self::apply_pax(&mut header, &pax)?;
+            header.pax = pax;
+
+            // a directory with a trailing slash is reported as a regular file by old writers
+            if header.kind == EntryKind::Regular && header.name.ends_with('/') {
+                header.kind = EntryKind::Directory;
+            }
+
+            let data_size = match header.kind {
+                EntryKind::HardLink | EntryKind::Symlink | EntryKind::Directory => 0,
+                _ => header.size,
+            };
+
+            self.remaining = data_size;
+            self.padding = (BLOCK_SIZE - data_size % BLOCK_SIZE) % BLOCK_SIZE;
+
+            return Ok(Some(TarEntry { header, archive: self }));
+        }
+    }
+
+    fn apply_pax(header: &mut TarHeader, pax: &BTreeMap<String, String>) -> Result<()> {
+        let parse = |k: &str, v: &str| -> Result<u64> {
+            v.parse().map_err(|_| invalid_data(format!("invalid pax {k}: {v:?}")))
+        };
+
+        for (k, v) in pax {
+            match k.as_str() {
+                "path"      => header.name = v.clone(),
+                "linkpath"  => header.link_name = Some(v.clone()),
+                "size"      => header.size = parse(k, v)?,
+                "uid"       => header.uid = parse(k, v)?,
+                "gid"       => header.gid = parse(k, v)?,
+                "uname"     => header.uname = v.clone(),
+                "gname"     => header.gname = v.clone(),
+                "mtime"     => {
+                    header.mtime = pax_time(v).ok_or_else(|| invalid_data(format!("invalid pax mtime: {v:?}")))?;
+                },
+                _ => {},
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A tar member; reading yields exactly its data.
+pub struct TarEntry<'a, R> {
+    header  : TarHeader,
+    archive : &'a mut TarReader<R>,
+}
+
+impl<R> TarEntry<'_, R> {
+    pub const fn header(&self) -> &TarHeader {
+        &self.header
+    }
+
+    /// Unread bytes of the entry data.
+    pub fn remaining(&self) -> u64 {
+        self.archive.remaining
+    }
+}
+
+impl<R: Read> Read for TarEntry<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let max = self.archive.remaining.min(buf.len() as u64) as usize;
+        if max == 0 {
+            return Ok(0);
+        }
+
+        let n = self.archive.reader.read(&mut buf[..max])?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        self.archive.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set_checksum(block: &mut [u8]) {
+        block[148..156].fill(b' ');
+        let sum: u32 = block.iter().map(|&b| b as u32).sum();
+        block[148..155].copy_from_slice(format!("{sum:06o}\0").as_bytes());
+    }
+
+    /// A header block of type `flag` whose size field is `size`.
+    fn block(flag: u8, size: &[u8; 12]) -> Vec<u8> {
+        let mut block = vec![0u8; BLOCK_SIZE as usize];
+        block[..4].copy_from_slice(b"file");
+        block[124..136].copy_from_slice(size);
+        block[156] = flag;
+        block[257..263].copy_from_slice(b"ustar\0");
+        set_checksum(&mut block);
+        block
+    }
+
+    #[test]
+    fn parses_base256_numbers() {
+        assert_eq!(signed_number(&[0x80, 0, 0, 0, 0, 0, 0, 1, 0]).unwrap(), 256);
+        assert_eq!(signed_number(&[0xFF; 12]).unwrap(), -1);
+        assert_eq!(signed_number(&[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE, 0]).unwrap(), -512);
+        assert!(signed_number(&[0x80, 0, 0, 0, 0x80, 0, 0, 0, 0, 0, 0, 0]).is_err());
+        assert!(number(&[0xFF; 8]).is_err());
+    }
+
+    #[test]
+    fn rejects_oversized_entries() {
+        let kind = |data: Vec<u8>| TarReader::new(data.as_slice()).next_entry().err().expect("crafted header should be rejected").kind();
+
+        let huge = [0x80, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF];
+        assert_eq!(kind(block(b'0', &huge)), io::ErrorKind::InvalidData);
+        assert_eq!(kind(block(b'L', b"00000010001\0")), io::ErrorKind::InvalidData);
+        assert_eq!(kind(block(b'x', b"00010000001\0")), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn rejects_pax_size_overflow() {
+        let record = b"29 size=18446744073709551615\n";
+        let mut data = block(b'x', b"00000000035\0");
+        data.extend_from_slice(record);
+        data.resize(2 * BLOCK_SIZE as usize, 0);
+        data.extend_from_slice(&block(b'0', b"00000000000\0"));
+
+        let mut tar = TarReader::new(data.as_slice());
+        assert_eq!(tar.next_entry().unwrap().unwrap().header().size, u64::MAX);
+        assert_eq!(tar.next_entry().err().unwrap().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn reads_negative_mtime() {
+        let mut data = block(b'0', b"00000000000\0");
+        data[136..148].fill(0xFF);
+        set_checksum(&mut data);
+
+        let mut tar = TarReader::new(data.as_slice());
+        let entry = tar.next_entry().unwrap().unwrap();
+        assert_eq!(entry.header().mtime, SystemTime::UNIX_EPOCH - std::time::Duration::from_secs(1));
+    }
+}
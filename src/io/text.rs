@@ -0,0 +1,316 @@
+use std::io::{self, Read};
+
+use super::error::Result;
+
+const READ_CHUNK: usize = 0x2000;
+
+// bytes examined to guess the encoding of text without a BOM
+const SNIFF_LEN: usize = 0x1000;
+
+/// Windows-1252 code points for 0x80..=0x9F, the rest of the range matches Latin-1.
+const WINDOWS_1252: [char; 32] = [
+    '\u{20AC}', '\u{0081}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{008D}', '\u{017D}', '\u{008F}',
+    '\u{0090}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{009D}', '\u{017E}', '\u{0178}',
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// UTF-8, where lines that are not valid UTF-8 are decoded as Windows-1252.
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    Windows1252,
+}
+
+impl Encoding {
+    /// Bytes per code unit, which is also the size of a line terminator.
+    pub const fn unit_size(self) -> usize {
+        match self {
+            Encoding::Utf16Le | Encoding::Utf16Be => 2,
+            _ => 1,
+        }
+    }
+
+    pub const fn bom(self) -> &'static [u8] {
+        match self {
+            Encoding::Utf8 => b"\xEF\xBB\xBF",
+            Encoding::Utf16Le => b"\xFF\xFE",
+            Encoding::Utf16Be => b"\xFE\xFF",
+            Encoding::Windows1252 => b"",
+        }
+    }
+
+    /// Guesses the encoding from the first bytes of a stream, returning it with the BOM length.
+    pub fn detect(data: &[u8]) -> (Self, usize) {
+        for encoding in [Encoding::Utf8, Encoding::Utf16Le, Encoding::Utf16Be] {
+            if data.starts_with(encoding.bom()) {
+                return (encoding, encoding.bom().len());
+            }
+        }
+
+        // mostly ASCII UTF-16 has a zero in every other byte
+        let pairs = data.len() / 2;
+        if pairs > 0 {
+            let even = data.iter().step_by(2).filter(|&&b| b == 0).count();
+            let odd = data.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+
+            if odd * 10 >= pairs * 4 && even * 10 < pairs {
+                return (Encoding::Utf16Le, 0);
+            }
+            if even * 10 >= pairs * 4 && odd * 10 < pairs {
+                return (Encoding::Utf16Be, 0);
+            }
+        }
+
+        // any valid multibyte sequence means UTF-8 with some Windows-1252 lines mixed in,
+        // since those are decoded line by line
+        let mut chunks = data.utf8_chunks().peekable();
+        let mut multibyte = false;
+        let mut invalid = false;
+
+        while let Some(chunk) = chunks.next() {
+            multibyte |= !chunk.valid().is_ascii();
+
+            // a sequence cut off at the end of the sample does not count
+            invalid |= !chunk.invalid().is_empty() && chunks.peek().is_some();
+        }
+
+        if invalid && !multibyte { (Encoding::Windows1252, 0) } else { (Encoding::Utf8, 0) }
+    }
+
+    pub fn decode(self, data: &[u8]) -> String {
+        let units = |f: fn([u8; 2]) -> u16| {
+            let mut units: Vec<u16> = data.chunks_exact(2).map(|c| f([c[0], c[1]])).collect();
+            if data.len() % 2 != 0 {
+                units.push(0xFFFD);
+            }
+            String::from_utf16_lossy(&units)
+        };
+
+        match self {
+            Encoding::Utf8 => match std::str::from_utf8(data) {
+                Ok(s) => s.to_string(),
+                Err(_) => Encoding::Windows1252.decode(data),
+            },
+            Encoding::Utf16Le => units(u16::from_le_bytes),
+            Encoding::Utf16Be => units(u16::from_be_bytes),
+            Encoding::Windows1252 => data
+                .iter()
+                .map(|&b| match b {
+                    0x80..=0x9F => WINDOWS_1252[(b - 0x80) as usize],
+                    _ => b as char,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    /// The last line of a stream that does not end with a terminator.
+    None,
+    Lf,
+    CrLf,
+    Cr,
+}
+
+/// A decoded line without its terminator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Line {
+    pub text    : String,
+
+    /// Byte offset of the line in the stream, and its raw length including the terminator.
+    pub offset  : u64,
+    pub len     : u64,
+    pub ending  : LineEnding,
+}
+
+impl Line {
+    pub const fn end(&self) -> u64 {
+        self.offset + self.len
+    }
+}
+
+/// Reads text line by line, detecting the encoding from a BOM or the first bytes.
+///
+/// UTF-8, UTF-16LE/BE and Windows-1252 are recognized, lines may end in LF,
+/// CRLF or a lone CR, and each line carries its byte offset counted from where
+/// the reader started, including any BOM. Over a [`SubReader`](super::SubReader)
+/// that is relative to its window, so add [`SubReader::start`](super::SubReader::start)
+/// for a file offset. Only one line is decoded at a time.
+pub struct TextReader<R> {
+    inner       : R,
+    buf         : Vec<u8>,
+    head        : usize,
+
+    // stream offset of buf[head]
+    pos         : u64,
+    encoding    : Option<Encoding>,
+    sniffed     : bool,
+    eof         : bool,
+}
+
+impl<R: Read> TextReader<R> {
+    pub const fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buf         : Vec::new(),
+            head        : 0,
+            pos         : 0,
+            encoding    : None,
+            sniffed     : false,
+            eof         : false,
+        }
+    }
+
+    /// Skips detection; a BOM matching `encoding` is still skipped.
+    pub const fn with_encoding(inner: R, encoding: Encoding) -> Self {
+        let mut reader = Self::new(inner);
+        reader.encoding = Some(encoding);
+        reader
+    }
+
+    /// The encoding in use, sniffing the start of the stream if not done yet.
+    pub fn encoding(&mut self) -> Result<Encoding> {
+        match self.encoding {
+            Some(encoding) if self.sniffed => Ok(encoding),
+            _ => self.sniff(),
+        }
+    }
+
+    /// Stream offset of the next line.
+    pub const fn pos(&self) -> u64 {
+        self.pos
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    pub const fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    fn fill(&mut self) -> Result<()> {
+        self.buf.drain(..self.head);
+        self.head = 0;
+
+        let len = self.buf.len();
+        self.buf.resize(len + READ_CHUNK, 0);
+
+        let n = loop {
+            match self.inner.read(&mut self.buf[len..]) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => {
+                    self.buf.truncate(len);
+                    return Err(e);
+                },
+            }
+        };
+
+        self.buf.truncate(len + n);
+        self.eof = n == 0;
+        Ok(())
+    }
+
+    fn sniff(&mut self) -> Result<Encoding> {
+        while self.buf.len() < SNIFF_LEN && !self.eof {
+            self.fill()?;
+        }
+
+        let (encoding, bom_len) = match self.encoding {
+            Some(encoding) => (encoding, if self.buf.starts_with(encoding.bom()) { encoding.bom().len() } else { 0 }),
+            None => Encoding::detect(&self.buf[..self.buf.len().min(SNIFF_LEN)]),
+        };
+
+        self.encoding = Some(encoding);
+        self.sniffed = true;
+        self.head = bom_len;
+        self.pos = bom_len as u64;
+        Ok(encoding)
+    }
+
+    fn unit_at(&self, i: usize, encoding: Encoding) -> u16 {
+        match encoding {
+            Encoding::Utf16Le => u16::from_le_bytes([self.buf[i], self.buf[i + 1]]),
+            Encoding::Utf16Be => u16::from_be_bytes([self.buf[i], self.buf[i + 1]]),
+            _ => self.buf[i] as u16,
+        }
+    }
+
+    fn take(&mut self, end: usize, terminator: usize, ending: LineEnding, encoding: Encoding) -> Line {
+        let len = (end + terminator - self.head) as u64;
+        let line = Line {
+            text    : encoding.decode(&self.buf[self.head..end]),
+            offset  : self.pos,
+            len,
+            ending,
+        };
+
+        self.head = end + terminator;
+        self.pos += len;
+        line
+    }
+
+    /// The next line, or `None` at the end of the stream.
+    pub fn read_line(&mut self) -> Result<Option<Line>> {
+        let encoding = self.encoding()?;
+        let unit = encoding.unit_size();
+
+        // scan offset relative to head, so it survives fill() compacting the buffer
+        let mut scanned = 0;
+
+        loop {
+            let mut i = self.head + scanned;
+
+            while i + unit <= self.buf.len() {
+                match self.unit_at(i, encoding) {
+                    0x0A => return Ok(Some(self.take(i, unit, LineEnding::Lf, encoding))),
+                    0x0D if i + 2 * unit <= self.buf.len() => {
+                        return Ok(Some(if self.unit_at(i + unit, encoding) == 0x0A {
+                            self.take(i, 2 * unit, LineEnding::CrLf, encoding)
+                        } else {
+                            self.take(i, unit, LineEnding::Cr, encoding)
+                        }));
+                    },
+                    0x0D if self.eof => return Ok(Some(self.take(i, unit, LineEnding::Cr, encoding))),
+                    // a CR at the end of the buffer needs the next unit to tell CRLF apart
+                    0x0D => break,
+                    _ => i += unit,
+                }
+            }
+
+            if self.eof {
+                if self.head == self.buf.len() {
+                    return Ok(None);
+                }
+
+                let end = self.buf.len();
+                return Ok(Some(self.take(end, 0, LineEnding::None, encoding)));
+            }
+
+            scanned = i - self.head;
+            self.fill()?;
+        }
+    }
+
+    pub fn lines(self) -> Lines<R> {
+        Lines { reader: self }
+    }
+}
+
+/// Iterator returned by [`TextReader::lines`].
+pub struct Lines<R> {
+    reader  : TextReader<R>,
+}
+
+impl<R: Read> Iterator for Lines<R> {
+    type Item = Result<Line>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.reader.read_line().transpose()
+    }
+}
@@ -0,0 +1,80 @@
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use super::error::Result;
+
+/// Seconds between 1601-01-01 (FILETIME epoch) and 1970-01-01.
+pub const FILETIME_UNIX_DELTA: u64 = 11_644_473_600;
+
+/// Seconds between 1904-01-01 (Mac HFS epoch) and 1970-01-01.
+pub const HFS_UNIX_DELTA: u64 = 2_082_844_800;
+
+const FILETIME_TICKS_PER_SECOND: u64 = 10_000_000;
+
+fn invalid_time(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{what} out of range"))
+}
+
+fn from_unix_offset(secs: i64, nanos: u32) -> Option<SystemTime> {
+    let t = if secs >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64))?
+    } else {
+        UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs()))?
+    };
+
+    t.checked_add(Duration::from_nanos(nanos as u64))
+}
+
+/// Converts a FILETIME interval (100ns ticks) to a [`Duration`].
+pub fn filetime_duration(ticks: u64) -> Duration {
+    Duration::new(ticks / FILETIME_TICKS_PER_SECOND, ((ticks % FILETIME_TICKS_PER_SECOND) * 100) as u32)
+}
+
+/// Converts a Windows FILETIME (100ns ticks since 1601-01-01 UTC).
+pub fn filetime_to_system_time(ticks: u64) -> Result<SystemTime> {
+    let d = filetime_duration(ticks);
+    let secs = d.as_secs() as i64 - FILETIME_UNIX_DELTA as i64;
+
+    from_unix_offset(secs, d.subsec_nanos()).ok_or_else(|| invalid_time("FILETIME"))
+}
+
+/// Converts seconds since the Unix epoch.
+pub fn unix_to_system_time(secs: i64) -> Result<SystemTime> {
+    from_unix_offset(secs, 0).ok_or_else(|| invalid_time("unix time"))
+}
+
+/// Converts a Mac HFS timestamp (seconds since 1904-01-01).
+pub fn hfs_to_system_time(secs: u32) -> Result<SystemTime> {
+    unix_to_system_time(secs as i64 - HFS_UNIX_DELTA as i64)
+}
+
+/// Days between 1970-01-01 and the given proleptic Gregorian date.
+pub(crate) fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = if y >= 0 { y } else { y - 399 } / 400;
+    let yoe = y - era * 400;
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+
+    era * 146_097 + doe - 719_468
+}
+
+/// Converts an MS-DOS date/time pair. DOS timestamps carry no zone and are treated as UTC.
+///
+/// date: bits 15-9 year since 1980, 8-5 month, 4-0 day
+/// time: bits 15-11 hour, 10-5 minute, 4-0 second / 2
+pub fn dos_to_system_time(date: u16, time: u16) -> Result<SystemTime> {
+    let year    = 1980 + (date >> 9) as i64;
+    let month   = ((date >> 5) & 0x0F) as u32;
+    let day     = (date & 0x1F) as u32;
+    let hour    = (time >> 11) as i64;
+    let minute  = ((time >> 5) & 0x3F) as i64;
+    let second  = ((time & 0x1F) * 2) as i64;
+
+    if !(1..=12).contains(&month) || day == 0 || hour > 23 || minute > 59 || second > 59 {
+        return Err(invalid_time("DOS date/time"));
+    }
+
+    let secs = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second;
+    unix_to_system_time(secs)
+}
@@ -0,0 +1,62 @@
+use super::error::Result;
+use super::stream::Write;
+use super::byteorder::{ByteOrder, LittleEndian};
+
+#[allow(unused_imports)]
+use super::byteorder::BigEndian;
+
+/// Extends [`Write`] with methods for writing numbers.
+///
+/// The counterpart of [`ReadExt`](super::ReadExt); the type parameter is
+/// instantiated with either [`BigEndian`] or [`LittleEndian`].
+pub trait WriteExt: Write {
+    fn write_i8(&mut self, n: i8) -> Result<()> {
+        LittleEndian::write_i8(self, n)
+    }
+
+    fn write_u8(&mut self, n: u8) -> Result<()> {
+        LittleEndian::write_u8(self, n)
+    }
+
+    fn write_i16<T: ByteOrder>(&mut self, n: i16) -> Result<()> {
+        T::write_i16(self, n)
+    }
+
+    fn write_u16<T: ByteOrder>(&mut self, n: u16) -> Result<()> {
+        T::write_u16(self, n)
+    }
+
+    fn write_i32<T: ByteOrder>(&mut self, n: i32) -> Result<()> {
+        T::write_i32(self, n)
+    }
+
+    fn write_u32<T: ByteOrder>(&mut self, n: u32) -> Result<()> {
+        T::write_u32(self, n)
+    }
+
+    fn write_i64<T: ByteOrder>(&mut self, n: i64) -> Result<()> {
+        T::write_i64(self, n)
+    }
+
+    fn write_u64<T: ByteOrder>(&mut self, n: u64) -> Result<()> {
+        T::write_u64(self, n)
+    }
+
+    fn write_i128<T: ByteOrder>(&mut self, n: i128) -> Result<()> {
+        T::write_i128(self, n)
+    }
+
+    fn write_u128<T: ByteOrder>(&mut self, n: u128) -> Result<()> {
+        T::write_u128(self, n)
+    }
+
+    fn write_f32<T: ByteOrder>(&mut self, n: f32) -> Result<()> {
+        T::write_f32(self, n)
+    }
+
+    fn write_f64<T: ByteOrder>(&mut self, n: f64) -> Result<()> {
+        T::write_f64(self, n)
+    }
+}
+
+impl<W: Write + ?Sized> WriteExt for W {}
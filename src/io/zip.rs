@@ -0,0 +1,400 @@
+use std::io::{self, Read, Seek, SeekFrom};
+use std::time::SystemTime;
+
+use super::byteorder::LittleEndian;
+use super::decompress::{Compression, Decoder};
+use super::error::Result;
+use super::hash::{Crc32, HashingReader};
+use super::read::ReadExt;
+use super::sub::SubReader;
+
+const LOCAL_HEADER_SIGNATURE: u32   = 0x0403_4B50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4B50;
+const EOCD_SIGNATURE: u32           = 0x0605_4B50;
+const ZIP64_LOCATOR_SIGNATURE: u32  = 0x0706_4B50;
+const ZIP64_EOCD_SIGNATURE: u32     = 0x0606_4B50;
+
+const EOCD_SIZE: u64            = 22;
+const ZIP64_LOCATOR_SIZE: u64   = 20;
+const LOCAL_HEADER_SIZE: u64    = 30;
+const MAX_COMMENT_SIZE: u64     = 0xFFFF;
+
+const ZIP64_EXTRA_ID: u16       = 0x0001;
+
+const FLAG_ENCRYPTED: u16       = 0x0001;
+const FLAG_UTF8: u16            = 0x0800;
+
+pub const METHOD_STORED: u16    = 0;
+pub const METHOD_DEFLATED: u16  = 8;
+
+fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Metadata of an archive member, taken from the central directory.
+#[derive(Debug, Clone)]
+pub struct ZipEntry {
+    /// Name as stored, UTF-8 or (without the UTF-8 flag) usually CP437.
+    pub raw_name            : Vec<u8>,
+    pub name                : String,
+    pub comment             : Vec<u8>,
+    pub version_made_by     : u16,
+    pub flags               : u16,
+    pub method              : u16,
+    pub modified            : Option<SystemTime>,
+    pub crc32               : u32,
+    pub compressed_size     : u64,
+    pub uncompressed_size   : u64,
+    pub external_attributes : u32,
+
+    /// Offset of the local header, already adjusted for data prepended to the archive.
+    pub header_offset       : u64,
+}
+
+impl ZipEntry {
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+
+    pub const fn is_encrypted(&self) -> bool {
+        self.flags & FLAG_ENCRYPTED != 0
+    }
+
+    /// Unix mode bits when the entry was created on a Unix host.
+    pub const fn unix_mode(&self) -> Option<u32> {
+        match self.version_made_by >> 8 {
+            3 => Some(self.external_attributes >> 16),
+            _ => None,
+        }
+    }
+}
+
+struct EndOfCentralDirectory {
+    entries     : u64,
+    cd_size     : u64,
+    cd_offset   : u64,
+
+    /// Where the central directory is expected to end if nothing was prepended.
+    cd_end      : u64,
+    comment     : Vec<u8>,
+}
+
+/// Reads a ZIP archive, including ZIP64 and archives with data prepended
+/// such as self-extracting executables.
+pub struct ZipArchive<R> {
+    reader      : R,
+    entries     : Vec<ZipEntry>,
+    comment     : Vec<u8>,
+    base_offset : u64,
+}
+
+impl<R: Read + Seek> ZipArchive<R> {
+    pub fn new(mut reader: R) -> Result<Self> {
+        let eocd = Self::find_eocd(&mut reader)?;
+
+        // bytes before the archive shift every stored offset by the same amount
+        let base_offset = eocd.cd_offset.checked_add(eocd.cd_size)
+            .and_then(|cd_end| eocd.cd_end.checked_sub(cd_end))
+            .ok_or_else(|| invalid_data("central directory lies past its end record"))?;
+
+        let size = reader.seek(SeekFrom::End(0))?;
+        if eocd.cd_size > size {
+            return Err(invalid_data("central directory larger than the file"));
+        }
+
+        let cd_start = base_offset.checked_add(eocd.cd_offset).ok_or_else(|| invalid_data("central directory offset overflows"))?;
+        reader.seek(SeekFrom::Start(cd_start))?;
+        let cd = reader.read_bytes(eocd.cd_size as usize)?;
+        let mut cd = cd.as_slice();
+
+        let mut entries = Vec::with_capacity(eocd.entries.min(0x10000) as usize);
+        for _ in 0..eocd.entries {
+            let mut entry = Self::read_central_header(&mut cd)?;
+            entry.header_offset = entry.header_offset.checked_add(base_offset)
+                .ok_or_else(|| invalid_data(format!("local header offset of {:?} overflows", entry.name)))?;
+            entries.push(entry);
+        }
+
+        Ok(Self {
+            reader,
+            entries,
+            comment: eocd.comment,
+            base_offset,
+        })
+    }
+
+    fn find_eocd(reader: &mut R) -> Result<EndOfCentralDirectory> {
+        let size = reader.seek(SeekFrom::End(0))?;
+        if size < EOCD_SIZE {
+            return Err(invalid_data("file too small for a zip archive"));
+        }
+
+        let tail_size = size.min(EOCD_SIZE + MAX_COMMENT_SIZE);
+        let tail_start = size - tail_size;
+
+        reader.seek(SeekFrom::Start(tail_start))?;
+        let tail = reader.read_bytes(tail_size as usize)?;
+
+        let sig = EOCD_SIGNATURE.to_le_bytes();
+        let pos = (0..=tail.len() - EOCD_SIZE as usize)
+            .rev()
+            .find(|&i| tail[i..i + 4] == sig)
+            .ok_or_else(|| invalid_data("end of central directory not found"))?;
+
+        let mut r = &tail[pos + 4..];
+        let _disk           = r.read_u16::<LittleEndian>()?;
+        let _cd_disk        = r.read_u16::<LittleEndian>()?;
+        let _disk_entries   = r.read_u16::<LittleEndian>()?;
+        let entries         = r.read_u16::<LittleEndian>()?;
+        let cd_size         = r.read_u32::<LittleEndian>()?;
+        let cd_offset       = r.read_u32::<LittleEndian>()?;
+        let comment_len     = r.read_u16::<LittleEndian>()? as usize;
+        let comment         = r[..comment_len.min(r.len())].to_vec();
+
+        let eocd_pos = tail_start + pos as u64;
+
+        let mut eocd = EndOfCentralDirectory {
+            entries     : entries as u64,
+            cd_size     : cd_size as u64,
+            cd_offset   : cd_offset as u64,
+            cd_end      : eocd_pos,
+            comment,
+        };
+
+        if eocd_pos >= ZIP64_LOCATOR_SIZE {
+            reader.seek(SeekFrom::Start(eocd_pos - ZIP64_LOCATOR_SIZE))?;
+
+            if reader.read_u32::<LittleEndian>()? == ZIP64_LOCATOR_SIGNATURE {
+                let _disk       = reader.read_u32::<LittleEndian>()?;
+                let _eocd64_rel = reader.read_u64::<LittleEndian>()?;
+
+                // the stored offset does not account for prepended data; the record sits
+                // immediately before the locator, so find it from there instead
+                let eocd64_pos = Self::find_zip64_eocd(reader, eocd_pos - ZIP64_LOCATOR_SIZE)?;
+                reader.seek(SeekFrom::Start(eocd64_pos + 4))?;
+
+                if reader.read_u64::<LittleEndian>()? < 44 {
+                    return Err(invalid_data("zip64 end of central directory too small"));
+                }
+
+                let _made_by        = reader.read_u16::<LittleEndian>()?;
+                let _needed         = reader.read_u16::<LittleEndian>()?;
+                let _disk           = reader.read_u32::<LittleEndian>()?;
+                let _cd_disk        = reader.read_u32::<LittleEndian>()?;
+                let _disk_entries   = reader.read_u64::<LittleEndian>()?;
+                eocd.entries        = reader.read_u64::<LittleEndian>()?;
+                eocd.cd_size        = reader.read_u64::<LittleEndian>()?;
+                eocd.cd_offset      = reader.read_u64::<LittleEndian>()?;
+                eocd.cd_end         = eocd64_pos;
+            }
+        }
+
+        Ok(eocd)
+    }
+
+    /// Scans back from the locator for the ZIP64 end record, which is 56 bytes plus
+    /// an optional extensible data sector.
+    fn find_zip64_eocd(reader: &mut R, locator_pos: u64) -> Result<u64> {
+        const MIN_SIZE: u64 = 56;
+
+        let search = locator_pos.min(MIN_SIZE + 0x10000);
+        let start = locator_pos - search;
+
+        reader.seek(SeekFrom::Start(start))?;
+        let buf = reader.read_bytes(search as usize)?;
+
+        let sig = ZIP64_EOCD_SIGNATURE.to_le_bytes();
+        (0..buf.len().saturating_sub(MIN_SIZE as usize - 1))
+            .rev()
+            .find(|&i| buf[i..i + 4] == sig)
+            .map(|i| start + i as u64)
+            .ok_or_else(|| invalid_data("zip64 end of central directory not found"))
+    }
+
+    fn read_central_header(r: &mut &[u8]) -> Result<ZipEntry> {
+        if r.read_u32::<LittleEndian>()? != CENTRAL_HEADER_SIGNATURE {
+            return Err(invalid_data("bad central directory header signature"));
+        }
+
+        let version_made_by     = r.read_u16::<LittleEndian>()?;
+        let _version_needed     = r.read_u16::<LittleEndian>()?;
+        let flags               = r.read_u16::<LittleEndian>()?;
+        let method              = r.read_u16::<LittleEndian>()?;
+        let modified            = r.read_dos_datetime::<LittleEndian>().ok();
+        let crc32               = r.read_u32::<LittleEndian>()?;
+        let compressed_size     = r.read_u32::<LittleEndian>()?;
+        let uncompressed_size   = r.read_u32::<LittleEndian>()?;
+        let name_len            = r.read_u16::<LittleEndian>()? as usize;
+        let extra_len           = r.read_u16::<LittleEndian>()? as usize;
+        let comment_len         = r.read_u16::<LittleEndian>()? as usize;
+        let _disk_start         = r.read_u16::<LittleEndian>()?;
+        let _internal_attr      = r.read_u16::<LittleEndian>()?;
+        let external_attributes = r.read_u32::<LittleEndian>()?;
+        let header_offset       = r.read_u32::<LittleEndian>()?;
+        let raw_name            = r.read_bytes(name_len)?;
+        let extra               = r.read_bytes(extra_len)?;
+        let comment             = r.read_bytes(comment_len)?;
+
+        let name = if flags & FLAG_UTF8 != 0 {
+            String::from_utf8(raw_name.clone()).map_err(|_| invalid_data("invalid utf-8 entry name"))?
+        } else {
+            String::from_utf8_lossy(&raw_name).into_owned()
+        };
+
+        let mut entry = ZipEntry {
+            raw_name,
+            name,
+            comment,
+            version_made_by,
+            flags,
+            method,
+            modified,
+            crc32,
+            compressed_size     : compressed_size as u64,
+            uncompressed_size   : uncompressed_size as u64,
+            external_attributes,
+            header_offset       : header_offset as u64,
+        };
+
+        Self::apply_zip64_extra(&mut entry, &extra)?;
+        Ok(entry)
+    }
+
+    /// The ZIP64 extra field holds, in order, only the values whose 32-bit field is saturated.
+    fn apply_zip64_extra(entry: &mut ZipEntry, mut extra: &[u8]) -> Result<()> {
+        while extra.len() >= 4 {
+            let id = extra.read_u16::<LittleEndian>()?;
+            let len = (extra.read_u16::<LittleEndian>()? as usize).min(extra.len());
+            let (mut data, rest) = extra.split_at(len);
+            extra = rest;
+
+            if id != ZIP64_EXTRA_ID {
+                continue;
+            }
+
+            if entry.uncompressed_size == 0xFFFF_FFFF {
+                entry.uncompressed_size = data.read_u64::<LittleEndian>()?;
+            }
+
+            if entry.compressed_size == 0xFFFF_FFFF {
+                entry.compressed_size = data.read_u64::<LittleEndian>()?;
+            }
+
+            if entry.header_offset == 0xFFFF_FFFF {
+                entry.header_offset = data.read_u64::<LittleEndian>()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn entries(&self) -> &[ZipEntry] {
+        &self.entries
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn comment(&self) -> &[u8] {
+        &self.comment
+    }
+
+    /// Number of bytes found before the archive.
+    pub const fn base_offset(&self) -> u64 {
+        self.base_offset
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.entries.iter().position(|e| e.name == name)
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<&ZipEntry> {
+        self.entries.iter().find(|e| e.name == name)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Opens the entry at `index` as a stream of its uncompressed data.
+    ///
+    /// Stored and deflated (with the `zlib` feature) entries are supported. The CRC-32 and
+    /// size are checked when the end of the data is reached.
+    pub fn open_entry(&mut self, index: usize) -> Result<ZipEntryReader<'_, R>> {
+        let entry = self.entries.get(index)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("no entry at index {index}")))?
+            .clone();
+
+        if entry.is_encrypted() {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} is encrypted", entry.name)));
+        }
+
+        let compression = match entry.method {
+            METHOD_STORED => Compression::None,
+            METHOD_DEFLATED => Compression::Deflate,
+            m => return Err(io::Error::new(io::ErrorKind::Unsupported, format!("compression method {m}"))),
+        };
+
+        self.reader.seek(SeekFrom::Start(entry.header_offset))?;
+        if self.reader.read_u32::<LittleEndian>()? != LOCAL_HEADER_SIGNATURE {
+            return Err(invalid_data("bad local header signature"));
+        }
+
+        // sizes and crc may be zero here (data descriptor), only the variable lengths are needed
+        self.reader.seek(SeekFrom::Start(entry.header_offset + 26))?;
+        let name_len = self.reader.read_u16::<LittleEndian>()? as u64;
+        let extra_len = self.reader.read_u16::<LittleEndian>()? as u64;
+
+        let data_offset = entry.header_offset + LOCAL_HEADER_SIZE + name_len + extra_len;
+        let sub = SubReader::new(&mut self.reader, data_offset, entry.compressed_size)?;
+        let decoder = Decoder::new(sub, compression)?;
+
+        Ok(ZipEntryReader {
+            inner: HashingReader::new(decoder, Crc32::new()),
+            entry,
+        })
+    }
+
+    pub fn open_by_name(&mut self, name: &str) -> Result<ZipEntryReader<'_, R>> {
+        let index = self.index_of(name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("{name} not found")))?;
+
+        self.open_entry(index)
+    }
+}
+
+/// Uncompressed data of a [`ZipEntry`], verified against its CRC-32 at the end.
+pub struct ZipEntryReader<'a, R: Read> {
+    inner   : HashingReader<Decoder<SubReader<&'a mut R>>, Crc32>,
+    entry   : ZipEntry,
+}
+
+impl<R: Read> ZipEntryReader<'_, R> {
+    pub const fn entry(&self) -> &ZipEntry {
+        &self.entry
+    }
+}
+
+impl<R: Read> Read for ZipEntryReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+
+        if n == 0 && !buf.is_empty() {
+            if self.inner.pos() != self.entry.uncompressed_size {
+                return Err(invalid_data(format!(
+                    "{}: size mismatch: expected {}, got {}",
+                    self.entry.name, self.entry.uncompressed_size, self.inner.pos(),
+                )));
+            }
+
+            self.inner.verify(self.entry.crc32)?;
+        }
+
+        Ok(n)
+    }
+}
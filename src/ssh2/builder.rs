@@ -0,0 +1,279 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use ssh2::{KeyboardInteractivePrompt, Prompt};
+
+use super::error::{AuthFailure, Error, Result};
+use super::known_hosts::{HostKeyPolicy, Verifier};
+use super::Session;
+
+enum Method {
+    Password(String),
+    KeyFile {
+        private_key : PathBuf,
+        passphrase  : Option<String>,
+    },
+    KeyMemory {
+        private_key : String,
+        passphrase  : Option<String>,
+    },
+    Agent,
+    KeyboardInteractive(Box<dyn KeyboardInteractivePrompt + Send>),
+}
+
+impl Method {
+    /// Name of the method in the protocol, as listed by the server.
+    const fn protocol_name(&self) -> &'static str {
+        match self {
+            Method::Password(_) => "password",
+            Method::KeyFile { .. } | Method::KeyMemory { .. } | Method::Agent => "publickey",
+            Method::KeyboardInteractive(_) => "keyboard-interactive",
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Method::Password(_) => "password".into(),
+            Method::KeyFile { private_key, .. } => format!("key file {}", private_key.display()),
+            Method::KeyMemory { .. } => "in-memory key".into(),
+            Method::Agent => "ssh-agent".into(),
+            Method::KeyboardInteractive(_) => "keyboard-interactive".into(),
+        }
+    }
+}
+
+/// Lets a boxed prompt be passed where libssh2 wants a sized one.
+struct DynPrompt<'a>(&'a mut (dyn KeyboardInteractivePrompt + Send));
+
+impl KeyboardInteractivePrompt for DynPrompt<'_> {
+    fn prompt<'a>(&mut self, username: &str, instructions: &str, prompts: &[Prompt<'a>]) -> Vec<String> {
+        self.0.prompt(username, instructions, prompts)
+    }
+}
+
+/// Answers every keyboard-interactive prompt with the same password.
+struct PasswordPrompt(String);
+
+impl KeyboardInteractivePrompt for PasswordPrompt {
+    fn prompt<'a>(&mut self, _username: &str, _instructions: &str, prompts: &[Prompt<'a>]) -> Vec<String> {
+        prompts.iter().map(|_| self.0.clone()).collect()
+    }
+}
+
+/// Connects and authenticates a [`Session`].
+///
+/// Authentication methods are tried in the order they were added, skipping
+/// those the server does not offer, until one succeeds:
+///
+/// ```ignore
+/// let session = SessionBuilder::new("build-01")
+///     .user("deploy")
+///     .agent()
+///     .key_file("/home/deploy/.ssh/id_ed25519", None)
+///     .password("hunter2")
+///     .connect()?;
+/// ```
+///
+/// If all of them fail, [`Error::Auth`] lists the methods the server offered and why each attempt failed.
+///
+/// Before authenticating, the host key is checked against `~/.ssh/known_hosts`
+/// with [`HostKeyPolicy::Strict`] unless told otherwise. The handshake asks for
+/// the key types recorded there, so a server with several keys presents the known one.
+pub struct SessionBuilder {
+    host        : String,
+    port        : u16,
+    user        : Option<String>,
+    timeout     : Option<Duration>,
+    methods     : Vec<Method>,
+    policy      : HostKeyPolicy,
+    known_hosts : Option<PathBuf>,
+    hash_hosts  : bool,
+}
+
+impl SessionBuilder {
+    pub fn new(host: impl Into<String>) -> Self {
+        Self {
+            host        : host.into(),
+            port        : 22,
+            user        : None,
+            timeout     : None,
+            methods     : Vec::new(),
+            policy      : HostKeyPolicy::Strict,
+            known_hosts : None,
+            hash_hosts  : false,
+        }
+    }
+
+    pub const fn port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// The user to log in as, `$USER` by default.
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    /// Timeout for each blocking libssh2 call, none by default.
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn host_key_policy(mut self, policy: HostKeyPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// The known_hosts file to check and add to, instead of `~/.ssh/known_hosts`.
+    pub fn known_hosts(mut self, path: impl AsRef<Path>) -> Self {
+        self.known_hosts = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Stores hosts added under [`HostKeyPolicy::AcceptNew`] hashed, like `HashKnownHosts yes`.
+    pub const fn hash_known_hosts(mut self, hash: bool) -> Self {
+        self.hash_hosts = hash;
+        self
+    }
+
+    pub fn password(mut self, password: impl Into<String>) -> Self {
+        self.methods.push(Method::Password(password.into()));
+        self
+    }
+
+    /// A private key file; the public key is derived from it.
+    pub fn key_file(mut self, private_key: impl AsRef<Path>, passphrase: Option<&str>) -> Self {
+        self.methods.push(Method::KeyFile {
+            private_key : private_key.as_ref().to_path_buf(),
+            passphrase  : passphrase.map(str::to_string),
+        });
+        self
+    }
+
+    /// A private key in PEM or OpenSSH format.
+    pub fn key_memory(mut self, private_key: impl Into<String>, passphrase: Option<&str>) -> Self {
+        self.methods.push(Method::KeyMemory {
+            private_key : private_key.into(),
+            passphrase  : passphrase.map(str::to_string),
+        });
+        self
+    }
+
+    /// Every identity held by the running ssh-agent, in turn.
+    pub fn agent(mut self) -> Self {
+        self.methods.push(Method::Agent);
+        self
+    }
+
+    /// Keyboard-interactive authentication answering every prompt with `password`.
+    pub fn keyboard_interactive(mut self, password: impl Into<String>) -> Self {
+        self.methods.push(Method::KeyboardInteractive(Box::new(PasswordPrompt(password.into()))));
+        self
+    }
+
+    /// Keyboard-interactive authentication answered by `prompt`, for one-time codes and the like.
+    pub fn keyboard_interactive_with(mut self, prompt: impl KeyboardInteractivePrompt + Send + 'static) -> Self {
+        self.methods.push(Method::KeyboardInteractive(Box::new(prompt)));
+        self
+    }
+
+    pub fn connect(self) -> Result<Session> {
+        let user = match self.user {
+            Some(user) => user,
+            None => std::env::var("USER").or_else(|_| std::env::var("USERNAME")).unwrap_or_else(|_| "root".into()),
+        };
+
+        let mut session = Session::new()?;
+        if let Some(timeout) = self.timeout {
+            session.set_timeout(timeout.as_millis().try_into().unwrap_or(u32::MAX));
+        }
+
+        let verifier = Verifier::new(&self.policy, self.known_hosts.as_deref(), self.hash_hosts)?;
+
+        session.connect_tcp((self.host.as_str(), self.port))?;
+        session.host = format!("{}:{}", self.host, self.port);
+
+        verifier.prefer_known_types(&session, &self.host, self.port)?;
+        session.handshake_transport()?;
+        verifier.verify(&session, &self.host, self.port)?;
+
+        authenticate(&session, &user, self.methods)?;
+
+        tracing::debug!(host = %session.host, user, "authenticated");
+        Ok(session)
+    }
+}
+
+fn authenticate(session: &Session, user: &str, methods: Vec<Method>) -> Result<()> {
+    // asking for the list tries the "none" method, which some servers accept
+    let offered: Vec<String> = match session.auth_methods(user) {
+        Ok(list) => list.split(',').map(str::to_string).collect(),
+        Err(_) if session.authenticated() => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut failures = Vec::new();
+
+    for mut method in methods {
+        let description = method.describe();
+
+        if !offered.iter().any(|m| m == method.protocol_name()) {
+            failures.push(AuthFailure { method: description, reason: format!("server does not offer {}", method.protocol_name()) });
+            continue;
+        }
+
+        match try_method(session, user, &mut method) {
+            Ok(()) if session.authenticated() => {
+                tracing::debug!(user, method = %description, "authentication succeeded");
+                return Ok(());
+            },
+            Ok(()) => failures.push(AuthFailure { method: description, reason: "partial success, more methods required".into() }),
+            Err(reason) => {
+                tracing::debug!(user, method = %description, %reason, "authentication failed");
+                failures.push(AuthFailure { method: description, reason });
+            },
+        }
+    }
+
+    Err(Error::Auth {
+        user    : user.to_string(),
+        offered,
+        failures,
+    })
+}
+
+fn try_method(session: &Session, user: &str, method: &mut Method) -> std::result::Result<(), String> {
+    let result = match method {
+        Method::Password(password) => session.userauth_password(user, password),
+        Method::KeyFile { private_key, passphrase } => session.userauth_pubkey_file(user, None, private_key, passphrase.as_deref()),
+        #[cfg(unix)]
+        Method::KeyMemory { private_key, passphrase } => session.userauth_pubkey_memory(user, None, private_key, passphrase.as_deref()),
+        #[cfg(not(unix))]
+        Method::KeyMemory { .. } => return Err("in-memory keys need libssh2 built with OpenSSL".into()),
+        Method::KeyboardInteractive(prompt) => session.userauth_keyboard_interactive(user, &mut DynPrompt(prompt.as_mut())),
+        Method::Agent => return try_agent(session, user),
+    };
+
+    result.map_err(|e| e.message().to_string())
+}
+
+fn try_agent(session: &Session, user: &str) -> std::result::Result<(), String> {
+    let mut agent = session.agent().map_err(|e| e.message().to_string())?;
+    agent.connect().map_err(|e| format!("cannot reach the agent: {}", e.message()))?;
+    agent.list_identities().map_err(|e| e.message().to_string())?;
+
+    let identities = agent.identities().map_err(|e| e.message().to_string())?;
+    if identities.is_empty() {
+        return Err("the agent holds no identities".into());
+    }
+
+    for identity in &identities {
+        if agent.userauth(user, identity).is_ok() {
+            return Ok(());
+        }
+    }
+
+    Err(format!("all {} agent identities were rejected", identities.len()))
+}
@@ -0,0 +1,124 @@
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+use super::exec::ExecOutput;
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("cannot reach {host}")]
+    Transport {
+        host    : String,
+        #[source]
+        source  : io::Error,
+    },
+
+    #[error("{}", auth_failure(.user, .offered, .failures))]
+    Auth {
+        user        : String,
+
+        /// Methods the server offered, such as `publickey` and `password`.
+        offered     : Vec<String>,
+        failures    : Vec<AuthFailure>,
+    },
+
+    #[error("host {host} is not in known_hosts, it offered key {offered}")]
+    UnknownHost {
+        host        : String,
+        offered     : String,
+    },
+
+    #[error("host key for {host} has changed: offered {offered}, expected {}", .expected.join(" or "))]
+    HostKeyMismatch {
+        host        : String,
+        offered     : String,
+        expected    : Vec<String>,
+    },
+
+    #[error("host key {offered} for {host} is marked @revoked in known_hosts")]
+    HostKeyRevoked {
+        host        : String,
+        offered     : String,
+    },
+
+    #[error("cannot access known_hosts file {path}")]
+    KnownHosts {
+        path        : PathBuf,
+        #[source]
+        source      : io::Error,
+    },
+
+    #[error("channel error")]
+    Channel(#[source] io::Error),
+
+    #[error("{}", command_failure(.0))]
+    Command(Box<ExecOutput>),
+
+    #[error("ssh error")]
+    Ssh(#[from] ssh2::Error),
+
+    #[error("io error")]
+    Io(#[from] io::Error),
+}
+
+/// Why one authentication attempt failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthFailure {
+    /// The method tried, such as `ssh-agent` or `key file /home/me/.ssh/id_ed25519`.
+    pub method  : String,
+    pub reason  : String,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// The output of the command, for [`Error::Command`].
+    pub fn output(&self) -> Option<&ExecOutput> {
+        match self {
+            Error::Command(output) => Some(output),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        let kind = match e {
+            Error::Io(e) => return e,
+            Error::Transport { ref source, .. } | Error::Channel(ref source) => source.kind(),
+            _ => io::ErrorKind::Other,
+        };
+
+        io::Error::new(kind, e)
+    }
+}
+
+fn command_failure(output: &ExecOutput) -> String {
+    let how = match &output.exit_signal {
+        Some(signal) => format!("was killed by SIG{signal}"),
+        None => format!("exited with status {}", output.exit_status),
+    };
+
+    let stderr = output.stderr_str();
+    let stderr = stderr.trim_end();
+
+    if stderr.is_empty() {
+        format!("command {:?} {how}", output.command)
+    } else {
+        format!("command {:?} {how}: {stderr}", output.command)
+    }
+}
+
+fn auth_failure(user: &str, offered: &[String], failures: &[AuthFailure]) -> String {
+    let mut s = format!("authentication as {user} failed, the server offers {}", offered.join(", "));
+
+    if failures.is_empty() {
+        s.push_str("; no authentication method was configured");
+    }
+
+    for failure in failures {
+        s.push_str(&format!("; {}: {}", failure.method, failure.reason));
+    }
+
+    s
+}
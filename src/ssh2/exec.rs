@@ -0,0 +1,137 @@
+use std::borrow::Cow;
+use std::io::{self, Read};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tracing::field;
+
+use super::error::{Error, Result};
+
+// how long to wait when neither stream has data, since the session cannot block on both
+const POLL_INTERVAL: Duration = Duration::from_millis(2);
+const READ_CHUNK: usize = 0x4000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+/// Callback receiving command output as it arrives, see [`Session::set_observer`](super::Session::set_observer).
+pub type Observer = dyn Fn(OutputStream, &[u8]) + Send + Sync;
+
+/// What a remote command produced, with stdout and stderr kept apart.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExecOutput {
+    pub command     : String,
+    pub stdout      : Vec<u8>,
+    pub stderr      : Vec<u8>,
+    pub exit_status : i32,
+
+    /// Signal that terminated the command, without the `SIG` prefix, such as `KILL`.
+    pub exit_signal : Option<String>,
+    pub duration    : Duration,
+}
+
+impl ExecOutput {
+    /// `true` if the command exited with status 0 and was not killed by a signal.
+    pub fn success(&self) -> bool {
+        self.exit_status == 0 && self.exit_signal.is_none()
+    }
+
+    /// Turns a failed command into [`Error::Command`], with its stderr in the message.
+    pub fn check(self) -> Result<Self> {
+        if self.success() {
+            Ok(self)
+        } else {
+            Err(Error::Command(Box::new(self)))
+        }
+    }
+
+    pub fn stdout_str(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.stdout)
+    }
+
+    pub fn stderr_str(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.stderr)
+    }
+}
+
+/// Runs `cmd` on a new channel and collects both streams until the command exits.
+pub(crate) fn run(session: &ssh2::Session, host: &str, cmd: &str, observer: Option<&Observer>) -> io::Result<ExecOutput> {
+    let span = tracing::debug_span!("exec", host, command = cmd, exit_status = field::Empty, duration_ms = field::Empty);
+    let _entered = span.enter();
+
+    tracing::debug!("started");
+    let start = Instant::now();
+
+    let mut channel = session.channel_session()?;
+    channel.exec(cmd)?;
+
+    let mut stdout = Vec::new();
+    let mut stderr = Vec::new();
+
+    // reading one stream to the end first deadlocks once the other fills the channel window
+    let blocking = session.is_blocking();
+    session.set_blocking(false);
+    let drained = drain(&mut channel, &mut stdout, &mut stderr, observer);
+    session.set_blocking(blocking);
+
+    if let Err(e) = &drained {
+        tracing::warn!(error = %e, "reading output failed");
+    }
+    drained?;
+
+    channel.wait_close()?;
+
+    let output = ExecOutput {
+        command     : cmd.to_string(),
+        stdout,
+        stderr,
+        exit_status : channel.exit_status()?,
+        exit_signal : channel.exit_signal()?.exit_signal,
+        duration    : start.elapsed(),
+    };
+
+    span.record("exit_status", output.exit_status);
+    span.record("duration_ms", output.duration.as_millis() as u64);
+    tracing::debug!(exit_signal = output.exit_signal.as_deref(), stdout_len = output.stdout.len(), stderr_len = output.stderr.len(), "finished");
+
+    Ok(output)
+}
+
+fn drain(channel: &mut ssh2::Channel, stdout: &mut Vec<u8>, stderr: &mut Vec<u8>, observer: Option<&Observer>) -> io::Result<()> {
+    let mut buf = vec![0u8; READ_CHUNK];
+
+    loop {
+        let mut progress = false;
+
+        for (id, kind, sink) in [(0, OutputStream::Stdout, &mut *stdout), (ssh2::EXTENDED_DATA_STDERR, OutputStream::Stderr, &mut *stderr)] {
+            match channel.stream(id).read(&mut buf) {
+                Ok(0) => {},
+                Ok(n) => {
+                    let chunk = &buf[..n];
+                    tracing::trace!(stream = ?kind, data = %String::from_utf8_lossy(chunk), "output");
+
+                    if let Some(observer) = observer {
+                        observer(kind, chunk);
+                    }
+
+                    sink.extend_from_slice(chunk);
+                    progress = true;
+                },
+                Err(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted) => {},
+                Err(e) => return Err(e),
+            }
+        }
+
+        // eof() is only true once nothing is left queued for the channel
+        if !progress {
+            if channel.eof() {
+                return Ok(());
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
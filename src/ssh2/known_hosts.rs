@@ -0,0 +1,540 @@
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD};
+use base64::Engine as _;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+use super::error::{Error, Result};
+
+// prefix of a host name hashed with HMAC-SHA1, followed by the salt and the hash
+const HASH_MAGIC: &str = "|1|";
+const SALT_LEN: usize = 20;
+
+/// How [`SessionBuilder`](super::SessionBuilder) decides whether to trust the key the server presents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostKeyPolicy {
+    /// The host must be in known_hosts with the same key.
+    Strict,
+
+    /// Unknown hosts are trusted on first use and added to known_hosts; changed keys are refused.
+    AcceptNew,
+
+    /// Only the key with this SHA-256 fingerprint is accepted, as printed by `ssh-keygen -lf`.
+    Pinned(String),
+
+    /// Any key is accepted, for throwaway test machines only.
+    Insecure,
+}
+
+/// The OpenSSH fingerprint of a public key blob, such as `SHA256:uNiVztksCsDhcc0u9e8BujQXVUpKZIDTMczCvj3tD2s`.
+pub fn fingerprint(key: &[u8]) -> String {
+    format!("SHA256:{}", STANDARD_NO_PAD.encode(Sha256::digest(key)))
+}
+
+/// The algorithm name a public key blob starts with, such as `ssh-ed25519`.
+pub fn key_type(key: &[u8]) -> Option<&str> {
+    let len = u32::from_be_bytes(key.get(..4)?.try_into().ok()?) as usize;
+    std::str::from_utf8(key.get(4..4 + len)?).ok()
+}
+
+/// Fingerprints compare equal with or without the `SHA256:` prefix and base64 padding.
+fn same_fingerprint(a: &str, b: &str) -> bool {
+    let normalize = |f: &str| f.trim().strip_prefix("SHA256:").unwrap_or(f.trim()).trim_end_matches('=').to_string();
+    normalize(a) == normalize(b)
+}
+
+/// The name a host is recorded under: `host` on port 22, `[host]:port` elsewhere.
+fn host_name(host: &str, port: u16) -> String {
+    let host = host.to_ascii_lowercase();
+    if port == 22 { host } else { format!("[{host}]:{port}") }
+}
+
+fn hash_host(name: &str, salt: &[u8]) -> Option<Vec<u8>> {
+    let mut mac = Hmac::<Sha1>::new_from_slice(salt).ok()?;
+    mac.update(name.as_bytes());
+    Some(mac.finalize().into_bytes().to_vec())
+}
+
+/// Glob matching with `*` and `?`, as in known_hosts patterns.
+fn glob_match(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => glob_match(&pattern[1..], name) || (!name.is_empty() && glob_match(pattern, &name[1..])),
+        (Some(b'?'), Some(_)) => glob_match(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p.eq_ignore_ascii_case(n) => glob_match(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Marker {
+    CertAuthority,
+    Revoked,
+}
+
+/// One host key line of a known_hosts file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub marker      : Option<Marker>,
+
+    /// Comma-separated host patterns, or a hashed name `|1|salt|hash`.
+    pub hosts       : String,
+    pub key_type    : String,
+    pub key         : Vec<u8>,
+    pub comment     : Option<String>,
+}
+
+impl Entry {
+    /// Parses a line, returning `None` for comments, blank lines and lines OpenSSH would ignore.
+    pub fn parse(line: &str) -> Option<Entry> {
+        let mut fields = line.split_whitespace();
+        let mut first = fields.next()?;
+
+        let marker = match first {
+            "@cert-authority" => Some(Marker::CertAuthority),
+            "@revoked" => Some(Marker::Revoked),
+            _ if first.starts_with('#') || first.starts_with('@') => return None,
+            _ => None,
+        };
+
+        if marker.is_some() {
+            first = fields.next()?;
+        }
+
+        let key_type = fields.next()?;
+        let key = STANDARD.decode(fields.next()?).ok()?;
+        let comment = fields.collect::<Vec<_>>().join(" ");
+
+        Some(Entry {
+            marker,
+            hosts       : first.to_string(),
+            key_type    : key_type.to_string(),
+            key,
+            comment     : (!comment.is_empty()).then_some(comment),
+        })
+    }
+
+    pub fn is_hashed(&self) -> bool {
+        self.hosts.starts_with(HASH_MAGIC)
+    }
+
+    /// `true` if the entry is for `host` on `port`.
+    pub fn matches(&self, host: &str, port: u16) -> bool {
+        let name = host_name(host, port);
+
+        if let Some(hashed) = self.hosts.strip_prefix(HASH_MAGIC) {
+            let Some((salt, hash)) = hashed.split_once('|') else {
+                return false;
+            };
+
+            return match (STANDARD.decode(salt), STANDARD.decode(hash)) {
+                (Ok(salt), Ok(hash)) => hash_host(&name, &salt) == Some(hash),
+                _ => false,
+            };
+        }
+
+        let mut matched = false;
+
+        for pattern in self.hosts.split(',') {
+            match pattern.strip_prefix('!') {
+                // a negated pattern excludes the host whatever else matches
+                Some(negated) if glob_match(negated.as_bytes(), name.as_bytes()) => return false,
+                Some(_) => {},
+                None => matched |= glob_match(pattern.as_bytes(), name.as_bytes()),
+            }
+        }
+
+        matched
+    }
+
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&self.key)
+    }
+}
+
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.marker {
+            Some(Marker::CertAuthority) => write!(f, "@cert-authority ")?,
+            Some(Marker::Revoked) => write!(f, "@revoked ")?,
+            None => {},
+        }
+
+        write!(f, "{} {} {}", self.hosts, self.key_type, STANDARD.encode(&self.key))?;
+
+        if let Some(comment) = &self.comment {
+            write!(f, " {comment}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// What known_hosts says about a host key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HostKeyCheck {
+    Match,
+
+    /// The host is known with other keys, whose fingerprints are listed.
+    Mismatch(Vec<String>),
+    NotFound,
+    Revoked,
+}
+
+#[derive(Debug, Clone)]
+enum Line {
+    Entry(Entry),
+
+    // comments, blank lines and anything unparsed, kept so saving does not lose them
+    Other(String),
+}
+
+/// An OpenSSH known_hosts file, with plain and hashed host names.
+#[derive(Debug, Clone, Default)]
+pub struct KnownHosts {
+    lines   : Vec<Line>,
+}
+
+impl KnownHosts {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `~/.ssh/known_hosts`.
+    pub fn default_path() -> Option<PathBuf> {
+        let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+        Some(Path::new(&home).join(".ssh").join("known_hosts"))
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let lines = text
+            .lines()
+            .map(|line| match Entry::parse(line) {
+                Some(entry) => Line::Entry(entry),
+                None => Line::Other(line.to_string()),
+            })
+            .collect();
+
+        Self { lines }
+    }
+
+    /// Reads a known_hosts file; a missing file is empty.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        match fs::read_to_string(path.as_ref()) {
+            Ok(text) => Ok(Self::parse(&text)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::new()),
+            Err(source) => Err(Error::KnownHosts { path: path.as_ref().to_path_buf(), source }),
+        }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Entry(entry) => Some(entry),
+            Line::Other(_) => None,
+        })
+    }
+
+    /// Checks the public key blob `key` offered by `host` on `port`.
+    ///
+    /// Keys of a type not recorded for a known host count as a mismatch, so an
+    /// attacker cannot get around a recorded key by offering another algorithm.
+    pub fn check(&self, host: &str, port: u16, key: &[u8]) -> HostKeyCheck {
+        let mut expected = Vec::new();
+
+        for entry in self.entries().filter(|e| e.matches(host, port)) {
+            match entry.marker {
+                Some(Marker::Revoked) if entry.key == key => return HostKeyCheck::Revoked,
+                Some(_) => {},
+                None if entry.key == key => return HostKeyCheck::Match,
+                None => expected.push(entry.fingerprint()),
+            }
+        }
+
+        if expected.is_empty() { HostKeyCheck::NotFound } else { HostKeyCheck::Mismatch(expected) }
+    }
+
+    /// Key types recorded for `host` on `port`, in file order, such as `ssh-ed25519`.
+    pub fn key_types(&self, host: &str, port: u16) -> Vec<&str> {
+        let mut types = Vec::new();
+
+        for entry in self.entries().filter(|e| e.marker.is_none() && e.matches(host, port)) {
+            if !types.contains(&entry.key_type.as_str()) {
+                types.push(entry.key_type.as_str());
+            }
+        }
+
+        types
+    }
+
+    /// Adds `key` for `host` on `port`, storing the name hashed if `hash` is set.
+    pub fn add(&mut self, host: &str, port: u16, key: &[u8], hash: bool) -> Result<Entry> {
+        let key_type = key_type(key).ok_or_else(|| Error::Io(io::Error::new(io::ErrorKind::InvalidData, "malformed host key")))?;
+        let name = host_name(host, port);
+
+        let hosts = if hash {
+            let mut salt = [0u8; SALT_LEN];
+            getrandom::fill(&mut salt).map_err(|e| Error::Io(io::Error::other(e.to_string())))?;
+            let hashed = hash_host(&name, &salt).ok_or_else(|| Error::Io(io::Error::other("cannot hash the host name")))?;
+            format!("{HASH_MAGIC}{}|{}", STANDARD.encode(salt), STANDARD.encode(hashed))
+        } else {
+            name
+        };
+
+        let entry = Entry {
+            marker      : None,
+            hosts,
+            key_type    : key_type.to_string(),
+            key         : key.to_vec(),
+            comment     : None,
+        };
+
+        self.lines.push(Line::Entry(entry.clone()));
+        Ok(entry)
+    }
+
+    /// Writes the whole file, keeping comments and lines that were not understood.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let io_error = |source| Error::KnownHosts { path: path.to_path_buf(), source };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(io_error)?;
+        }
+
+        fs::write(path, self.to_string()).map_err(io_error)
+    }
+
+    /// Appends one entry to the file at `path` without rewriting it, as `ssh` does.
+    pub fn append<P: AsRef<Path>>(path: P, entry: &Entry) -> Result<()> {
+        let path = path.as_ref();
+        let io_error = |source| Error::KnownHosts { path: path.to_path_buf(), source };
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(io_error)?;
+        }
+
+        let mut file = OpenOptions::new().read(true).append(true).create(true).open(path).map_err(io_error)?;
+
+        // do not glue the entry to a last line without a newline
+        let mut last = [0u8; 1];
+        let len = file.seek(SeekFrom::End(0)).map_err(io_error)?;
+        if len > 0 {
+            file.seek(SeekFrom::End(-1)).map_err(io_error)?;
+            file.read_exact(&mut last).map_err(io_error)?;
+        }
+
+        let separator = if len > 0 && last[0] != b'\n' { "\n" } else { "" };
+        writeln!(file, "{separator}{entry}").map_err(io_error)
+    }
+}
+
+impl fmt::Display for KnownHosts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            match line {
+                Line::Entry(entry) => writeln!(f, "{entry}")?,
+                Line::Other(text) => writeln!(f, "{text}")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Host key checking for one connection, set up before the handshake.
+pub(crate) struct Verifier<'a> {
+    policy      : &'a HostKeyPolicy,
+    path        : Option<PathBuf>,
+    known_hosts : KnownHosts,
+    hash        : bool,
+}
+
+impl<'a> Verifier<'a> {
+    /// Loads known_hosts from `path`, or `~/.ssh/known_hosts`, if `policy` needs it.
+    pub(crate) fn new(policy: &'a HostKeyPolicy, path: Option<&Path>, hash: bool) -> Result<Self> {
+        let path = match policy {
+            HostKeyPolicy::Strict | HostKeyPolicy::AcceptNew => path.map(Path::to_path_buf).or_else(KnownHosts::default_path),
+            HostKeyPolicy::Pinned(_) | HostKeyPolicy::Insecure => None,
+        };
+
+        let known_hosts = match &path {
+            Some(path) => KnownHosts::open(path)?,
+            None => KnownHosts::new(),
+        };
+
+        Ok(Self { policy, path, known_hosts, hash })
+    }
+
+    /// Asks the server for a key of a type recorded for `host`, so a server with
+    /// several keys is not refused for offering one that was never recorded.
+    ///
+    /// Must be called before the handshake.
+    pub(crate) fn prefer_known_types(&self, session: &ssh2::Session, host: &str, port: u16) -> Result<()> {
+        let supported = session.supported_algs(ssh2::MethodType::HostKey)?;
+
+        let mut algorithms: Vec<&str> = Vec::new();
+        for key_type in self.known_hosts.key_types(host, port) {
+            // RSA keys are signed with SHA-2 by current servers but recorded as ssh-rsa
+            let candidates: &[&str] = match key_type {
+                "ssh-rsa" => &["rsa-sha2-512", "rsa-sha2-256", "ssh-rsa"],
+                other => &[other],
+            };
+
+            for &algorithm in candidates {
+                if supported.contains(&algorithm) && !algorithms.contains(&algorithm) {
+                    algorithms.push(algorithm);
+                }
+            }
+        }
+
+        if !algorithms.is_empty() {
+            tracing::debug!(host, algorithms = %algorithms.join(","), "preferring known host key types");
+            session.method_pref(ssh2::MethodType::HostKey, &algorithms.join(","))?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks the key the server presented during the handshake against the policy.
+    pub(crate) fn verify(mut self, session: &ssh2::Session, host: &str, port: u16) -> Result<()> {
+        let (key, _) = session.host_key().ok_or_else(|| Error::Io(io::Error::new(io::ErrorKind::InvalidData, "the server sent no host key")))?;
+        let offered = fingerprint(key);
+        let name = host_name(host, port);
+
+        match self.policy {
+            HostKeyPolicy::Insecure => {
+                tracing::warn!(host = %name, fingerprint = %offered, "host key not verified");
+                Ok(())
+            },
+
+            HostKeyPolicy::Pinned(expected) if same_fingerprint(expected, &offered) => Ok(()),
+            HostKeyPolicy::Pinned(expected) => Err(Error::HostKeyMismatch {
+                host        : name,
+                offered,
+                expected    : vec![expected.clone()],
+            }),
+
+            HostKeyPolicy::Strict | HostKeyPolicy::AcceptNew => match self.known_hosts.check(host, port, key) {
+                HostKeyCheck::Match => Ok(()),
+                HostKeyCheck::Revoked => Err(Error::HostKeyRevoked { host: name, offered }),
+                HostKeyCheck::Mismatch(expected) => Err(Error::HostKeyMismatch { host: name, offered, expected }),
+                HostKeyCheck::NotFound if *self.policy == HostKeyPolicy::Strict => Err(Error::UnknownHost { host: name, offered }),
+
+                HostKeyCheck::NotFound => {
+                    let entry = self.known_hosts.add(host, port, key, self.hash)?;
+                    if let Some(path) = &self.path {
+                        KnownHosts::append(path, &entry)?;
+                    }
+
+                    tracing::info!(host = %name, fingerprint = %offered, "added host key to known_hosts");
+                    Ok(())
+                },
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ed25519 keys from `ssh-keygen -t ed25519`, fingerprints from `ssh-keygen -lf`
+    const KEY_A: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIEfVmEwOZnHwkebvl9fkVU752QTxjIs8NUANw9Uwd0au";
+    const KEY_B: &str = "AAAAC3NzaC1lZDI1NTE5AAAAICr8HufnatukvY5l+sCqmd5N7Ej1k8fbWEEqoLhlhwdh";
+    const FINGERPRINT_A: &str = "SHA256:fnhcM4ck5Q+fQS+weSgvqZSZ8Psuip5TS62ELo3y9L4";
+
+    // build-01.example.com and [build-01.example.com]:2222 with KEY_A, hashed by `ssh-keygen -H`
+    const HASHED: &str = "|1|+lScWTlrbddxerVKY57DAV51Y3M=|9iNqkK9jIAV/K9FfsPdXpvMCiEA=";
+    const HASHED_2222: &str = "|1|Q/HqbDwI75ZR4WszdcScSYIg8f0=|s2J+onCXfmc3ig/YMGm4bCIFEFQ=";
+
+    fn key(base64: &str) -> Vec<u8> {
+        STANDARD.decode(base64).unwrap()
+    }
+
+    #[test]
+    fn parses_entries() {
+        let entry = Entry::parse(&format!("@revoked web-*,10.0.0.1 ssh-ed25519 {KEY_A} old  key")).unwrap();
+        assert_eq!(entry.marker, Some(Marker::Revoked));
+        assert_eq!(entry.hosts, "web-*,10.0.0.1");
+        assert_eq!(entry.key_type, "ssh-ed25519");
+        assert_eq!(entry.key, key(KEY_A));
+        assert_eq!(entry.comment.as_deref(), Some("old key"));
+        assert_eq!(entry.fingerprint(), FINGERPRINT_A);
+        assert_eq!(key_type(&entry.key), Some("ssh-ed25519"));
+
+        assert!(Entry::parse("# comment").is_none());
+        assert!(Entry::parse("").is_none());
+        assert!(Entry::parse(&format!("@unknown host ssh-ed25519 {KEY_A}")).is_none());
+        assert!(Entry::parse("host ssh-ed25519 not-base64!").is_none());
+        assert!(Entry::parse("host ssh-ed25519").is_none());
+    }
+
+    #[test]
+    fn matches_hashed_names() {
+        let entry = Entry::parse(&format!("{HASHED} ssh-ed25519 {KEY_A}")).unwrap();
+        assert!(entry.is_hashed());
+        assert!(entry.matches("build-01.example.com", 22));
+        assert!(entry.matches("BUILD-01.example.com", 22));
+        assert!(!entry.matches("build-02.example.com", 22));
+        assert!(!entry.matches("build-01.example.com", 2222));
+
+        let entry = Entry::parse(&format!("{HASHED_2222} ssh-ed25519 {KEY_A}")).unwrap();
+        assert!(entry.matches("build-01.example.com", 2222));
+        assert!(!entry.matches("build-01.example.com", 22));
+    }
+
+    #[test]
+    fn matches_patterns_and_ports() {
+        let entry = Entry::parse(&format!("web-*.example.com,!web-db.example.com,[bastion]:2222 ssh-ed25519 {KEY_A}")).unwrap();
+        assert!(entry.matches("web-01.example.com", 22));
+        assert!(!entry.matches("web-db.example.com", 22));
+        assert!(!entry.matches("web-01.example.com", 2222));
+        assert!(entry.matches("bastion", 2222));
+        assert!(!entry.matches("bastion", 22));
+
+        // a negated pattern alone never matches
+        assert!(!Entry::parse(&format!("!web-db ssh-ed25519 {KEY_A}")).unwrap().matches("web-01", 22));
+    }
+
+    #[test]
+    fn checks_keys() {
+        let known_hosts = KnownHosts::parse(&format!(
+            "# deployment hosts\n\
+             {HASHED} ssh-ed25519 {KEY_A}\n\
+             @revoked * ssh-ed25519 {KEY_B}\n\
+             @cert-authority *.example.com ssh-ed25519 {KEY_B}\n"
+        ));
+
+        let (a, b) = (key(KEY_A), key(KEY_B));
+        assert_eq!(known_hosts.check("build-01.example.com", 22, &a), HostKeyCheck::Match);
+        assert_eq!(known_hosts.check("build-01.example.com", 22, &b), HostKeyCheck::Revoked);
+        assert_eq!(known_hosts.check("build-02.example.com", 22, &a), HostKeyCheck::NotFound);
+        assert_eq!(known_hosts.key_types("build-01.example.com", 22), ["ssh-ed25519"]);
+
+        let known_hosts = KnownHosts::parse(&format!("{HASHED} ssh-ed25519 {KEY_B}"));
+        assert_eq!(known_hosts.check("build-01.example.com", 22, &a), HostKeyCheck::Mismatch(vec![fingerprint(&b)]));
+    }
+
+    #[test]
+    fn round_trips_added_entries() {
+        let mut known_hosts = KnownHosts::parse("# kept\n");
+        let entry = known_hosts.add("build-01.example.com", 2222, &key(KEY_A), true).unwrap();
+        assert!(entry.is_hashed());
+
+        let reparsed = KnownHosts::parse(&known_hosts.to_string());
+        assert!(reparsed.to_string().starts_with("# kept\n"));
+        assert_eq!(reparsed.check("build-01.example.com", 2222, &key(KEY_A)), HostKeyCheck::Match);
+        assert_eq!(reparsed.check("build-01.example.com", 22, &key(KEY_A)), HostKeyCheck::NotFound);
+    }
+
+    #[test]
+    fn compares_fingerprints_loosely() {
+        assert!(same_fingerprint(FINGERPRINT_A, "fnhcM4ck5Q+fQS+weSgvqZSZ8Psuip5TS62ELo3y9L4="));
+        assert!(!same_fingerprint(FINGERPRINT_A, &fingerprint(&key(KEY_B))));
+    }
+}
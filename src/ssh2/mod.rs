@@ -1,71 +1,152 @@
 use ssh2;
 use std::net;
-use std::io::{self, Read, Write};
-
-pub type Error = ssh2::Error;
-pub type Result<T> = std::result::Result<T, ssh2::Error>;
-
-pub struct Session(ssh2::Session);
+use std::io::{Read, Write};
+use std::time::Instant;
+
+mod builder;
+mod error;
+mod exec;
+mod known_hosts;
+
+pub use builder::SessionBuilder;
+pub use error::{AuthFailure, Error, Result};
+pub use exec::{ExecOutput, Observer, OutputStream};
+pub use known_hosts::{fingerprint, key_type, Entry, HostKeyCheck, HostKeyPolicy, KnownHosts, Marker};
+
+/// An SSH session that runs commands quietly.
+///
+/// Diagnostics go through `tracing`: every command gets an `exec` span with the
+/// host, command, exit status and duration, and output chunks are logged at
+/// `TRACE`. Nothing is printed unless a subscriber is installed, and
+/// [`Session::set_observer`] receives the output as it arrives.
+pub struct Session {
+    inner       : ssh2::Session,
+    host        : String,
+    observer    : Option<Box<Observer>>,
+}
 
 impl std::ops::Deref for Session {
     type Target = ssh2::Session;
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.inner
     }
 }
 
 impl std::ops::DerefMut for Session {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
+        &mut self.inner
     }
 }
 
 impl Session {
     pub fn new() -> Result<Session> {
         let s = ssh2::Session::new()?;
-        Ok(Session(s))
+        Ok(Session {
+            inner       : s,
+            host        : String::new(),
+            observer    : None,
+        })
     }
 
-    pub fn connect<A: net::ToSocketAddrs>(&mut self, addr: A) -> io::Result<()> {
-        self.set_tcp_stream(std::net::TcpStream::connect(addr)?);
-        self.handshake()?;
-        Ok(())
+    /// Connects and does the SSH handshake **without checking the host key**.
+    ///
+    /// # Security
+    ///
+    /// Anyone who can intercept the connection can impersonate the server and
+    /// read everything sent over it, passwords included. [`SessionBuilder`]
+    /// checks the host key against known_hosts or a pinned fingerprint as set by
+    /// [`HostKeyPolicy`]; to skip the check on a trusted network, say so with
+    /// [`HostKeyPolicy::Insecure`].
+    #[deprecated(note = "does not check the host key; use SessionBuilder, which verifies it against known_hosts")]
+    pub fn connect<A: net::ToSocketAddrs>(&mut self, addr: A) -> Result<()> {
+        self.connect_tcp(addr)?;
+        tracing::warn!(host = %self.host, "host key not verified, use SessionBuilder to check it");
+        self.handshake_transport()
     }
 
-    pub fn exec(&self, cmd: &str) -> io::Result<String> {
-        let mut channel = self.channel_session()?;
+    pub(crate) fn connect_tcp<A: net::ToSocketAddrs>(&mut self, addr: A) -> Result<()> {
+        let addrs: Vec<net::SocketAddr> = addr.to_socket_addrs().map_err(|source| Error::Transport { host: "unresolved address".into(), source })?.collect();
+        let host = addrs.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ");
 
-        println!("------------- exec start -------------");
-        println!("{cmd}");
+        let stream = net::TcpStream::connect(&addrs[..]).map_err(|source| Error::Transport { host: host.clone(), source })?;
+        self.host = stream.peer_addr().map_or(host, |a| a.to_string());
 
-        channel.exec(cmd)?;
+        tracing::debug!(host = %self.host, "connected");
 
-        let mut s = String::new();
-        let mut stderr = channel.stderr();
+        self.set_tcp_stream(stream);
+        Ok(())
+    }
 
-        stderr.read_to_string(&mut s)?;
-        println!("{}", s);
+    pub(crate) fn handshake_transport(&mut self) -> Result<()> {
+        self.handshake().map_err(|e| Error::Transport { host: self.host.clone(), source: e.into() })
+    }
 
-        channel.read_to_string(&mut s)?;
-        println!("{}", s);
+    /// The peer address, empty until connected.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
 
-        _ = channel.wait_close();
+    /// Calls `observer` with each chunk of command output as it is read.
+    pub fn set_observer(&mut self, observer: impl Fn(OutputStream, &[u8]) + Send + Sync + 'static) {
+        self.observer = Some(Box::new(observer));
+    }
 
-        println!("exit status: {}", channel.exit_status().unwrap());
+    pub fn clear_observer(&mut self) {
+        self.observer = None;
+    }
+
+    /// Runs `cmd` and returns its stderr followed by its stdout.
+    ///
+    /// A command that fails is still `Ok`; use [`Session::exec_checked`] to treat it as an error.
+    pub fn exec(&self, cmd: &str) -> Result<String> {
+        let output = self.exec_output(cmd)?;
 
-        println!("------------- exec end -------------\n");
+        let mut s = output.stderr_str().into_owned();
+        s.push_str(&output.stdout_str());
+        Ok(s)
+    }
 
-        Ok(s.to_string())
+    /// Runs `cmd` and returns its stdout and stderr separately, with how it exited.
+    ///
+    /// A command that fails is still `Ok`; use [`ExecOutput::check`] or [`Session::exec_checked`] to treat it as an error.
+    pub fn exec_output(&self, cmd: &str) -> Result<ExecOutput> {
+        exec::run(&self.inner, &self.host, cmd, self.observer.as_deref()).map_err(Error::Channel)
+    }
 
+    /// Runs `cmd` like [`Session::exec_output`], failing with [`Error::Command`] if it does not exit with status 0.
+    pub fn exec_checked(&self, cmd: &str) -> Result<ExecOutput> {
+        self.exec_output(cmd)?.check()
     }
 
-    pub fn upload_file(&self, remote_path: &str, data: &[u8]) -> io::Result<()> {
-        let mut channel = self.channel_session()?;
-        channel.exec(format!("cat > {remote_path}").as_str())?;
+    /// Writes `data` to `remote_path` through `cat`, failing with [`Error::Command`] if it cannot be written.
+    pub fn upload_file(&self, remote_path: &str, data: &[u8]) -> Result<()> {
+        tracing::debug!(host = %self.host, remote_path, len = data.len(), "upload");
+
+        let command = format!("cat > {remote_path}");
+        let start = Instant::now();
+
+        let upload = || -> std::io::Result<ExecOutput> {
+            let mut channel = self.channel_session()?;
+            channel.exec(&command)?;
+
+            channel.write_all(data)?;
+            channel.send_eof()?;
+
+            let mut stderr = Vec::new();
+            channel.stderr().read_to_end(&mut stderr)?;
+            channel.wait_close()?;
 
-        channel.write_all(data)?;
-        channel.close()?;
+            Ok(ExecOutput {
+                command     : command.clone(),
+                stdout      : Vec::new(),
+                stderr,
+                exit_status : channel.exit_status()?,
+                exit_signal : channel.exit_signal()?.exit_signal,
+                duration    : start.elapsed(),
+            })
+        };
 
+        upload().map_err(Error::Channel)?.check()?;
         Ok(())
     }
 }
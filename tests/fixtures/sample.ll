@@ -0,0 +1,27 @@
+; Source of sample.pdb, rebuilt with
+;   llc -filetype=obj sample.ll -o sample.obj
+;   rust-lld -flavor link /debug /entry:mainCRTStartup /subsystem:console /nodefaultlib /out:sample.exe /pdb:sample.pdb sample.obj
+target triple = "x86_64-pc-windows-msvc"
+@gvar = dso_local global i32 42, align 4, !dbg !20
+define dso_local i32 @foo(i32 %x) !dbg !10 {
+  %r = add i32 %x, 1, !dbg !30
+  ret i32 %r, !dbg !30
+}
+define dso_local i32 @mainCRTStartup() !dbg !15 {
+  %v = call i32 @foo(i32 1), !dbg !31
+  ret i32 %v, !dbg !31
+}
+!llvm.dbg.cu = !{!0}
+!llvm.module.flags = !{!3, !4}
+!0 = distinct !DICompileUnit(language: DW_LANG_C99, file: !1, producer: "x", isOptimized: false, runtimeVersion: 0, emissionKind: FullDebug, globals: !{!20})
+!1 = !DIFile(filename: "t.c", directory: "/tmp")
+!3 = !{i32 2, !"CodeView", i32 1}
+!4 = !{i32 2, !"Debug Info Version", i32 3}
+!5 = !DIBasicType(name: "int", size: 32, encoding: DW_ATE_signed)
+!6 = !DISubroutineType(types: !{!5, !5})
+!10 = distinct !DISubprogram(name: "foo", scope: !1, file: !1, line: 1, type: !6, unit: !0, spFlags: DISPFlagDefinition)
+!15 = distinct !DISubprogram(name: "mainCRTStartup", scope: !1, file: !1, line: 2, type: !6, unit: !0, spFlags: DISPFlagDefinition)
+!21 = distinct !DIGlobalVariable(name: "gvar", scope: !0, file: !1, line: 1, type: !5, isLocal: false, isDefinition: true)
+!20 = !DIGlobalVariableExpression(var: !21, expr: !DIExpression())
+!30 = !DILocation(line: 1, column: 1, scope: !10)
+!31 = !DILocation(line: 2, column: 1, scope: !15)